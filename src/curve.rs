@@ -0,0 +1,187 @@
+use pinocchio::program_error::ProgramError;
+
+use crate::{math, PinocchioError};
+
+/// The invariant a pool prices against, chosen at creation.
+///
+/// Encoded as a trailing field on `InitializePool` data: a tag byte followed by
+/// a little-endian 8-byte parameter. Data without the trailing bytes creates a
+/// constant-product pool, so the layout from before curves existed keeps working.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Curve {
+    /// `x * y = k`. The parameter must be zero.
+    ConstantProduct,
+    /// Curve's StableSwap invariant for pegged pairs. Higher `amplification`
+    /// keeps the price flatter around the peg.
+    StableSwap { amplification: u64 },
+    /// Balancer's weighted invariant `a^w_a * b^w_b = k`, for pools such as 80/20
+    /// that hold more value in one token. Weights are out of
+    /// [`Curve::WEIGHT_DENOMINATOR`]; the parameter is `weight_a`.
+    Weighted { weight_a: u16, weight_b: u16 },
+}
+
+impl Curve {
+    pub const LEN: usize = 1 + 8;
+
+    pub const CONSTANT_PRODUCT_TAG: u8 = 0;
+    pub const STABLE_SWAP_TAG: u8 = 1;
+    pub const WEIGHTED_TAG: u8 = 2;
+
+    /// Bounds for the StableSwap amplification coefficient `A`, as in Curve.
+    pub const MIN_AMPLIFICATION: u64 = 1;
+    pub const MAX_AMPLIFICATION: u64 = 1_000_000;
+
+    /// Weighted-pool weights sum to this, so they read as basis points.
+    pub const WEIGHT_DENOMINATOR: u16 = 10_000;
+    /// Lightest weight a token may have, as in Balancer (1%).
+    pub const MIN_WEIGHT: u16 = 100;
+
+    /// Decodes the bytes after `InitializePool`'s fixed fields: nothing, or exactly one curve.
+    pub fn parse(trailing: &[u8]) -> Result<Self, ProgramError> {
+        if trailing.is_empty() {
+            return Ok(Self::ConstantProduct);
+        }
+        if trailing.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let parameter = u64::from_le_bytes(trailing[1..9].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        Ok(Self::from_parts(trailing[0], parameter)?)
+    }
+
+    /// Rebuilds a curve from its tag and parameter, rejecting out-of-range values.
+    pub fn from_parts(tag: u8, parameter: u64) -> Result<Self, PinocchioError> {
+        match tag {
+            Self::CONSTANT_PRODUCT_TAG if parameter == 0 => Ok(Self::ConstantProduct),
+            Self::STABLE_SWAP_TAG => {
+                if !(Self::MIN_AMPLIFICATION..=Self::MAX_AMPLIFICATION).contains(&parameter) {
+                    return Err(PinocchioError::InvalidAmplification);
+                }
+                Ok(Self::StableSwap { amplification: parameter })
+            }
+            Self::WEIGHTED_TAG => {
+                let weight_a = u16::try_from(parameter).map_err(|_| PinocchioError::InvalidWeights)?;
+                let weight_b = Self::WEIGHT_DENOMINATOR
+                    .checked_sub(weight_a)
+                    .ok_or(PinocchioError::InvalidWeights)?;
+                Self::weighted(weight_a, weight_b)
+            }
+            _ => Err(PinocchioError::InvalidPoolState),
+        }
+    }
+
+    /// A weighted curve, checking that both weights are at least
+    /// [`Self::MIN_WEIGHT`] and sum to [`Self::WEIGHT_DENOMINATOR`].
+    pub fn weighted(weight_a: u16, weight_b: u16) -> Result<Self, PinocchioError> {
+        if weight_a < Self::MIN_WEIGHT
+            || weight_b < Self::MIN_WEIGHT
+            || weight_a.checked_add(weight_b) != Some(Self::WEIGHT_DENOMINATOR)
+        {
+            return Err(PinocchioError::InvalidWeights);
+        }
+        Ok(Self::Weighted { weight_a, weight_b })
+    }
+
+    pub fn tag(&self) -> u8 {
+        match self {
+            Self::ConstantProduct => Self::CONSTANT_PRODUCT_TAG,
+            Self::StableSwap { .. } => Self::STABLE_SWAP_TAG,
+            Self::Weighted { .. } => Self::WEIGHTED_TAG,
+        }
+    }
+
+    pub fn parameter(&self) -> u64 {
+        match *self {
+            Self::ConstantProduct => 0,
+            Self::StableSwap { amplification } => amplification,
+            Self::Weighted { weight_a, .. } => weight_a as u64,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0] = self.tag();
+        bytes[1..].copy_from_slice(&self.parameter().to_le_bytes());
+        bytes
+    }
+
+    /// `(weight_in, weight_out)` of a weighted curve for a swap in the given direction.
+    fn weights(weight_a: u16, weight_b: u16, a_to_b: bool) -> (u16, u16) {
+        if a_to_b { (weight_a, weight_b) } else { (weight_b, weight_a) }
+    }
+
+    /// Output for an exact `amount_in`, fee taken from the input. Returns `(amount_out, fee)`.
+    ///
+    /// `a_to_b` says which reserve is which; only weighted curves are asymmetric.
+    pub fn get_amount_out(
+        &self,
+        amount_in: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        a_to_b: bool,
+        fee_rate: u16,
+    ) -> Result<(u64, u64), PinocchioError> {
+        match *self {
+            Self::ConstantProduct => math::get_amount_out(amount_in, reserve_in, reserve_out, fee_rate),
+            Self::StableSwap { amplification } => {
+                math::stable_get_amount_out(amount_in, reserve_in, reserve_out, amplification, fee_rate)
+            }
+            Self::Weighted { weight_a, weight_b } => {
+                let (weight_in, weight_out) = Self::weights(weight_a, weight_b, a_to_b);
+                math::weighted_get_amount_out(amount_in, reserve_in, reserve_out, weight_in, weight_out, fee_rate)
+            }
+        }
+    }
+
+    /// Input needed to receive exactly `amount_out`, rounded in the pool's favour.
+    pub fn get_amount_in(
+        &self,
+        amount_out: u64,
+        reserve_in: u64,
+        reserve_out: u64,
+        a_to_b: bool,
+        fee_rate: u16,
+    ) -> Result<u64, PinocchioError> {
+        match *self {
+            Self::ConstantProduct => math::get_amount_in(amount_out, reserve_in, reserve_out, fee_rate),
+            Self::StableSwap { amplification } => {
+                math::stable_get_amount_in(amount_out, reserve_in, reserve_out, amplification, fee_rate)
+            }
+            Self::Weighted { weight_a, weight_b } => {
+                let (weight_in, weight_out) = Self::weights(weight_a, weight_b, a_to_b);
+                math::weighted_get_amount_in(amount_out, reserve_in, reserve_out, weight_in, weight_out, fee_rate)
+            }
+        }
+    }
+
+    /// What `amount_in` buys at the marginal price, before fees and price impact.
+    pub fn spot_amount_out(
+        &self,
+        amount_in: u128,
+        reserve_in: u64,
+        reserve_out: u64,
+        a_to_b: bool,
+    ) -> Result<u128, PinocchioError> {
+        match *self {
+            Self::ConstantProduct => math::mul_div(amount_in, reserve_out as u128, reserve_in as u128)
+                .ok_or(PinocchioError::InsufficientLiquidity),
+            Self::StableSwap { amplification } => {
+                math::stable_spot_amount_out(amount_in, reserve_in, reserve_out, amplification)
+            }
+            Self::Weighted { weight_a, weight_b } => {
+                let (weight_in, weight_out) = Self::weights(weight_a, weight_b, a_to_b);
+                math::weighted_spot_amount_out(amount_in, reserve_in, reserve_out, weight_in, weight_out)
+            }
+        }
+    }
+
+    /// Marginal price of the base token in units of the quote token, as Q64.64
+    /// fixed point. For constant product this is [`math::q64_price`]. `base_is_a`
+    /// says which token is the base.
+    pub fn q64_price(&self, reserve_base: u64, reserve_quote: u64, base_is_a: bool) -> Result<u128, PinocchioError> {
+        match self {
+            Self::ConstantProduct => Ok(math::q64_price(reserve_base, reserve_quote)),
+            _ => self.spot_amount_out(1 << math::PRICE_FRACTIONAL_BITS, reserve_base, reserve_quote, base_is_a),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use pinocchio::{program_error::ProgramError, sysvars::clock::Clock};
+
+use crate::PinocchioError;
+
+/// Optional expiry carried as a trailing field on user-facing instruction data.
+///
+/// Encoded as a tag byte followed by a little-endian 8-byte value:
+/// `0` for a unix timestamp (`i64`), `1` for a maximum slot (`u64`).
+/// Instruction data without the trailing bytes decodes to no deadline, so
+/// the layouts from before deadlines existed keep working.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deadline {
+    UnixTimestamp(i64),
+    Slot(u64),
+}
+
+impl Deadline {
+    pub const LEN: usize = 1 + 8;
+
+    pub const UNIX_TIMESTAMP_TAG: u8 = 0;
+    pub const SLOT_TAG: u8 = 1;
+
+    /// Decodes the bytes after an instruction's fixed fields: nothing, or exactly one deadline.
+    pub fn parse(trailing: &[u8]) -> Result<Option<Self>, ProgramError> {
+        if trailing.is_empty() {
+            return Ok(None);
+        }
+        if trailing.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let value: [u8; 8] = trailing[1..9].try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+        match trailing[0] {
+            Self::UNIX_TIMESTAMP_TAG => Ok(Some(Self::UnixTimestamp(i64::from_le_bytes(value)))),
+            Self::SLOT_TAG => Ok(Some(Self::Slot(u64::from_le_bytes(value)))),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        match self {
+            Self::UnixTimestamp(timestamp) => {
+                bytes[0] = Self::UNIX_TIMESTAMP_TAG;
+                bytes[1..].copy_from_slice(&timestamp.to_le_bytes());
+            }
+            Self::Slot(slot) => {
+                bytes[0] = Self::SLOT_TAG;
+                bytes[1..].copy_from_slice(&slot.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// The deadline is inclusive: a transaction landing exactly at it still goes through.
+    pub fn check(&self, clock: &Clock) -> Result<(), PinocchioError> {
+        let expired = match *self {
+            Self::UnixTimestamp(timestamp) => clock.unix_timestamp > timestamp,
+            Self::Slot(slot) => clock.slot > slot,
+        };
+
+        if expired {
+            return Err(PinocchioError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    /// Rejects the instruction if `deadline` is set and has passed at `clock`.
+    pub fn enforce(deadline: Option<Self>, clock: &Clock) -> Result<(), ProgramError> {
+        if let Some(deadline) = deadline {
+            deadline.check(clock)?;
+        }
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ impl From<PinocchioError> for ProgramError{
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PinocchioError{
     InvalidAmount = 0x0,
     SlippageExceeded = 0x1,
@@ -18,6 +19,16 @@ pub enum PinocchioError{
     Unauthorized = 0x7,
     InvalidPoolState = 0x8,
     IdenticalMints = 0x9,
+    SwapDisabled = 0xa,
+    AddLiquidityDisabled = 0xb,
+    RemoveLiquidityDisabled = 0xc,
+    UnsortedMints = 0xd,
+    DeadlineExceeded = 0xe,
+    ObservationTooOld = 0xf,
+    InvalidAmplification = 0x10,
+    CurveNotConverged = 0x11,
+    InvalidRamp = 0x12,
+    InvalidWeights = 0x13,
 }
 
 impl PinocchioError {
@@ -33,6 +44,16 @@ impl PinocchioError {
             PinocchioError::Unauthorized => "Unauthorized access",
             PinocchioError::InvalidPoolState => "The pool is in an invalid state",
             PinocchioError::IdenticalMints => "Cannot swap between identical mints",
+            PinocchioError::SwapDisabled => "Swaps are disabled for this pool",
+            PinocchioError::AddLiquidityDisabled => "Deposits are disabled for this pool",
+            PinocchioError::RemoveLiquidityDisabled => "Withdrawals are disabled for this pool",
+            PinocchioError::UnsortedMints => "Pool mints must be passed in canonical (ascending) order",
+            PinocchioError::DeadlineExceeded => "The transaction deadline has passed",
+            PinocchioError::ObservationTooOld => "The requested time is older than the oldest oracle observation",
+            PinocchioError::InvalidAmplification => "Amplification coefficient is out of range",
+            PinocchioError::CurveNotConverged => "The curve's invariant did not converge",
+            PinocchioError::InvalidRamp => "Amplification ramps must last and be spaced at least the minimum ramp duration",
+            PinocchioError::InvalidWeights => "Pool weights must each be at least 1% and sum to 100%",
         }
     }
 }
\ No newline at end of file
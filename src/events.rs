@@ -0,0 +1,528 @@
+use pinocchio::{log::sol_log_data, pubkey::Pubkey};
+
+use crate::Curve;
+
+/// Binary events logged with `sol_log_data`, one per state change an indexer
+/// cares about. They show up in transaction logs as `Program data: <base64>`.
+///
+/// Every event starts with a two-byte discriminator, `[kind, version]`, followed
+/// by its fields in declaration order: pubkeys as 32 raw bytes, integers little
+/// endian, booleans as one byte. A layout change bumps [`EVENT_VERSION`] so
+/// decoders can tell old logs from new ones.
+pub const EVENT_VERSION: u8 = 1;
+
+pub const DISCRIMINATOR_LEN: usize = 2;
+
+/// Largest encoded event, discriminator included.
+pub const MAX_EVENT_LEN: usize = DISCRIMINATOR_LEN + 5 * 32 + 2 + Curve::LEN;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Swap = 0,
+    Deposit = 1,
+    Withdraw = 2,
+    PoolCreated = 3,
+    FeeChanged = 4,
+    AmplificationRamp = 5,
+    AuthorityChanged = 6,
+    PoolStatusChanged = 7,
+    ProtocolFeesWithdrawn = 8,
+    ObservationCardinalityChanged = 9,
+}
+
+impl EventKind {
+    pub const fn discriminator(self) -> [u8; DISCRIMINATOR_LEN] {
+        [self as u8, EVENT_VERSION]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventDecodeError {
+    UnknownKind(u8),
+    UnsupportedVersion(u8),
+    InvalidLength,
+}
+
+/// An encoded event, ready to log.
+pub struct EventBytes {
+    buffer: [u8; MAX_EVENT_LEN],
+    len: usize,
+}
+
+impl EventBytes {
+    fn new(kind: EventKind) -> Self {
+        let mut bytes = Self { buffer: [0; MAX_EVENT_LEN], len: DISCRIMINATOR_LEN };
+        bytes.buffer[..DISCRIMINATOR_LEN].copy_from_slice(&kind.discriminator());
+        bytes
+    }
+
+    fn put(&mut self, value: &[u8]) -> &mut Self {
+        self.buffer[self.len..self.len + value.len()].copy_from_slice(value);
+        self.len += value.len();
+        self
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+/// Reads an event's fields back in order.
+struct EventReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> EventReader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], EventDecodeError> {
+        let (value, rest) = self.data.split_at_checked(N).ok_or(EventDecodeError::InvalidLength)?;
+        self.data = rest;
+        Ok(value.try_into().unwrap())
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, EventDecodeError> {
+        self.take::<32>()
+    }
+
+    fn u64(&mut self) -> Result<u64, EventDecodeError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, EventDecodeError> {
+        Ok(i64::from_le_bytes(self.take()?))
+    }
+
+    fn u8(&mut self) -> Result<u8, EventDecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, EventDecodeError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn bool(&mut self) -> Result<bool, EventDecodeError> {
+        Ok(self.take::<1>()?[0] != 0)
+    }
+
+    fn finish<T>(self, event: T) -> Result<T, EventDecodeError> {
+        if !self.data.is_empty() {
+            return Err(EventDecodeError::InvalidLength);
+        }
+        Ok(event)
+    }
+}
+
+/// A token swap, including each hop of a routed swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub a_to_b: bool,
+    /// Sent by the user, transfer fees included.
+    pub amount_in: u64,
+    /// Sent by the output vault.
+    pub amount_out: u64,
+    pub fee: u64,
+    pub protocol_fee: u64,
+}
+
+/// Liquidity added, from either side or both. LP minted to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub lp_tokens: u64,
+}
+
+/// Liquidity removed to either side or both. LP burned by the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithdrawEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub lp_tokens: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolCreatedEvent {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub fee_rate: u16,
+    /// The pool's curve as [`Curve::to_bytes`] encodes it; [`Curve::parse`] reads it back.
+    pub curve: [u8; Curve::LEN],
+}
+
+/// The swap fee or the protocol's share of it changed. Both settings are
+/// reported, before and after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeChangedEvent {
+    pub pool: Pubkey,
+    pub old_fee_rate: u16,
+    pub new_fee_rate: u16,
+    pub old_protocol_fee_share: u16,
+    pub new_protocol_fee_share: u16,
+}
+
+/// A StableSwap amplification ramp started or was stopped. `A` moves linearly
+/// from `start_amplification` at `start_timestamp` to `target_amplification` at
+/// `end_timestamp`; a stopped ramp reports the same value and time twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmplificationRampEvent {
+    pub pool: Pubkey,
+    pub start_amplification: u64,
+    pub target_amplification: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+/// The pool's authority or pending authority changed: a transfer was proposed,
+/// accepted or cancelled. Both keys are reported, before and after; the default
+/// key means no transfer is pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthorityChangedEvent {
+    pub pool: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub old_pending_authority: Pubkey,
+    pub new_pending_authority: Pubkey,
+}
+
+/// The pool's status bits changed. See the `Pool::*_DISABLED` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStatusChangedEvent {
+    pub pool: Pubkey,
+    pub old_status: u8,
+    pub new_status: u8,
+}
+
+/// Accrued protocol fees sent from the vaults to the treasury accounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolFeesWithdrawnEvent {
+    pub pool: Pubkey,
+    pub treasury_token_a: Pubkey,
+    pub treasury_token_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+}
+
+/// The oracle's target ring buffer size changed. A newly created observations
+/// account reports `0` as the old size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservationCardinalityChangedEvent {
+    pub pool: Pubkey,
+    pub old_cardinality_next: u16,
+    pub new_cardinality_next: u16,
+}
+
+impl SwapEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::Swap);
+        bytes
+            .put(&self.pool)
+            .put(&self.user)
+            .put(&[self.a_to_b as u8])
+            .put(&self.amount_in.to_le_bytes())
+            .put(&self.amount_out.to_le_bytes())
+            .put(&self.fee.to_le_bytes())
+            .put(&self.protocol_fee.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            user: reader.pubkey()?,
+            a_to_b: reader.bool()?,
+            amount_in: reader.u64()?,
+            amount_out: reader.u64()?,
+            fee: reader.u64()?,
+            protocol_fee: reader.u64()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl DepositEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::Deposit);
+        bytes
+            .put(&self.pool)
+            .put(&self.user)
+            .put(&self.amount_a.to_le_bytes())
+            .put(&self.amount_b.to_le_bytes())
+            .put(&self.lp_tokens.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            user: reader.pubkey()?,
+            amount_a: reader.u64()?,
+            amount_b: reader.u64()?,
+            lp_tokens: reader.u64()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl WithdrawEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::Withdraw);
+        bytes
+            .put(&self.pool)
+            .put(&self.user)
+            .put(&self.amount_a.to_le_bytes())
+            .put(&self.amount_b.to_le_bytes())
+            .put(&self.lp_tokens.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            user: reader.pubkey()?,
+            amount_a: reader.u64()?,
+            amount_b: reader.u64()?,
+            lp_tokens: reader.u64()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl PoolCreatedEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::PoolCreated);
+        bytes
+            .put(&self.pool)
+            .put(&self.authority)
+            .put(&self.token_a_mint)
+            .put(&self.token_b_mint)
+            .put(&self.lp_mint)
+            .put(&self.fee_rate.to_le_bytes())
+            .put(&self.curve);
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            authority: reader.pubkey()?,
+            token_a_mint: reader.pubkey()?,
+            token_b_mint: reader.pubkey()?,
+            lp_mint: reader.pubkey()?,
+            fee_rate: reader.u16()?,
+            curve: reader.take()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl FeeChangedEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::FeeChanged);
+        bytes
+            .put(&self.pool)
+            .put(&self.old_fee_rate.to_le_bytes())
+            .put(&self.new_fee_rate.to_le_bytes())
+            .put(&self.old_protocol_fee_share.to_le_bytes())
+            .put(&self.new_protocol_fee_share.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            old_fee_rate: reader.u16()?,
+            new_fee_rate: reader.u16()?,
+            old_protocol_fee_share: reader.u16()?,
+            new_protocol_fee_share: reader.u16()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl AmplificationRampEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::AmplificationRamp);
+        bytes
+            .put(&self.pool)
+            .put(&self.start_amplification.to_le_bytes())
+            .put(&self.target_amplification.to_le_bytes())
+            .put(&self.start_timestamp.to_le_bytes())
+            .put(&self.end_timestamp.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            start_amplification: reader.u64()?,
+            target_amplification: reader.u64()?,
+            start_timestamp: reader.i64()?,
+            end_timestamp: reader.i64()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl AuthorityChangedEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::AuthorityChanged);
+        bytes
+            .put(&self.pool)
+            .put(&self.old_authority)
+            .put(&self.new_authority)
+            .put(&self.old_pending_authority)
+            .put(&self.new_pending_authority);
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            old_authority: reader.pubkey()?,
+            new_authority: reader.pubkey()?,
+            old_pending_authority: reader.pubkey()?,
+            new_pending_authority: reader.pubkey()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl PoolStatusChangedEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::PoolStatusChanged);
+        bytes
+            .put(&self.pool)
+            .put(&[self.old_status])
+            .put(&[self.new_status]);
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            old_status: reader.u8()?,
+            new_status: reader.u8()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl ProtocolFeesWithdrawnEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::ProtocolFeesWithdrawn);
+        bytes
+            .put(&self.pool)
+            .put(&self.treasury_token_a)
+            .put(&self.treasury_token_b)
+            .put(&self.amount_a.to_le_bytes())
+            .put(&self.amount_b.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            treasury_token_a: reader.pubkey()?,
+            treasury_token_b: reader.pubkey()?,
+            amount_a: reader.u64()?,
+            amount_b: reader.u64()?,
+        };
+        reader.finish(event)
+    }
+}
+
+impl ObservationCardinalityChangedEvent {
+    pub fn to_bytes(&self) -> EventBytes {
+        let mut bytes = EventBytes::new(EventKind::ObservationCardinalityChanged);
+        bytes
+            .put(&self.pool)
+            .put(&self.old_cardinality_next.to_le_bytes())
+            .put(&self.new_cardinality_next.to_le_bytes());
+        bytes
+    }
+
+    fn read(mut reader: EventReader) -> Result<Self, EventDecodeError> {
+        let event = Self {
+            pool: reader.pubkey()?,
+            old_cardinality_next: reader.u16()?,
+            new_cardinality_next: reader.u16()?,
+        };
+        reader.finish(event)
+    }
+}
+
+/// Any event this program logs, decoded from the bytes of a `Program data:` log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Swap(SwapEvent),
+    Deposit(DepositEvent),
+    Withdraw(WithdrawEvent),
+    PoolCreated(PoolCreatedEvent),
+    FeeChanged(FeeChangedEvent),
+    AmplificationRamp(AmplificationRampEvent),
+    AuthorityChanged(AuthorityChangedEvent),
+    PoolStatusChanged(PoolStatusChangedEvent),
+    ProtocolFeesWithdrawn(ProtocolFeesWithdrawnEvent),
+    ObservationCardinalityChanged(ObservationCardinalityChangedEvent),
+}
+
+impl Event {
+    pub fn decode(data: &[u8]) -> Result<Self, EventDecodeError> {
+        let [kind, version, fields @ ..] = data else {
+            return Err(EventDecodeError::InvalidLength);
+        };
+        if *version != EVENT_VERSION {
+            return Err(EventDecodeError::UnsupportedVersion(*version));
+        }
+
+        let reader = EventReader { data: fields };
+        match *kind {
+            k if k == EventKind::Swap as u8 => SwapEvent::read(reader).map(Self::Swap),
+            k if k == EventKind::Deposit as u8 => DepositEvent::read(reader).map(Self::Deposit),
+            k if k == EventKind::Withdraw as u8 => WithdrawEvent::read(reader).map(Self::Withdraw),
+            k if k == EventKind::PoolCreated as u8 => PoolCreatedEvent::read(reader).map(Self::PoolCreated),
+            k if k == EventKind::FeeChanged as u8 => FeeChangedEvent::read(reader).map(Self::FeeChanged),
+            k if k == EventKind::AmplificationRamp as u8 => {
+                AmplificationRampEvent::read(reader).map(Self::AmplificationRamp)
+            }
+            k if k == EventKind::AuthorityChanged as u8 => AuthorityChangedEvent::read(reader).map(Self::AuthorityChanged),
+            k if k == EventKind::PoolStatusChanged as u8 => PoolStatusChangedEvent::read(reader).map(Self::PoolStatusChanged),
+            k if k == EventKind::ProtocolFeesWithdrawn as u8 => {
+                ProtocolFeesWithdrawnEvent::read(reader).map(Self::ProtocolFeesWithdrawn)
+            }
+            k if k == EventKind::ObservationCardinalityChanged as u8 => {
+                ObservationCardinalityChangedEvent::read(reader).map(Self::ObservationCardinalityChanged)
+            }
+            k => Err(EventDecodeError::UnknownKind(k)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> EventBytes {
+        match self {
+            Self::Swap(event) => event.to_bytes(),
+            Self::Deposit(event) => event.to_bytes(),
+            Self::Withdraw(event) => event.to_bytes(),
+            Self::PoolCreated(event) => event.to_bytes(),
+            Self::FeeChanged(event) => event.to_bytes(),
+            Self::AmplificationRamp(event) => event.to_bytes(),
+            Self::AuthorityChanged(event) => event.to_bytes(),
+            Self::PoolStatusChanged(event) => event.to_bytes(),
+            Self::ProtocolFeesWithdrawn(event) => event.to_bytes(),
+            Self::ObservationCardinalityChanged(event) => event.to_bytes(),
+        }
+    }
+
+    /// Logs the event with `sol_log_data`.
+    pub fn emit(&self) {
+        sol_log_data(&[self.to_bytes().as_slice()]);
+    }
+}
@@ -0,0 +1,74 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+
+use crate::{events::{AuthorityChangedEvent, Event}, PinocchioError, Pool, SignerAccount};
+
+pub struct AcceptAuthorityAccounts<'a> {
+    pub pending_authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for AcceptAuthorityAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            pending_authority,
+            pool,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(pending_authority)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { pending_authority, pool })
+    }
+}
+
+pub struct AcceptAuthority<'a> {
+    pub accounts: AcceptAuthorityAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for AcceptAuthority<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, _data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = AcceptAuthorityAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> AcceptAuthority<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &6;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if !pool.has_pending_authority() {
+            return Err(PinocchioError::InvalidPoolState.into());
+        }
+        if pool.pending_authority != *self.accounts.pending_authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+
+        let old_authority = pool.authority;
+        pool.authority = pool.pending_authority;
+        pool.pending_authority = Pubkey::default();
+
+        Event::AuthorityChanged(AuthorityChangedEvent {
+            pool: *self.accounts.pool.key(),
+            old_authority,
+            new_authority: pool.authority,
+            old_pending_authority: pool.authority,
+            new_pending_authority: pool.pending_authority,
+        }).emit();
+
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
-use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::find_program_address, ProgramResult};
-use pinocchio_token::instructions::{MintTo, Transfer};
-use crate::{AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, MintInterface, SignerAccount, PinocchioError};
+use pinocchio::{account_info::AccountInfo, instruction::Signer, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+use pinocchio_token::instructions::MintTo;
+use crate::{events::{DepositEvent, Event}, math, Curve, token_interface::{active_transfer_fee, TokenTransfer, TransferFee}, Deadline, AssociatedTokenAccount, AssociatedTokenAccountInit, MintInterface, PoolAccount, PoolAccountCheck, SignerAccount, PinocchioError, Pool, TokenProgram, TokenProgramCheck};
 
 pub struct AddLiquidityAccounts<'a> {
     pub user: &'a AccountInfo,
@@ -19,6 +19,9 @@ pub struct AddLiquidityAccounts<'a> {
     pub token_a_mint: &'a AccountInfo,
     pub token_b_mint: &'a AccountInfo,
 
+    pub token_a_program: &'a AccountInfo,
+    pub token_b_program: &'a AccountInfo,
+
     pub token_program: &'a AccountInfo,
     pub associated_token_program: &'a AccountInfo,
     pub system_program: &'a AccountInfo,
@@ -39,6 +42,8 @@ impl<'a>TryFrom<&'a [AccountInfo]> for AddLiquidityAccounts<'a> {
             user_lp_token,
             token_a_mint,
             token_b_mint,
+            token_a_program,
+            token_b_program,
             token_program,
             associated_token_program,
             system_program,
@@ -50,25 +55,12 @@ impl<'a>TryFrom<&'a [AccountInfo]> for AddLiquidityAccounts<'a> {
         <&AccountInfo as SignerAccount>::check(user)?;
         <&AccountInfo as MintInterface>::check(token_a_mint)?;
         <&AccountInfo as MintInterface>::check(token_b_mint)?;
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
         
-        AssociatedTokenAccount::check(token_a_vault, pool, token_a_mint)?;
-        AssociatedTokenAccount::check(token_b_vault, pool, token_b_mint)?;
-
-        let (expected_lp_mint, _) = find_program_address(
-            &[b"lp_mint", pool.key().as_ref()], 
-            &crate::ID
-        );
-        if lp_mint.key() != &expected_lp_mint {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        
-        let (expected_pool, _) = find_program_address(
-            &[b"pool", token_a_mint.key().as_ref(), token_b_mint.key().as_ref()], 
-            &crate::ID
-        );
-        if pool.key() != &expected_pool {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+        PoolAccount::check_lp_mint(pool, lp_mint)?;
 
         if token_a_mint.key() == token_b_mint.key() {
             return Err(PinocchioError::IdenticalMints.into());
@@ -85,6 +77,8 @@ impl<'a>TryFrom<&'a [AccountInfo]> for AddLiquidityAccounts<'a> {
             user_lp_token,
             token_a_mint,
             token_b_mint,
+            token_a_program,
+            token_b_program,
             token_program,
             associated_token_program,
             system_program
@@ -93,9 +87,12 @@ impl<'a>TryFrom<&'a [AccountInfo]> for AddLiquidityAccounts<'a> {
 }
 
 pub struct AddLiquidityData {
-    pub amount_a: u64,
-    pub amount_b: u64,
+    /// Most of token A the depositor will pay; only the ratio-matched amount is taken.
+    pub max_amount_a: u64,
+    /// Most of token B the depositor will pay; only the ratio-matched amount is taken.
+    pub max_amount_b: u64,
     pub min_lp_amount: u64,
+    pub deadline: Option<Deadline>,
 }
 
 impl TryFrom<&[u8]> for AddLiquidityData {
@@ -106,18 +103,20 @@ impl TryFrom<&[u8]> for AddLiquidityData {
             return Err(ProgramError::InvalidInstructionData);
         }
 
-        let amount_a = u64::from_le_bytes([data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7]]);
-        let amount_b = u64::from_le_bytes([data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15]]);
+        let max_amount_a = u64::from_le_bytes([data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7]]);
+        let max_amount_b = u64::from_le_bytes([data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15]]);
         let min_lp_amount = u64::from_le_bytes([data[16], data[17], data[18], data[19], data[20], data[21], data[22], data[23]]);
+        let deadline = Deadline::parse(&data[24..])?;
 
-        if amount_a == 0 || amount_b == 0 {
+        if max_amount_a == 0 || max_amount_b == 0 {
             return Err(PinocchioError::InvalidAmount.into());
         }
 
         Ok(Self {
-            amount_a,
-            amount_b,
+            max_amount_a,
+            max_amount_b,
             min_lp_amount,
+            deadline,
         })
     }
 }
@@ -125,8 +124,6 @@ impl TryFrom<&[u8]> for AddLiquidityData {
 pub struct AddLiquidity<'a> {
     pub accounts: AddLiquidityAccounts<'a>,
     pub data: AddLiquidityData,
-    pub pool_bump: u8,
-    pub lp_mint_bump: u8,
 }
 
 impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for AddLiquidity<'a> {
@@ -142,7 +139,7 @@ impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for AddLiquidity<'a> {
             accounts.user, 
             accounts.user, 
             accounts.system_program, 
-            accounts.token_program
+            accounts.token_a_program
         )?;
         AssociatedTokenAccount::init_if_needed(
             accounts.user_token_b, 
@@ -150,7 +147,7 @@ impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for AddLiquidity<'a> {
             accounts.user, 
             accounts.user, 
             accounts.system_program, 
-            accounts.token_program
+            accounts.token_b_program
         )?;
         AssociatedTokenAccount::init_if_needed(
             accounts.user_lp_token, 
@@ -161,117 +158,201 @@ impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for AddLiquidity<'a> {
             accounts.token_program
         )?;
 
-        let (_, pool_bump) = find_program_address(
-            &[b"pool", accounts.token_a_mint.key().as_ref(), accounts.token_b_mint.key().as_ref()], 
-            &crate::ID
-        );
-        let (_, lp_mint_bump) = find_program_address(
-            &[b"lp_mint", accounts.pool.key().as_ref()], 
-            &crate::ID
-        );
-
         Ok(Self {
             accounts,
             data,
-            pool_bump,
-            lp_mint_bump,
         })
     }
 }
 
+/// A deposit priced against the current reserves. `AddLiquidity` executes it and
+/// `QuoteAddLiquidity` returns it, so both run the same math.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddLiquidityQuote {
+    /// LP minted to the depositor (the first deposit also locks `MINIMUM_LIQUIDITY`).
+    pub lp_tokens: u64,
+    /// Sent by the depositor, transfer fees included.
+    pub amount_a: u64,
+    pub amount_b: u64,
+}
+
+impl AddLiquidityQuote {
+    pub const LEN: usize = 8 + 8 + 8;
+
+    /// The first deposit is taken in full and sets the price. Later deposits are
+    /// matched to the reserve ratio on every curve; the surplus on the other side
+    /// stays with the depositor. Unbalanced deposits go through `DepositSingle`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pool: &Pool,
+        now: i64,
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        transfer_fee_a: TransferFee,
+        transfer_fee_b: TransferFee,
+    ) -> Result<Self, ProgramError> {
+        // LP is priced on what actually reaches the vaults after transfer fees.
+        let max_amount_a = transfer_fee_a.net_amount(max_amount_a)?;
+        let max_amount_b = transfer_fee_b.net_amount(max_amount_b)?;
+
+        let curve = pool.curve_at(now)?;
+
+        let (lp_tokens, amount_a, amount_b) = if lp_supply == 0 {
+            // The first deposit sets the price, so it's taken in full.
+            if max_amount_a == 0 || max_amount_b == 0 {
+                return Err(PinocchioError::InvalidAmount.into());
+            }
+            let initial_liquidity = match curve {
+                Curve::ConstantProduct => math::sqrt(max_amount_a as u128 * max_amount_b as u128),
+                Curve::StableSwap { amplification } => {
+                    math::stable_invariant(max_amount_a, max_amount_b, amplification)?
+                }
+                Curve::Weighted { weight_a, weight_b } => {
+                    math::weighted_invariant(max_amount_a, max_amount_b, weight_a, weight_b)?
+                }
+            };
+            let initial_liquidity = u64::try_from(initial_liquidity)
+                .map_err(|_| PinocchioError::MathOverflow)?;
+            if initial_liquidity <= Pool::MINIMUM_LIQUIDITY {
+                return Err(PinocchioError::InsufficientLiquidity.into());
+            }
+
+            (initial_liquidity - Pool::MINIMUM_LIQUIDITY, max_amount_a, max_amount_b)
+        } else {
+            // Every curve's invariant scales with its reserves, so a deposit in the
+            // reserve ratio grows it by the same factor as the LP supply. For weighted
+            // pools that ratio already carries the weights: the matched amounts split
+            // the deposit's value `weight_a : weight_b`.
+            math::proportional_deposit(max_amount_a, max_amount_b, reserve_a, reserve_b, lp_supply)?
+        };
+
+        if lp_tokens == 0 {
+            return Err(PinocchioError::InvalidAmount.into());
+        }
+
+        // Sending the gross-up of a net amount never exceeds the maximum it came from.
+        Ok(Self {
+            lp_tokens,
+            amount_a: transfer_fee_a.gross_amount(amount_a)?,
+            amount_b: transfer_fee_b.gross_amount(amount_b)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.lp_tokens.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.amount_a.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.amount_b.to_le_bytes());
+        bytes
+    }
+}
+
 impl<'a> AddLiquidity<'a> {
     pub const DISCRIMINATOR: &'a u8 = &1;
 
     pub fn process(&mut self) -> ProgramResult {
-        let token_a_vault_data = self.accounts.token_a_vault.try_borrow_data()?;
-        let token_a_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_a_vault_data) };
-        
-        let token_b_vault_data = self.accounts.token_b_vault.try_borrow_data()?;
-        let token_b_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_b_vault_data) };
+        self.process_at(&Clock::get()?)
+    }
 
-        let lp_mint_data = self.accounts.lp_mint.try_borrow_data()?;
-        let lp_mint = unsafe { pinocchio_token::state::Mint::from_bytes(&lp_mint_data) };
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
 
-        if token_a_vault.owner() != self.accounts.pool.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if token_b_vault.owner() != self.accounts.pool.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if lp_mint.mint_authority() != Some(self.accounts.pool.key()) {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let transfer_fee_a = active_transfer_fee(self.accounts.token_a_mint, clock)?;
+        let transfer_fee_b = active_transfer_fee(self.accounts.token_b_mint, clock)?;
 
-        if token_a_vault.mint() != self.accounts.token_a_mint.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if token_b_vault.mint() != self.accounts.token_b_mint.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let (lp_tokens_to_mint, amount_a, amount_b, pool_bump) = {
+            let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+            let pool = Pool::load_mut(&mut pool_data)?;
 
-        let reserve_a = token_a_vault.amount();
-        let reserve_b = token_b_vault.amount();
+            if pool.is_disabled(Pool::ADD_LIQUIDITY_DISABLED) {
+                return Err(PinocchioError::AddLiquidityDisabled.into());
+            }
 
-        let lp_tokens_to_mint = if reserve_a == 0 && reserve_b == 0 {
-            let product = (self.data.amount_a as f64) * (self.data.amount_b as f64);
-            if product <= 0.0 {
-                return Err(PinocchioError::InvalidAmount.into());
+            let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+                self.accounts.pool,
+                self.accounts.token_a_vault,
+                self.accounts.token_b_vault,
+                self.accounts.token_a_mint,
+                self.accounts.token_b_mint,
+            )?;
+
+            let lp_mint_data = self.accounts.lp_mint.try_borrow_data()?;
+            let lp_mint = unsafe { pinocchio_token::state::Mint::from_bytes(&lp_mint_data) };
+
+            if lp_mint.mint_authority() != Some(self.accounts.pool.key()) {
+                return Err(ProgramError::InvalidAccountData);
             }
-            let sqrt_result = product.sqrt() as u64;
-            if sqrt_result < 1000 {
-                return Err(PinocchioError::InsufficientLiquidity.into());
+
+            let (reserve_a, reserve_b) = pool.pricing_reserves(vault_a_amount, vault_b_amount)?;
+            pool.update_price_accumulators(reserve_a, reserve_b, clock.unix_timestamp)?;
+
+            let lp_supply = pool.total_lp_supply(lp_mint.supply())?;
+
+            let quote = AddLiquidityQuote::new(
+                pool,
+                clock.unix_timestamp,
+                reserve_a,
+                reserve_b,
+                lp_supply,
+                self.data.max_amount_a,
+                self.data.max_amount_b,
+                transfer_fee_a,
+                transfer_fee_b,
+            )?;
+
+            if quote.lp_tokens < self.data.min_lp_amount {
+                return Err(PinocchioError::SlippageExceeded.into());
             }
-            sqrt_result
-        } else {
-            let lp_supply = lp_mint.supply();
-            
-            if reserve_a == 0 || reserve_b == 0 || lp_supply == 0 {
-                return Err(PinocchioError::InvalidPoolState.into());
+
+            if lp_supply == 0 {
+                // Locked forever, so the share price can't be inflated from a near-zero supply.
+                pool.locked_liquidity = Pool::MINIMUM_LIQUIDITY;
             }
 
-            let lp_from_a = (self.data.amount_a as u128)
-                .checked_mul(lp_supply as u128)
-                .ok_or(PinocchioError::MathOverflow)?
-                .checked_div(reserve_a as u128)
-                .ok_or(PinocchioError::MathOverflow)? as u64;
-                
-            let lp_from_b = (self.data.amount_b as u128)
-                .checked_mul(lp_supply as u128)
-                .ok_or(PinocchioError::MathOverflow)?
-                .checked_div(reserve_b as u128)
-                .ok_or(PinocchioError::MathOverflow)? as u64;
-                
-            std::cmp::min(lp_from_a, lp_from_b)
+            (quote.lp_tokens, quote.amount_a, quote.amount_b, pool.bump)
         };
 
-        if lp_tokens_to_mint == 0 {
-            return Err(PinocchioError::InvalidAmount.into());
-        }
-        
-        if lp_tokens_to_mint < self.data.min_lp_amount {
-            return Err(PinocchioError::SlippageExceeded.into());
-        }
-
-        Transfer {
+        TokenTransfer {
             from: self.accounts.user_token_a,
+            mint: self.accounts.token_a_mint,
             to: self.accounts.token_a_vault,
             authority: self.accounts.user,
-            amount: self.data.amount_a,
+            token_program: self.accounts.token_a_program,
+            amount: amount_a,
         }.invoke()?;
 
-        Transfer {
+        TokenTransfer {
             from: self.accounts.user_token_b,
+            mint: self.accounts.token_b_mint,
             to: self.accounts.token_b_vault,
             authority: self.accounts.user,
-            amount: self.data.amount_b,
+            token_program: self.accounts.token_b_program,
+            amount: amount_b,
         }.invoke()?;
 
+        let pool_bump_binding = [pool_bump];
+        let seeds = PoolAccount::signer_seeds(self.accounts.token_a_mint, self.accounts.token_b_mint, &pool_bump_binding);
+
+        let signers = [Signer::from(&seeds)];
+
         MintTo {
             mint: self.accounts.lp_mint,
             amount: lp_tokens_to_mint,
             mint_authority: self.accounts.pool,
             account: self.accounts.user_lp_token,
-        }.invoke()?;
+        }.invoke_signed(&signers)?;
+
+        Event::Deposit(DepositEvent {
+            pool: *self.accounts.pool.key(),
+            user: *self.accounts.user.key(),
+            amount_a,
+            amount_b,
+            lp_tokens: lp_tokens_to_mint,
+        }).emit();
 
         Ok(())
     }
@@ -0,0 +1,73 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+
+use crate::{events::{AuthorityChangedEvent, Event}, PinocchioError, Pool, SignerAccount};
+
+pub struct CancelAuthorityTransferAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for CancelAuthorityTransferAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            authority,
+            pool,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(authority)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { authority, pool })
+    }
+}
+
+pub struct CancelAuthorityTransfer<'a> {
+    pub accounts: CancelAuthorityTransferAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for CancelAuthorityTransfer<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, _data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = CancelAuthorityTransferAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> CancelAuthorityTransfer<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &7;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if pool.authority != *self.accounts.authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+        if !pool.has_pending_authority() {
+            return Err(PinocchioError::InvalidPoolState.into());
+        }
+
+        let old_pending_authority = pool.pending_authority;
+        pool.pending_authority = Pubkey::default();
+
+        Event::AuthorityChanged(AuthorityChangedEvent {
+            pool: *self.accounts.pool.key(),
+            old_authority: pool.authority,
+            new_authority: pool.authority,
+            old_pending_authority,
+            new_pending_authority: pool.pending_authority,
+        }).emit();
+
+        Ok(())
+    }
+}
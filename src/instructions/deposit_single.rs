@@ -0,0 +1,336 @@
+use pinocchio::{account_info::AccountInfo, instruction::Signer, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+use pinocchio_token::instructions::MintTo;
+
+use crate::{
+    events::{DepositEvent, Event}, math, Curve, token_interface::{active_transfer_fee, TokenTransfer}, AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit,
+    Deadline, MintInterface, Observations, PinocchioError, Pool, PoolAccount, PoolAccountCheck, SignerAccount, TokenProgram,
+    TokenProgramCheck,
+};
+
+pub struct DepositSingleAccounts<'a> {
+    pub user: &'a AccountInfo,
+
+    pub pool: &'a AccountInfo,
+    pub lp_mint: &'a AccountInfo,
+
+    pub token_a_vault: &'a AccountInfo,
+    pub token_b_vault: &'a AccountInfo,
+
+    /// The user's account for the side being deposited.
+    pub user_token: &'a AccountInfo,
+    pub user_lp_token: &'a AccountInfo,
+
+    pub token_a_mint: &'a AccountInfo,
+    pub token_b_mint: &'a AccountInfo,
+
+    pub token_a_program: &'a AccountInfo,
+    pub token_b_program: &'a AccountInfo,
+
+    pub token_program: &'a AccountInfo,
+    pub associated_token_program: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+
+    /// The pool's oracle account, if the caller passes one after the fixed accounts.
+    pub observations: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for DepositSingleAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(value: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            user,
+            pool,
+            lp_mint,
+            token_a_vault,
+            token_b_vault,
+            user_token,
+            user_lp_token,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+            token_program,
+            associated_token_program,
+            system_program,
+            _,
+            optional @ ..
+        ] = value else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(user)?;
+        <&AccountInfo as MintInterface>::check(token_a_mint)?;
+        <&AccountInfo as MintInterface>::check(token_b_mint)?;
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
+
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+        PoolAccount::check_lp_mint(pool, lp_mint)?;
+
+        let observations = optional.first();
+        if let Some(observations) = observations {
+            PoolAccount::check_observations(pool, observations)?;
+        }
+
+        Ok(Self {
+            user,
+            pool,
+            lp_mint,
+            token_a_vault,
+            token_b_vault,
+            user_token,
+            user_lp_token,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+            token_program,
+            associated_token_program,
+            system_program,
+            observations,
+        })
+    }
+}
+
+pub struct DepositSingleData {
+    pub amount_in: u64,
+    pub min_lp_amount: u64,
+    pub deposit_a: bool, // true to deposit token A, false to deposit token B
+    pub deadline: Option<Deadline>,
+}
+
+impl TryFrom<&[u8]> for DepositSingleData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 17 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let amount_in = u64::from_le_bytes(data[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let min_lp_amount = u64::from_le_bytes(data[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let deposit_a = data[16] != 0;
+        let deadline = Deadline::parse(&data[17..])?;
+
+        if amount_in == 0 {
+            return Err(PinocchioError::InvalidAmount.into());
+        }
+
+        Ok(Self {
+            amount_in,
+            min_lp_amount,
+            deposit_a,
+            deadline,
+        })
+    }
+}
+
+/// Single-sided deposit ("zap in"): swaps the optimal share of one token through
+/// the pool's own curve and deposits the rest alongside the swap output.
+///
+/// The swap output never leaves the vault, so the user makes one transfer in and
+/// receives LP. Only the amount backing the minted LP is taken; rounding dust
+/// stays with the user.
+///
+/// StableSwap and weighted pools accept deposits in any ratio, so there the whole
+/// amount is deposited one-sided and pays the curve's imbalance fee rather than a swap.
+pub struct DepositSingle<'a> {
+    pub accounts: DepositSingleAccounts<'a>,
+    pub data: DepositSingleData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for DepositSingle<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = DepositSingleAccounts::try_from(accounts)?;
+        let data = DepositSingleData::try_from(data)?;
+
+        let input_mint = if data.deposit_a { accounts.token_a_mint } else { accounts.token_b_mint };
+        AssociatedTokenAccount::check(accounts.user_token, accounts.user, input_mint)?;
+
+        AssociatedTokenAccount::init_if_needed(
+            accounts.user_lp_token,
+            accounts.lp_mint,
+            accounts.user,
+            accounts.user,
+            accounts.system_program,
+            accounts.token_program
+        )?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> DepositSingle<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &13;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
+
+        let input_mint = if self.data.deposit_a { self.accounts.token_a_mint } else { self.accounts.token_b_mint };
+        let transfer_fee = active_transfer_fee(input_mint, clock)?;
+
+        let (amount_to_transfer, lp_tokens_to_mint, pool_bump) = {
+            let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+            let pool = Pool::load_mut(&mut pool_data)?;
+
+            if pool.is_disabled(Pool::ADD_LIQUIDITY_DISABLED) {
+                return Err(PinocchioError::AddLiquidityDisabled.into());
+            }
+            if pool.is_disabled(Pool::SWAP_DISABLED) {
+                return Err(PinocchioError::SwapDisabled.into());
+            }
+
+            let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+                self.accounts.pool,
+                self.accounts.token_a_vault,
+                self.accounts.token_b_vault,
+                self.accounts.token_a_mint,
+                self.accounts.token_b_mint,
+            )?;
+
+            let lp_mint_data = self.accounts.lp_mint.try_borrow_data()?;
+            let lp_mint = unsafe { pinocchio_token::state::Mint::from_bytes(&lp_mint_data) };
+            if lp_mint.mint_authority() != Some(self.accounts.pool.key()) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            let (reserve_a, reserve_b) = pool.pricing_reserves(vault_a_amount, vault_b_amount)?;
+            pool.update_price_accumulators(reserve_a, reserve_b, clock.unix_timestamp)?;
+            if let Some(observations) = self.accounts.observations {
+                let mut observations_data = observations.try_borrow_mut_data()?;
+                Observations::load_mut(&mut observations_data)?.sync(pool, reserve_a, reserve_b, clock);
+            }
+
+            // A zap needs an existing price to swap against.
+            let lp_supply = pool.total_lp_supply(lp_mint.supply())?;
+            if lp_supply == 0 || reserve_a == 0 || reserve_b == 0 {
+                return Err(PinocchioError::InsufficientLiquidity.into());
+            }
+
+            // The zap is sized on what reaches the vault after the transfer fee.
+            let amount_in = transfer_fee.net_amount(self.data.amount_in)?;
+
+            let (lp_tokens_to_mint, amount_to_transfer) = match pool.curve_at(clock.unix_timestamp)? {
+                Curve::ConstantProduct => {
+                    let (reserve_in, reserve_out) = if self.data.deposit_a {
+                            (reserve_a, reserve_b)
+                        } else {
+                            (reserve_b, reserve_a)
+                        };
+
+                    let swap_amount = math::optimal_swap_amount(amount_in, reserve_in, pool.fee_rate)?;
+                    let (swap_output, fee) = math::get_amount_out(swap_amount, reserve_in, reserve_out, pool.fee_rate)?;
+                    let protocol_fee = pool.protocol_fee(fee)?;
+                    pool.accrue_protocol_fee(fee, self.data.deposit_a)?;
+
+                    // Reserves as they stand after the internal swap.
+                    let reserve_in = reserve_in
+                        .checked_add(swap_amount - protocol_fee)
+                        .ok_or(PinocchioError::MathOverflow)?;
+                    let reserve_out = reserve_out - swap_output;
+
+                    let remaining = amount_in - swap_amount;
+                    let lp_tokens_to_mint = core::cmp::min(
+                        math::lp_for_amount(remaining, reserve_in, lp_supply)?,
+                        math::lp_for_amount(swap_output, reserve_out, lp_supply)?,
+                    );
+                    let deposit_amount = if lp_tokens_to_mint == 0 {
+                        0
+                    } else {
+                        math::amount_for_lp(lp_tokens_to_mint, reserve_in, lp_supply)?
+                    };
+
+                    (lp_tokens_to_mint, transfer_fee.gross_amount(swap_amount + deposit_amount)?)
+                }
+                // StableSwap deposits take any ratio, so the whole amount goes in
+                // as a one-sided deposit and pays the imbalance fee instead of a swap fee.
+                Curve::StableSwap { amplification } => {
+                    let (amount_a, amount_b) = if self.data.deposit_a { (amount_in, 0) } else { (0, amount_in) };
+                    let lp_tokens_to_mint = math::stable_deposit(
+                        amount_a,
+                        amount_b,
+                        reserve_a,
+                        reserve_b,
+                        lp_supply,
+                        amplification,
+                        pool.fee_rate,
+                    )?;
+
+                    (lp_tokens_to_mint, self.data.amount_in)
+                }
+                // Likewise for weighted pools, where the share that would have been
+                // swapped pays the swap fee.
+                Curve::Weighted { weight_a, weight_b } => {
+                    let (amount_a, amount_b) = if self.data.deposit_a { (amount_in, 0) } else { (0, amount_in) };
+                    let lp_tokens_to_mint = math::weighted_deposit(
+                        amount_a,
+                        amount_b,
+                        reserve_a,
+                        reserve_b,
+                        lp_supply,
+                        weight_a,
+                        weight_b,
+                        pool.fee_rate,
+                    )?;
+
+                    (lp_tokens_to_mint, self.data.amount_in)
+                }
+            };
+
+            if lp_tokens_to_mint == 0 {
+                return Err(PinocchioError::InvalidAmount.into());
+            }
+            if lp_tokens_to_mint < self.data.min_lp_amount {
+                return Err(PinocchioError::SlippageExceeded.into());
+            }
+
+            (amount_to_transfer, lp_tokens_to_mint, pool.bump)
+        };
+
+        let (vault, token_program) = if self.data.deposit_a {
+                (self.accounts.token_a_vault, self.accounts.token_a_program)
+            } else {
+                (self.accounts.token_b_vault, self.accounts.token_b_program)
+            };
+        TokenTransfer {
+            from: self.accounts.user_token,
+            mint: input_mint,
+            to: vault,
+            authority: self.accounts.user,
+            token_program,
+            amount: amount_to_transfer,
+        }.invoke()?;
+
+        let pool_bump_binding = [pool_bump];
+        let seeds = PoolAccount::signer_seeds(self.accounts.token_a_mint, self.accounts.token_b_mint, &pool_bump_binding);
+
+        let signers = [Signer::from(&seeds)];
+
+        MintTo {
+            mint: self.accounts.lp_mint,
+            amount: lp_tokens_to_mint,
+            mint_authority: self.accounts.pool,
+            account: self.accounts.user_lp_token,
+        }.invoke_signed(&signers)?;
+
+        let (amount_a, amount_b) = if self.data.deposit_a { (amount_to_transfer, 0) } else { (0, amount_to_transfer) };
+        Event::Deposit(DepositEvent {
+            pool: *self.accounts.pool.key(),
+            user: *self.accounts.user.key(),
+            amount_a,
+            amount_b,
+            lp_tokens: lp_tokens_to_mint,
+        }).emit();
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
 use pinocchio::{
-    account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::find_program_address, sysvars::Sysvar
+    account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::{create_program_address, Pubkey}, sysvars::Sysvar
 };
-use pinocchio_token::state::Mint;
+use pinocchio_token::{instructions::InitializeMint2, state::Mint};
+
+use crate::token_interface::{self, MINT_ACCOUNT_TYPE, TOKEN_ACCOUNT_ACCOUNT_TYPE};
 
 pub trait SignerAccount {
     fn check(account: &AccountInfo) -> Result<(), ProgramError>;
@@ -20,15 +22,74 @@ pub trait MintInterface {
     fn check(account: &AccountInfo) -> Result<(), ProgramError>;
 }
 
+/// Accepts mints of either token program, including Token-2022 mints with extensions.
 impl MintInterface for &AccountInfo {
     fn check(account: &AccountInfo) -> Result<(), ProgramError> {
-        if account.data_len() != Mint::LEN {
+        if !token_interface::has_token_layout(account, Mint::LEN, MINT_ACCOUNT_TYPE)? {
             return Err(ProgramError::InvalidAccountData);
         }
         Ok(())
     }
 }
 
+pub trait TokenProgramCheck {
+    fn check(token_program: &AccountInfo, mint: &AccountInfo) -> Result<(), ProgramError>;
+}
+
+/// The token program passed for one side of a pool: either token program, and
+/// the one that owns that side's mint.
+pub struct TokenProgram;
+
+impl TokenProgramCheck for TokenProgram {
+    fn check(token_program: &AccountInfo, mint: &AccountInfo) -> Result<(), ProgramError> {
+        if !token_interface::is_token_program(token_program.key()) || !mint.is_owned_by(token_program.key()) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(())
+    }
+}
+
+pub trait MintInit {
+    fn init<'a>(
+        payer: &AccountInfo,
+        mint: &AccountInfo,
+        seeds: &[Seed<'a>],
+        decimals: u8,
+        mint_authority: &Pubkey,
+    ) -> Result<(), ProgramError>;
+}
+
+pub struct MintAccount;
+
+impl MintInit for MintAccount {
+    fn init<'a>(
+        payer: &AccountInfo,
+        mint: &AccountInfo,
+        seeds: &[Seed<'a>],
+        decimals: u8,
+        mint_authority: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        let signers = [Signer::from(seeds)];
+
+        pinocchio_system::instructions::CreateAccount {
+            from: payer,
+            to: mint,
+            owner: &pinocchio_token::ID,
+            lamports: pinocchio::sysvars::rent::Rent::get()?.minimum_balance(Mint::LEN),
+            space: Mint::LEN as u64,
+        }.invoke_signed(&signers)?;
+
+        InitializeMint2 {
+            mint,
+            decimals,
+            mint_authority,
+            freeze_authority: None,
+        }.invoke()?;
+
+        Ok(())
+    }
+}
+
 pub trait AccountCheck {
     fn check(account: &AccountInfo) -> Result<(), ProgramError>;
 }
@@ -70,11 +131,11 @@ pub struct TokenAccount;
  
 impl AccountCheck for TokenAccount {
     fn check(account: &AccountInfo) -> Result<(), ProgramError> {
-        if !account.is_owned_by(&pinocchio_token::ID) {
-            return Err(ProgramError::IllegalOwner.into());
+        if !token_interface::is_token_program(unsafe { account.owner() }) {
+            return Err(ProgramError::IllegalOwner);
         }
-        if account.data_len().ne(&pinocchio_token::state::TokenAccount::LEN) {
-            return Err(ProgramError::InvalidAccountData.into());
+        if !token_interface::has_token_layout(account, pinocchio_token::state::TokenAccount::LEN, TOKEN_ACCOUNT_ACCOUNT_TYPE)? {
+            return Err(ProgramError::InvalidAccountData);
         }
  
         Ok(())
@@ -109,6 +170,11 @@ pub trait AssociatedTokenAccountInit {
     ) -> Result<(), ProgramError>;
 }
 
+/// A user's token account for one mint. The check reads the account rather than
+/// re-deriving the associated address with `find_program_address`, whose bump
+/// search costs more than the rest of a swap's validation. Any token account the
+/// user owns for the mint is accepted; the associated token program checks the
+/// address itself when `init_if_needed` creates one.
 pub struct AssociatedTokenAccount;
 
 impl AssociatedTokenAccountCheck for AssociatedTokenAccount {
@@ -119,12 +185,17 @@ impl AssociatedTokenAccountCheck for AssociatedTokenAccount {
     ) -> Result<(), ProgramError> {
         TokenAccount::check(account)?;
 
-        let seeds : &[&[u8]] = &[authority.key(), &pinocchio_token::ID,mint.key()];
+        let token_program = unsafe { account.owner() };
+        if !mint.is_owned_by(token_program) {
+            return Err(ProgramError::InvalidAccountData);
+        }
 
-        if find_program_address(seeds, &pinocchio_associated_token_account::ID).0.ne(account.key()) {
+        let account_data = account.try_borrow_data()?;
+        let token_account = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&account_data) };
+        if token_account.mint() != mint.key() || token_account.owner() != authority.key() {
             return Err(ProgramError::InvalidAccountData);
         }
-        
+
         Ok(())
     }
 }
@@ -143,8 +214,8 @@ impl AssociatedTokenAccountInit for AssociatedTokenAccount {
             account: ata,
             wallet: owner,
             mint,
-            system_program: system_program,
-            token_program: token_program,
+            system_program,
+            token_program,
         }.invoke()?;
         
         Ok(())
@@ -162,4 +233,144 @@ impl AssociatedTokenAccountInit for AssociatedTokenAccount {
             Err(_) => Self::init(ata, mint, authority, owner, system_program, token_program)
         }
     }
-}
\ No newline at end of file
+}
+
+pub trait PoolAccountCheck {
+    fn check(
+        pool: &AccountInfo,
+        token_a_mint: &AccountInfo,
+        token_b_mint: &AccountInfo,
+    ) -> Result<(), ProgramError>;
+
+    fn check_lp_mint(pool: &AccountInfo, lp_mint: &AccountInfo) -> Result<(), ProgramError>;
+
+    fn check_vaults(
+        pool: &AccountInfo,
+        token_a_vault: &AccountInfo,
+        token_b_vault: &AccountInfo,
+    ) -> Result<(), ProgramError>;
+
+    fn check_observations(pool: &AccountInfo, observations: &AccountInfo) -> Result<(), ProgramError>;
+}
+
+/// Pool address checks for the hot path. They re-derive with the bumps stored in
+/// `Pool` via `create_program_address` rather than searching with `find_program_address`.
+pub struct PoolAccount;
+
+impl PoolAccountCheck for PoolAccount {
+    fn check(
+        pool: &AccountInfo,
+        token_a_mint: &AccountInfo,
+        token_b_mint: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let pool_data = pool.try_borrow_data()?;
+        let pool_state = crate::Pool::load(&pool_data)?;
+
+        if pool_state.token_a_mint.ne(token_a_mint.key()) || pool_state.token_b_mint.ne(token_b_mint.key()) {
+            return Err(crate::PinocchioError::InvalidTokenMint.into());
+        }
+
+        let seeds: &[&[u8]] = &[b"pool", token_a_mint.key(), token_b_mint.key(), &[pool_state.bump]];
+        if create_program_address(seeds, &crate::ID)?.ne(pool.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    fn check_lp_mint(pool: &AccountInfo, lp_mint: &AccountInfo) -> Result<(), ProgramError> {
+        let pool_data = pool.try_borrow_data()?;
+        let pool_state = crate::Pool::load(&pool_data)?;
+
+        if pool_state.lp_mint.ne(lp_mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let seeds: &[&[u8]] = &[b"lp_mint", pool.key(), &[pool_state.lp_mint_bump]];
+        if create_program_address(seeds, &crate::ID)?.ne(lp_mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    fn check_vaults(
+        pool: &AccountInfo,
+        token_a_vault: &AccountInfo,
+        token_b_vault: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        TokenAccount::check(token_a_vault)?;
+        TokenAccount::check(token_b_vault)?;
+
+        let pool_data = pool.try_borrow_data()?;
+        let pool_state = crate::Pool::load(&pool_data)?;
+
+        // Vault addresses were validated as the pool's ATAs at initialization.
+        if pool_state.token_a_vault.ne(token_a_vault.key()) || pool_state.token_b_vault.ne(token_b_vault.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    fn check_observations(pool: &AccountInfo, observations: &AccountInfo) -> Result<(), ProgramError> {
+        if !observations.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let observations_data = observations.try_borrow_data()?;
+        let observations_state = crate::Observations::load(&observations_data)?;
+
+        if observations_state.pool.ne(pool.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let seeds: &[&[u8]] = &[b"observations", pool.key(), &[observations_state.bump]];
+        if create_program_address(seeds, &crate::ID)?.ne(observations.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+}
+
+impl PoolAccount {
+    /// Balances of the pool's vaults, after checking that `pool` owns both and
+    /// that each holds its side's mint.
+    pub fn vault_amounts(
+        pool: &AccountInfo,
+        token_a_vault: &AccountInfo,
+        token_b_vault: &AccountInfo,
+        token_a_mint: &AccountInfo,
+        token_b_mint: &AccountInfo,
+    ) -> Result<(u64, u64), ProgramError> {
+        let token_a_vault_data = token_a_vault.try_borrow_data()?;
+        let token_a_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_a_vault_data) };
+
+        let token_b_vault_data = token_b_vault.try_borrow_data()?;
+        let token_b_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_b_vault_data) };
+
+        if token_a_vault.owner() != pool.key() || token_a_vault.mint() != token_a_mint.key() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if token_b_vault.owner() != pool.key() || token_b_vault.mint() != token_b_mint.key() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok((token_a_vault.amount(), token_b_vault.amount()))
+    }
+
+    /// Seeds the pool signs vault transfers and LP mints with.
+    pub fn signer_seeds<'a>(token_a_mint: &'a AccountInfo, token_b_mint: &'a AccountInfo, bump: &'a [u8; 1]) -> [Seed<'a>; 4] {
+        [
+            Seed::from(b"pool"),
+            Seed::from(token_a_mint.key().as_ref()),
+            Seed::from(token_b_mint.key().as_ref()),
+            Seed::from(bump),
+        ]
+    }
+}
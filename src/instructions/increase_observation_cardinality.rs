@@ -0,0 +1,131 @@
+use pinocchio::{account_info::AccountInfo, instruction::Seed, program_error::ProgramError, ProgramResult};
+
+use crate::{events::{Event, ObservationCardinalityChangedEvent}, Observations, PoolAccount, PoolAccountCheck, Pool, ProgramAccount, ProgramAccountInit, SignerAccount};
+
+pub struct IncreaseObservationCardinalityAccounts<'a> {
+    pub payer: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+    pub observations: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for IncreaseObservationCardinalityAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            payer,
+            pool,
+            observations,
+            system_program,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(payer)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        if pool.data_len() != Pool::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self { payer, pool, observations, system_program })
+    }
+}
+
+pub struct IncreaseObservationCardinalityData {
+    pub cardinality_next: u16,
+}
+
+impl TryFrom<&[u8]> for IncreaseObservationCardinalityData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 2 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let cardinality_next = u16::from_le_bytes([data[0], data[1]]);
+
+        if cardinality_next == 0 || cardinality_next as usize > Observations::MAX_CARDINALITY {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self { cardinality_next })
+    }
+}
+
+/// Creates the pool's oracle account on first use and raises the number of
+/// observations it keeps. Permissionless: whoever wants a longer history pays
+/// for the account.
+pub struct IncreaseObservationCardinality<'a> {
+    pub accounts: IncreaseObservationCardinalityAccounts<'a>,
+    pub data: IncreaseObservationCardinalityData,
+    /// Set when this instruction created the account, which still needs its header.
+    pub observations_bump: Option<u8>,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for IncreaseObservationCardinality<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = IncreaseObservationCardinalityAccounts::try_from(accounts)?;
+        let data = IncreaseObservationCardinalityData::try_from(data)?;
+
+        let observations_bump = if accounts.observations.data_is_empty() {
+            let (address, bump) = Observations::find_address(accounts.pool.key());
+            if address.ne(accounts.observations.key()) {
+                return Err(ProgramError::InvalidSeeds);
+            }
+
+            let bump_binding = [bump];
+            let seeds = [
+                Seed::from(b"observations"),
+                Seed::from(accounts.pool.key().as_ref()),
+                Seed::from(&bump_binding),
+            ];
+
+            ProgramAccount::init::<Observations>(
+                accounts.payer,
+                accounts.observations,
+                &seeds,
+                Observations::LEN,
+            )?;
+
+            Some(bump)
+        } else {
+            PoolAccount::check_observations(accounts.pool, accounts.observations)?;
+            None
+        };
+
+        Ok(Self { accounts, data, observations_bump })
+    }
+}
+
+impl<'a> IncreaseObservationCardinality<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &15;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut observations_data = self.accounts.observations.try_borrow_mut_data()?;
+        let observations = Observations::load_mut(&mut observations_data)?;
+
+        // A freshly created account is zeroed, so it reports a previous size of 0.
+        let old_cardinality_next = observations.cardinality_next;
+        if let Some(bump) = self.observations_bump {
+            observations.set_inner(*self.accounts.pool.key(), bump);
+        }
+
+        observations.grow(self.data.cardinality_next)?;
+
+        Event::ObservationCardinalityChanged(ObservationCardinalityChangedEvent {
+            pool: *self.accounts.pool.key(),
+            old_cardinality_next,
+            new_cardinality_next: observations.cardinality_next,
+        }).emit();
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use core::cmp::Ordering;
+
 use pinocchio::{
     account_info::AccountInfo, 
     instruction::Seed, 
@@ -7,8 +9,9 @@ use pinocchio::{
 };
 
 use crate::{
-    AssociatedTokenAccount, AssociatedTokenAccountInit, 
-    Pool, MintInterface, ProgramAccount, SignerAccount, ProgramAccountInit, PinocchioError,
+    events::{Event, PoolCreatedEvent}, AssociatedTokenAccount, Curve, AssociatedTokenAccountInit, MintAccount, MintInit,
+    Pool, PoolAccount, MintInterface, ProgramAccount, SignerAccount, ProgramAccountInit, PinocchioError, TokenProgram,
+    TokenProgramCheck,
 };
 
 pub struct InitializePoolAccounts<'a> {
@@ -55,9 +58,14 @@ impl<'a> TryFrom<&'a [AccountInfo]> for InitializePoolAccounts<'a> {
         <&AccountInfo as SignerAccount>::check(authority)?;
         <&AccountInfo as MintInterface>::check(token_a_mint)?;
         <&AccountInfo as MintInterface>::check(token_b_mint)?;
-
-        if token_a_mint.key() == token_b_mint.key() {
-            return Err(PinocchioError::IdenticalMints.into());
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
+
+        // One pool per pair: the PDA is only ever seeded with the mints in ascending order.
+        match token_a_mint.key().cmp(token_b_mint.key()) {
+            Ordering::Equal => return Err(PinocchioError::IdenticalMints.into()),
+            Ordering::Greater => return Err(PinocchioError::UnsortedMints.into()),
+            Ordering::Less => {}
         }
 
         Ok(Self { 
@@ -79,23 +87,28 @@ impl<'a> TryFrom<&'a [AccountInfo]> for InitializePoolAccounts<'a> {
 
 pub struct InitializePoolData {
     pub fee_rate: u16,
+    pub lp_decimals: u8,
+    pub curve: Curve,
 }
 
 impl<'a> TryFrom<&'a [u8]> for InitializePoolData {
     type Error = ProgramError;
 
     fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-        if data.len() < 2 {
+        if data.len() < 3 {
             return Err(ProgramError::InvalidInstructionData);
         }
 
         let fee_rate = u16::from_le_bytes([data[0], data[1]]);
+        let lp_decimals = data[2];
 
-        if fee_rate > 10000 {
+        if fee_rate > Pool::MAX_FEE_RATE {
             return Err(PinocchioError::InvalidFeeRate.into());
         }
 
-        Ok(Self { fee_rate })
+        let curve = Curve::parse(&data[3..])?;
+
+        Ok(Self { fee_rate, lp_decimals, curve })
     }
 }
 
@@ -123,12 +136,7 @@ impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for InitializePool<'a> {
         );
 
         let pool_bump_binding = [pool_bump];
-        let pool_seeds = [
-            Seed::from(b"pool"),
-            Seed::from(accounts.token_a_mint.key().as_ref()),
-            Seed::from(accounts.token_b_mint.key().as_ref()),
-            Seed::from(&pool_bump_binding),
-        ];
+        let pool_seeds = PoolAccount::signer_seeds(accounts.token_a_mint, accounts.token_b_mint, &pool_bump_binding);
 
         let lp_mint_bump_binding = [lp_mint_bump];
         let lp_mint_seeds = [
@@ -144,29 +152,30 @@ impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for InitializePool<'a> {
             Pool::LEN,
         )?;
 
-        ProgramAccount::init::<pinocchio_token::state::Mint>(
+        MintAccount::init(
             accounts.authority,
             accounts.lp_mint,
             &lp_mint_seeds,
-            pinocchio_token::state::Mint::LEN,
+            data.lp_decimals,
+            accounts.pool.key(),
         )?;
 
         AssociatedTokenAccount::init(
             accounts.token_a_vault,
             accounts.token_a_mint,
-            accounts.pool,
             accounts.authority,
+            accounts.pool,
             accounts.system_program,
-            accounts.token_program,
+            accounts.token_a_program,
         )?;
 
         AssociatedTokenAccount::init(
             accounts.token_b_vault,
             accounts.token_b_mint,
-            accounts.pool,
             accounts.authority,
+            accounts.pool,
             accounts.system_program,
-            accounts.token_program,
+            accounts.token_b_program,
         )?;
 
         Ok(Self { 
@@ -196,6 +205,17 @@ impl<'a> InitializePool<'a> {
             self.pool_bump,
             self.lp_mint_bump,
         );
+        pool.set_curve(self.data.curve);
+
+        Event::PoolCreated(PoolCreatedEvent {
+            pool: *self.accounts.pool.key(),
+            authority: *self.accounts.authority.key(),
+            token_a_mint: *self.accounts.token_a_mint.key(),
+            token_b_mint: *self.accounts.token_b_mint.key(),
+            lp_mint: *self.accounts.lp_mint.key(),
+            fee_rate: self.data.fee_rate,
+            curve: self.data.curve.to_bytes(),
+        }).emit();
 
         Ok(())
     }
@@ -3,8 +3,44 @@ pub mod initialize_pool;
 pub mod add_liquidity;
 pub mod remove_liquidity;
 pub mod swap;
+pub mod set_fee_rate;
+pub mod propose_authority;
+pub mod accept_authority;
+pub mod cancel_authority_transfer;
+pub mod set_pool_status;
+pub mod set_protocol_fee_share;
+pub mod withdraw_protocol_fees;
+pub mod swap_exact_out;
+pub mod route_swap;
+pub mod deposit_single;
+pub mod withdraw_single;
+pub mod increase_observation_cardinality;
+pub mod observe;
+pub mod quote_swap;
+pub mod quote_add_liquidity;
+pub mod quote_remove_liquidity;
+pub mod ramp_amplification;
+pub mod stop_ramp;
 
 pub use swap::*;
+pub use set_fee_rate::*;
+pub use propose_authority::*;
+pub use accept_authority::*;
+pub use cancel_authority_transfer::*;
+pub use set_pool_status::*;
+pub use set_protocol_fee_share::*;
+pub use withdraw_protocol_fees::*;
+pub use swap_exact_out::*;
+pub use route_swap::*;
+pub use deposit_single::*;
+pub use withdraw_single::*;
+pub use increase_observation_cardinality::*;
+pub use observe::*;
+pub use quote_swap::*;
+pub use quote_add_liquidity::*;
+pub use quote_remove_liquidity::*;
+pub use ramp_amplification::*;
+pub use stop_ramp::*;
 pub use remove_liquidity::*;
 pub use add_liquidity::*;
 pub use helper::*;
@@ -0,0 +1,128 @@
+use pinocchio::{account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{Observations, Pool, PoolAccount, PoolAccountCheck};
+
+pub struct ObserveAccounts<'a> {
+    pub pool: &'a AccountInfo,
+    pub observations: &'a AccountInfo,
+
+    pub token_a_vault: &'a AccountInfo,
+    pub token_b_vault: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ObserveAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            pool,
+            observations,
+            token_a_vault,
+            token_b_vault,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        PoolAccount::check_observations(pool, observations)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+
+        Ok(Self { pool, observations, token_a_vault, token_b_vault })
+    }
+}
+
+pub struct ObserveData {
+    pub seconds_agos: [u32; ObserveData::MAX_POINTS],
+    pub len: usize,
+}
+
+impl ObserveData {
+    /// Most points one call can read; two are enough for a TWAP.
+    pub const MAX_POINTS: usize = 8;
+
+    pub fn seconds_agos(&self) -> &[u32] {
+        &self.seconds_agos[..self.len]
+    }
+}
+
+impl TryFrom<&[u8]> for ObserveData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.is_empty() || !data.len().is_multiple_of(4) || data.len() / 4 > Self::MAX_POINTS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut seconds_agos = [0u32; Self::MAX_POINTS];
+        for (seconds_ago, bytes) in seconds_agos.iter_mut().zip(data.chunks_exact(4)) {
+            *seconds_ago = u32::from_le_bytes(bytes.try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        }
+
+        Ok(Self { seconds_agos, len: data.len() / 4 })
+    }
+}
+
+/// Read-only oracle query. For each `seconds_ago` in the data, returns the
+/// pool's `price_a_cumulative` and `price_b_cumulative` at that time as two
+/// little-endian `u128`s via return data, so other programs can read a TWAP
+/// through CPI. Off-chain readers can call [`Observations::observe`] directly.
+pub struct Observe<'a> {
+    pub accounts: ObserveAccounts<'a>,
+    pub data: ObserveData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for Observe<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = ObserveAccounts::try_from(accounts)?;
+        let data = ObserveData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> Observe<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &16;
+
+    /// Return data per requested point: two `u128` cumulative prices.
+    pub const POINT_LEN: usize = 16 + 16;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        let now = clock.unix_timestamp;
+
+        let pool_data = self.accounts.pool.try_borrow_data()?;
+        let pool = Pool::load(&pool_data)?;
+
+        let observations_data = self.accounts.observations.try_borrow_data()?;
+        let observations = Observations::load(&observations_data)?;
+
+        let token_a_vault_data = self.accounts.token_a_vault.try_borrow_data()?;
+        let token_a_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_a_vault_data) };
+
+        let token_b_vault_data = self.accounts.token_b_vault.try_borrow_data()?;
+        let token_b_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_b_vault_data) };
+
+        let (reserve_a, reserve_b) = pool.pricing_reserves(token_a_vault.amount(), token_b_vault.amount())?;
+
+        let mut return_data = [0u8; ObserveData::MAX_POINTS * Self::POINT_LEN];
+        for (seconds_ago, point) in self.data.seconds_agos().iter().zip(return_data.chunks_exact_mut(Self::POINT_LEN)) {
+            let (price_a_cumulative, price_b_cumulative) = observations.observe(pool, reserve_a, reserve_b, now, *seconds_ago)?;
+            point[..16].copy_from_slice(&price_a_cumulative.to_le_bytes());
+            point[16..].copy_from_slice(&price_b_cumulative.to_le_bytes());
+        }
+
+        set_return_data(&return_data[..self.data.len * Self::POINT_LEN]);
+
+        Ok(())
+    }
+}
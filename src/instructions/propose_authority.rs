@@ -0,0 +1,95 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+
+use crate::{events::{AuthorityChangedEvent, Event}, PinocchioError, Pool, SignerAccount};
+
+pub struct ProposeAuthorityAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ProposeAuthorityAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            authority,
+            pool,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(authority)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { authority, pool })
+    }
+}
+
+pub struct ProposeAuthorityData {
+    pub new_authority: Pubkey,
+}
+
+impl TryFrom<&[u8]> for ProposeAuthorityData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 32 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let new_authority: Pubkey = data[0..32].try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        // The default key marks "no pending authority" in `Pool`.
+        if new_authority == Pubkey::default() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self { new_authority })
+    }
+}
+
+pub struct ProposeAuthority<'a> {
+    pub accounts: ProposeAuthorityAccounts<'a>,
+    pub data: ProposeAuthorityData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for ProposeAuthority<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = ProposeAuthorityAccounts::try_from(accounts)?;
+        let data = ProposeAuthorityData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> ProposeAuthority<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &5;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if pool.authority != *self.accounts.authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+
+        let old_pending_authority = pool.pending_authority;
+        pool.pending_authority = self.data.new_authority;
+
+        Event::AuthorityChanged(AuthorityChangedEvent {
+            pool: *self.accounts.pool.key(),
+            old_authority: pool.authority,
+            new_authority: pool.authority,
+            old_pending_authority,
+            new_pending_authority: pool.pending_authority,
+        }).emit();
+
+        Ok(())
+    }
+}
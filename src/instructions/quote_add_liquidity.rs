@@ -0,0 +1,146 @@
+use pinocchio::{account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{
+    instructions::AddLiquidityQuote, token_interface::active_transfer_fee, MintInterface, PinocchioError, Pool,
+    PoolAccount, PoolAccountCheck,
+};
+
+/// Read-only accounts for pricing a deposit or withdrawal. Shared by
+/// `QuoteAddLiquidity` and `QuoteRemoveLiquidity`.
+pub struct QuoteLiquidityAccounts<'a> {
+    pub pool: &'a AccountInfo,
+    pub lp_mint: &'a AccountInfo,
+
+    pub token_a_vault: &'a AccountInfo,
+    pub token_b_vault: &'a AccountInfo,
+
+    pub token_a_mint: &'a AccountInfo,
+    pub token_b_mint: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for QuoteLiquidityAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            pool,
+            lp_mint,
+            token_a_vault,
+            token_b_vault,
+            token_a_mint,
+            token_b_mint,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as MintInterface>::check(token_a_mint)?;
+        <&AccountInfo as MintInterface>::check(token_b_mint)?;
+
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+        PoolAccount::check_lp_mint(pool, lp_mint)?;
+
+        Ok(Self { pool, lp_mint, token_a_vault, token_b_vault, token_a_mint, token_b_mint })
+    }
+}
+
+impl QuoteLiquidityAccounts<'_> {
+    /// The pool's pricing reserves and LP supply, as `(reserve_a, reserve_b, lp_supply)`.
+    pub fn reserves_and_supply(&self, pool: &Pool) -> Result<(u64, u64, u64), ProgramError> {
+        let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+            self.pool,
+            self.token_a_vault,
+            self.token_b_vault,
+            self.token_a_mint,
+            self.token_b_mint,
+        )?;
+
+        let lp_mint_data = self.lp_mint.try_borrow_data()?;
+        let lp_mint = unsafe { pinocchio_token::state::Mint::from_bytes(&lp_mint_data) };
+
+        let (reserve_a, reserve_b) = pool.pricing_reserves(vault_a_amount, vault_b_amount)?;
+        let lp_supply = pool.total_lp_supply(lp_mint.supply())?;
+
+        Ok((reserve_a, reserve_b, lp_supply))
+    }
+}
+
+pub struct QuoteAddLiquidityData {
+    pub max_amount_a: u64,
+    pub max_amount_b: u64,
+}
+
+impl TryFrom<&[u8]> for QuoteAddLiquidityData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 16 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let max_amount_a = u64::from_le_bytes(data[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let max_amount_b = u64::from_le_bytes(data[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        Ok(Self { max_amount_a, max_amount_b })
+    }
+}
+
+/// Prices an `AddLiquidity` with the given maximums without executing it and
+/// publishes the [`AddLiquidityQuote`] as return data, for simulation or CPI.
+pub struct QuoteAddLiquidity<'a> {
+    pub accounts: QuoteLiquidityAccounts<'a>,
+    pub data: QuoteAddLiquidityData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for QuoteAddLiquidity<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = QuoteLiquidityAccounts::try_from(accounts)?;
+        let data = QuoteAddLiquidityData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> QuoteAddLiquidity<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &18;
+
+    pub fn quote(&self, clock: &Clock) -> Result<AddLiquidityQuote, ProgramError> {
+        let transfer_fee_a = active_transfer_fee(self.accounts.token_a_mint, clock)?;
+        let transfer_fee_b = active_transfer_fee(self.accounts.token_b_mint, clock)?;
+
+        let pool_data = self.accounts.pool.try_borrow_data()?;
+        let pool = Pool::load(&pool_data)?;
+
+        if pool.is_disabled(Pool::ADD_LIQUIDITY_DISABLED) {
+            return Err(PinocchioError::AddLiquidityDisabled.into());
+        }
+
+        let (reserve_a, reserve_b, lp_supply) = self.accounts.reserves_and_supply(pool)?;
+
+        AddLiquidityQuote::new(
+            pool,
+            clock.unix_timestamp,
+            reserve_a,
+            reserve_b,
+            lp_supply,
+            self.data.max_amount_a,
+            self.data.max_amount_b,
+            transfer_fee_a,
+            transfer_fee_b,
+        )
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        set_return_data(&self.quote(clock)?.to_bytes());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use pinocchio::{account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{
+    instructions::{QuoteLiquidityAccounts, RemoveLiquidityQuote}, token_interface::active_transfer_fee, PinocchioError,
+    Pool,
+};
+
+pub struct QuoteRemoveLiquidityData {
+    pub lp_tokens: u64,
+}
+
+impl TryFrom<&[u8]> for QuoteRemoveLiquidityData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 8 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let lp_tokens = u64::from_le_bytes(data[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        Ok(Self { lp_tokens })
+    }
+}
+
+/// Prices a `RemoveLiquidity` of `lp_tokens` without executing it and publishes
+/// the [`RemoveLiquidityQuote`] as return data, for simulation or CPI. Takes the
+/// same accounts as `QuoteAddLiquidity`.
+pub struct QuoteRemoveLiquidity<'a> {
+    pub accounts: QuoteLiquidityAccounts<'a>,
+    pub data: QuoteRemoveLiquidityData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for QuoteRemoveLiquidity<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = QuoteLiquidityAccounts::try_from(accounts)?;
+        let data = QuoteRemoveLiquidityData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> QuoteRemoveLiquidity<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &19;
+
+    pub fn quote(&self, clock: &Clock) -> Result<RemoveLiquidityQuote, ProgramError> {
+        let transfer_fee_a = active_transfer_fee(self.accounts.token_a_mint, clock)?;
+        let transfer_fee_b = active_transfer_fee(self.accounts.token_b_mint, clock)?;
+
+        let pool_data = self.accounts.pool.try_borrow_data()?;
+        let pool = Pool::load(&pool_data)?;
+
+        if pool.is_disabled(Pool::REMOVE_LIQUIDITY_DISABLED) {
+            return Err(PinocchioError::RemoveLiquidityDisabled.into());
+        }
+
+        let (reserve_a, reserve_b, lp_supply) = self.accounts.reserves_and_supply(pool)?;
+
+        let quote = RemoveLiquidityQuote::new(
+            reserve_a,
+            reserve_b,
+            lp_supply,
+            self.data.lp_tokens,
+            transfer_fee_a,
+            transfer_fee_b,
+        )?;
+        if quote.amount_a == 0 && quote.amount_b == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(quote)
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        set_return_data(&self.quote(clock)?.to_bytes());
+
+        Ok(())
+    }
+}
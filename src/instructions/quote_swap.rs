@@ -0,0 +1,137 @@
+use pinocchio::{account_info::AccountInfo, cpi::set_return_data, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{
+    instructions::SwapQuote, token_interface::active_transfer_fee, MintInterface, PinocchioError, Pool, PoolAccount,
+    PoolAccountCheck,
+};
+
+/// Read-only accounts for pricing a swap: the pool, its vaults and its mints.
+pub struct QuoteSwapAccounts<'a> {
+    pub pool: &'a AccountInfo,
+
+    pub token_a_vault: &'a AccountInfo,
+    pub token_b_vault: &'a AccountInfo,
+
+    pub token_a_mint: &'a AccountInfo,
+    pub token_b_mint: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for QuoteSwapAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            pool,
+            token_a_vault,
+            token_b_vault,
+            token_a_mint,
+            token_b_mint,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as MintInterface>::check(token_a_mint)?;
+        <&AccountInfo as MintInterface>::check(token_b_mint)?;
+
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+
+        Ok(Self { pool, token_a_vault, token_b_vault, token_a_mint, token_b_mint })
+    }
+}
+
+impl QuoteSwapAccounts<'_> {
+    /// The pool's pricing reserves, read from the vaults.
+    pub fn reserves(&self, pool: &Pool) -> Result<(u64, u64), ProgramError> {
+        let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+            self.pool,
+            self.token_a_vault,
+            self.token_b_vault,
+            self.token_a_mint,
+            self.token_b_mint,
+        )?;
+
+        Ok(pool.pricing_reserves(vault_a_amount, vault_b_amount)?)
+    }
+}
+
+pub struct QuoteSwapData {
+    pub amount_in: u64,
+    pub swap_direction: bool, // true for A to B, false for B to A
+}
+
+impl TryFrom<&[u8]> for QuoteSwapData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 9 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let amount_in = u64::from_le_bytes(data[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let swap_direction = data[8] != 0;
+
+        Ok(Self { amount_in, swap_direction })
+    }
+}
+
+/// Prices a `Swap` of `amount_in` without executing it and publishes the
+/// [`SwapQuote`] as return data, for simulation or CPI. Fails wherever `Swap`
+/// would, slippage aside.
+pub struct QuoteSwap<'a> {
+    pub accounts: QuoteSwapAccounts<'a>,
+    pub data: QuoteSwapData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for QuoteSwap<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = QuoteSwapAccounts::try_from(accounts)?;
+        let data = QuoteSwapData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> QuoteSwap<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &17;
+
+    pub fn quote(&self, clock: &Clock) -> Result<SwapQuote, ProgramError> {
+        let (input_mint, output_mint) = if self.data.swap_direction {
+                (self.accounts.token_a_mint, self.accounts.token_b_mint)
+            } else {
+                (self.accounts.token_b_mint, self.accounts.token_a_mint)
+            };
+        let input_transfer_fee = active_transfer_fee(input_mint, clock)?;
+        let output_transfer_fee = active_transfer_fee(output_mint, clock)?;
+
+        let pool_data = self.accounts.pool.try_borrow_data()?;
+        let pool = Pool::load(&pool_data)?;
+
+        if pool.is_disabled(Pool::SWAP_DISABLED) {
+            return Err(PinocchioError::SwapDisabled.into());
+        }
+
+        let (reserve_a, reserve_b) = self.accounts.reserves(pool)?;
+        let (reserve_in, reserve_out) = if self.data.swap_direction {
+                (reserve_a, reserve_b)
+            } else {
+                (reserve_b, reserve_a)
+            };
+
+        SwapQuote::new(pool, clock.unix_timestamp, reserve_in, reserve_out, self.data.swap_direction, self.data.amount_in, input_transfer_fee, output_transfer_fee)
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        set_return_data(&self.quote(clock)?.to_bytes());
+
+        Ok(())
+    }
+}
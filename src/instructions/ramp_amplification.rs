@@ -0,0 +1,100 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{events::{AmplificationRampEvent, Event}, PinocchioError, Pool, SignerAccount};
+
+pub struct RampAmplificationAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for RampAmplificationAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            authority,
+            pool,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(authority)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { authority, pool })
+    }
+}
+
+pub struct RampAmplificationData {
+    pub target_amplification: u64,
+    pub ramp_end_timestamp: i64,
+}
+
+impl TryFrom<&[u8]> for RampAmplificationData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 16 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let target_amplification = u64::from_le_bytes(data[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let ramp_end_timestamp = i64::from_le_bytes(data[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        Ok(Self { target_amplification, ramp_end_timestamp })
+    }
+}
+
+/// Moves a StableSwap pool's amplification linearly from its current value to
+/// `target_amplification` by `ramp_end_timestamp`, so `A` never jumps. The
+/// bounds are enforced by [`Pool::start_ramp`].
+pub struct RampAmplification<'a> {
+    pub accounts: RampAmplificationAccounts<'a>,
+    pub data: RampAmplificationData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for RampAmplification<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = RampAmplificationAccounts::try_from(accounts)?;
+        let data = RampAmplificationData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> RampAmplification<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &20;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if pool.authority != *self.accounts.authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+
+        let now = clock.unix_timestamp;
+        pool.start_ramp(self.data.target_amplification, self.data.ramp_end_timestamp, now)?;
+
+        Event::AmplificationRamp(AmplificationRampEvent {
+            pool: *self.accounts.pool.key(),
+            start_amplification: pool.amplification,
+            target_amplification: pool.target_amplification,
+            start_timestamp: pool.ramp_start_timestamp,
+            end_timestamp: pool.ramp_end_timestamp,
+        }).emit();
+
+        Ok(())
+    }
+}
@@ -1,13 +1,13 @@
 use pinocchio::{
     account_info::AccountInfo,
-    instruction::{Seed, Signer},
+    instruction::Signer,
     program_error::ProgramError,
-    pubkey::find_program_address,
+    sysvars::{clock::Clock, Sysvar},
     ProgramResult,
 };
-use pinocchio_token::instructions::{Burn, Transfer};
+use pinocchio_token::instructions::Burn;
 
-use crate::{AssociatedTokenAccount, AssociatedTokenAccountCheck, MintInterface, PinocchioError, SignerAccount };
+use crate::{events::{Event, WithdrawEvent}, math, token_interface::{active_transfer_fee, TokenTransfer, TransferFee}, AssociatedTokenAccount, Deadline, AssociatedTokenAccountCheck, MintInterface, PinocchioError, Pool, PoolAccount, PoolAccountCheck, SignerAccount, TokenProgram, TokenProgramCheck };
 
 pub struct RemoveLiquidityAccounts<'a> {
     pub user: &'a AccountInfo,
@@ -23,6 +23,9 @@ pub struct RemoveLiquidityAccounts<'a> {
 
     pub token_a_mint: &'a AccountInfo,
     pub token_b_mint: &'a AccountInfo,
+
+    pub token_a_program: &'a AccountInfo,
+    pub token_b_program: &'a AccountInfo,
     pub token_program: &'a AccountInfo
 }
 
@@ -41,6 +44,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RemoveLiquidityAccounts<'a> {
             user_lp_token,
             token_a_mint,
             token_b_mint,
+            token_a_program,
+            token_b_program,
             token_program,
             _
         ] = accounts else {
@@ -50,9 +55,12 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RemoveLiquidityAccounts<'a> {
         <&AccountInfo as SignerAccount>::check(user)?;
         <&AccountInfo as MintInterface>::check(token_a_mint)?;
         <&AccountInfo as MintInterface>::check(token_b_mint)?;
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
 
-        AssociatedTokenAccount::check(token_a_vault, pool, token_a_mint)?;
-        AssociatedTokenAccount::check(token_b_vault, pool, token_b_mint)?;
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+        PoolAccount::check_lp_mint(pool, lp_mint)?;
 
         AssociatedTokenAccount::check(user_token_a, user, token_a_mint)?;
         AssociatedTokenAccount::check(user_token_b, user, token_b_mint)?;
@@ -70,6 +78,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RemoveLiquidityAccounts<'a> {
             user_lp_token,
             token_a_mint,
             token_b_mint,
+            token_a_program,
+            token_b_program,
             token_program
         })
     }
@@ -78,7 +88,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RemoveLiquidityAccounts<'a> {
 pub struct RemoveLiquidityData {
     pub lp_tokens: u64,
     pub min_amount_a: u64,
-    pub min_amount_b: u64
+    pub min_amount_b: u64,
+    pub deadline: Option<Deadline>,
 }
 
 impl TryFrom<&[u8]> for RemoveLiquidityData{
@@ -92,11 +103,13 @@ impl TryFrom<&[u8]> for RemoveLiquidityData{
         let lp_tokens = u64::from_le_bytes([data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7]]);
         let min_amount_a = u64::from_le_bytes([data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15]]);
         let min_amount_b = u64::from_le_bytes([data[16], data[17], data[18], data[19], data[20], data[21], data[22], data[23]]);
+        let deadline = Deadline::parse(&data[24..])?;
 
         Ok(Self { 
             lp_tokens, 
             min_amount_a, 
-            min_amount_b 
+            min_amount_b,
+            deadline,
         })
     }
 }
@@ -120,114 +133,159 @@ impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for RemoveLiquidity<'a> {
     }
 }
 
-impl<'a> RemoveLiquidity<'a> {
-    pub const DISCRIMINATOR: &'a u8 = &2;
-
-    pub fn process(&mut self) -> ProgramResult {
-        let token_a_vault_data = self.accounts.token_a_vault.try_borrow_data()?;
-        let token_a_vault = unsafe {
-            pinocchio_token::state::TokenAccount::from_bytes(&token_a_vault_data)
-        };
-        if token_a_vault.owner() != self.accounts.pool.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if token_a_vault.mint() != self.accounts.token_a_mint.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let reserve_a = token_a_vault.amount();
-
-        let token_b_vault_data = self.accounts.token_b_vault.try_borrow_data()?;
-        let token_b_vault = unsafe {
-            pinocchio_token::state::TokenAccount::from_bytes(&token_b_vault_data)
-        };
-        if token_b_vault.owner() != self.accounts.pool.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if token_b_vault.mint() != self.accounts.token_b_mint.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let reserve_b = token_b_vault.amount();
-
-        let lp_mint_data = self.accounts.lp_mint.try_borrow_data()?;
-        let lp_mint= unsafe {
-            pinocchio_token::state::Mint::from_bytes(&lp_mint_data)
-        };
-        if lp_mint.mint_authority() != Some(self.accounts.pool.key()) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let lp_suppy = lp_mint.supply();
+/// A withdrawal priced against the current reserves. `RemoveLiquidity` executes
+/// it and `QuoteRemoveLiquidity` returns it, so both run the same math.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemoveLiquidityQuote {
+    /// Sent by the vaults.
+    pub amount_a: u64,
+    pub amount_b: u64,
+    /// What reaches the user after transfer fees.
+    pub amount_a_received: u64,
+    pub amount_b_received: u64,
+}
 
-        if lp_suppy == 0 {
+impl RemoveLiquidityQuote {
+    pub const LEN: usize = 8 + 8 + 8 + 8;
+
+    pub fn new(
+        reserve_a: u64,
+        reserve_b: u64,
+        lp_supply: u64,
+        lp_tokens: u64,
+        transfer_fee_a: TransferFee,
+        transfer_fee_b: TransferFee,
+    ) -> Result<Self, ProgramError> {
+        if lp_supply == 0 {
             return Err(ProgramError::InsufficientFunds);
         }
-        if lp_suppy <= self.data.lp_tokens {
+        if lp_supply <= lp_tokens {
             return Err(ProgramError::InvalidArgument);
         }
 
-        let lp_tokens = self.data.lp_tokens;
-        let amount_a = lp_tokens
-            .checked_mul(reserve_a)
-            .ok_or(PinocchioError::MathOverflow)?
-            .checked_div(lp_suppy)
-            .ok_or(PinocchioError::MathOverflow)? as u64;
+        let amount_a = math::reserve_share(lp_tokens, reserve_a, lp_supply)?;
+        let amount_b = math::reserve_share(lp_tokens, reserve_b, lp_supply)?;
 
-        let amount_b = lp_tokens
-            .checked_mul(reserve_b) 
-            .ok_or(PinocchioError::MathOverflow)?
-            .checked_div(lp_suppy)
-            .ok_or(PinocchioError::MathOverflow)? as u64;
+        Ok(Self {
+            amount_a,
+            amount_b,
+            amount_a_received: transfer_fee_a.net_amount(amount_a)?,
+            amount_b_received: transfer_fee_b.net_amount(amount_b)?,
+        })
+    }
 
-        let min_amount_a = self.data.min_amount_a;
-        let min_amount_b = self.data.min_amount_b;
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.amount_a.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.amount_b.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.amount_a_received.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.amount_b_received.to_le_bytes());
+        bytes
+    }
+}
 
-        if amount_a < min_amount_a {
-            return Err(PinocchioError::SlippageExceeded.into());
-        }
-        if amount_b < min_amount_b {
-            return Err(PinocchioError::SlippageExceeded.into());
-        }
-        if amount_a == 0 && amount_b == 0 {
-            return Err(ProgramError::InvalidArgument);
-        }
+impl<'a> RemoveLiquidity<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &2;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
+
+        let transfer_fee_a = active_transfer_fee(self.accounts.token_a_mint, clock)?;
+        let transfer_fee_b = active_transfer_fee(self.accounts.token_b_mint, clock)?;
+
+        let (amount_a, amount_b, pool_bump) = {
+            let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+            let pool = Pool::load_mut(&mut pool_data)?;
+
+            if pool.is_disabled(Pool::REMOVE_LIQUIDITY_DISABLED) {
+                return Err(PinocchioError::RemoveLiquidityDisabled.into());
+            }
+
+            let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+                self.accounts.pool,
+                self.accounts.token_a_vault,
+                self.accounts.token_b_vault,
+                self.accounts.token_a_mint,
+                self.accounts.token_b_mint,
+            )?;
+
+            let (reserve_a, reserve_b) = pool.pricing_reserves(vault_a_amount, vault_b_amount)?;
+            pool.update_price_accumulators(reserve_a, reserve_b, clock.unix_timestamp)?;
+
+            let lp_mint_data = self.accounts.lp_mint.try_borrow_data()?;
+            let lp_mint= unsafe {
+                pinocchio_token::state::Mint::from_bytes(&lp_mint_data)
+            };
+            if lp_mint.mint_authority() != Some(self.accounts.pool.key()) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            let lp_supply = pool.total_lp_supply(lp_mint.supply())?;
+            let quote = RemoveLiquidityQuote::new(
+                reserve_a,
+                reserve_b,
+                lp_supply,
+                self.data.lp_tokens,
+                transfer_fee_a,
+                transfer_fee_b,
+            )?;
+
+            // Slippage is checked on what reaches the user after transfer fees.
+            if quote.amount_a_received < self.data.min_amount_a {
+                return Err(PinocchioError::SlippageExceeded.into());
+            }
+            if quote.amount_b_received < self.data.min_amount_b {
+                return Err(PinocchioError::SlippageExceeded.into());
+            }
+            if quote.amount_a == 0 && quote.amount_b == 0 {
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            (quote.amount_a, quote.amount_b, pool.bump)
+        };
 
         Burn {
             mint: self.accounts.lp_mint,
             authority: self.accounts.user,
-            amount: lp_tokens,
+            amount: self.data.lp_tokens,
             account: self.accounts.user_lp_token
         }.invoke()?;
 
-        let (_, pool_bump) = find_program_address(
-            &[b"pool", self.accounts.token_a_mint.key().as_ref(), self.accounts.token_b_mint.key().as_ref()], 
-            &crate::ID
-        );
-        
         let pool_bump_binding = [pool_bump];
-        let seeds = [
-            Seed::from("pool".as_bytes()),
-            Seed::from(self.accounts.token_a_mint.key().as_ref()),
-            Seed::from(self.accounts.token_b_mint.key().as_ref()),
-            Seed::from(pool_bump_binding.as_ref()),
-        ];
+        let seeds = PoolAccount::signer_seeds(self.accounts.token_a_mint, self.accounts.token_b_mint, &pool_bump_binding);
 
         let signers = [Signer::from(&seeds)];
-        Transfer {
+        TokenTransfer {
             from: self.accounts.token_a_vault,
+            mint: self.accounts.token_a_mint,
             to: self.accounts.user_token_a,
             authority: self.accounts.pool,
+            token_program: self.accounts.token_a_program,
             amount: amount_a,
         }.invoke_signed(&signers)?;
 
-        Transfer {
+        TokenTransfer {
             from: self.accounts.token_b_vault,
+            mint: self.accounts.token_b_mint,
             to: self.accounts.user_token_b,
             authority: self.accounts.pool,
+            token_program: self.accounts.token_b_program,
             amount: amount_b,
         }.invoke_signed(&signers)?;
 
+        Event::Withdraw(WithdrawEvent {
+            pool: *self.accounts.pool.key(),
+            user: *self.accounts.user.key(),
+            amount_a,
+            amount_b,
+            lp_tokens: self.data.lp_tokens,
+        }).emit();
+
         Ok(())
     }
 }
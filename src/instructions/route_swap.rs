@@ -0,0 +1,179 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{instructions::{execute_swap, SwapAccounts}, Deadline, PinocchioError};
+
+/// Accounts per hop after the shared `user`: pool, token_a_vault, token_b_vault,
+/// user_token_a, user_token_b, token_a_mint, token_b_mint, token_a_program, token_b_program.
+pub const ROUTE_HOP_ACCOUNTS: usize = 9;
+
+pub struct RouteSwapAccounts<'a> {
+    pub user: &'a AccountInfo,
+    pub hops: [Option<SwapAccounts<'a>>; RouteSwap::MAX_HOPS],
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for RouteSwapAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [user, hop_accounts @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        let hop_groups = hop_accounts.chunks_exact(ROUTE_HOP_ACCOUNTS);
+        if !hop_groups.remainder().is_empty() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        if hop_groups.len() == 0 || hop_groups.len() > RouteSwap::MAX_HOPS {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let mut hops = [const { None }; RouteSwap::MAX_HOPS];
+        for (hop, group) in hops.iter_mut().zip(hop_groups) {
+            let [
+                pool,
+                token_a_vault,
+                token_b_vault,
+                user_token_a,
+                user_token_b,
+                token_a_mint,
+                token_b_mint,
+                token_a_program,
+                token_b_program,
+            ] = group else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            *hop = Some(SwapAccounts::new(
+                user,
+                pool,
+                token_a_vault,
+                token_b_vault,
+                user_token_a,
+                user_token_b,
+                token_a_mint,
+                token_b_mint,
+                token_a_program,
+                token_b_program,
+            )?);
+        }
+
+        Ok(Self { user, hops })
+    }
+}
+
+impl<'a> RouteSwapAccounts<'a> {
+    pub fn hop_count(&self) -> usize {
+        self.hops.iter().flatten().count()
+    }
+
+    /// Checks that `data` has one direction per hop and that each hop spends
+    /// the mint the previous one produced.
+    pub fn check_route(&self, data: &RouteSwapData) -> Result<(), ProgramError> {
+        if self.hop_count() != data.hop_count {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let hops = self.hops.iter().flatten().zip(data.swap_directions);
+        for ((hop, swap_direction), (next_hop, next_swap_direction)) in hops.clone().zip(hops.skip(1)) {
+            if hop.output_mint(swap_direction).key() != next_hop.input_mint(next_swap_direction).key() {
+                return Err(PinocchioError::InvalidTokenMint.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub struct RouteSwapData {
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub hop_count: usize,
+    pub swap_directions: [bool; RouteSwap::MAX_HOPS], // per hop: true for A to B, false for B to A
+    pub deadline: Option<Deadline>,
+}
+
+impl TryFrom<&[u8]> for RouteSwapData {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() < 17 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let amount_in = u64::from_le_bytes(value[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let min_amount_out = u64::from_le_bytes(value[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        // A hop count, that many direction bytes, then an optional deadline.
+        let hop_count = value[16] as usize;
+        if hop_count == 0 || hop_count > RouteSwap::MAX_HOPS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let (directions, trailing) = value[17..]
+            .split_at_checked(hop_count)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+        let deadline = Deadline::parse(trailing)?;
+
+        if amount_in == 0 {
+            return Err(PinocchioError::InvalidAmount.into());
+        }
+
+        let mut swap_directions = [false; RouteSwap::MAX_HOPS];
+        for (swap_direction, direction) in swap_directions.iter_mut().zip(directions) {
+            *swap_direction = *direction != 0;
+        }
+
+        Ok(Self {
+            amount_in,
+            min_amount_out,
+            hop_count,
+            swap_directions,
+            deadline,
+        })
+    }
+}
+
+/// Multi-hop exact-input swap. Each hop is a `Swap` through one pool, fed with
+/// the previous hop's output via the user's token account for the shared mint.
+/// Only the final output is checked against `min_amount_out`.
+pub struct RouteSwap<'a> {
+    pub accounts: RouteSwapAccounts<'a>,
+    pub data: RouteSwapData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for RouteSwap<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, instruction_data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = RouteSwapAccounts::try_from(accounts)?;
+        let data = RouteSwapData::try_from(instruction_data)?;
+
+        accounts.check_route(&data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> RouteSwap<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &12;
+
+    pub const MAX_HOPS: usize = 4;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
+
+        let hop_count = self.data.hop_count;
+        let mut amount = self.data.amount_in;
+
+        // Intermediate outputs are only checked through the final `min_amount_out`.
+        for (index, hop) in self.accounts.hops.iter().flatten().enumerate() {
+            let min_amount_out = if index + 1 == hop_count { self.data.min_amount_out } else { 0 };
+            amount = execute_swap(hop, amount, min_amount_out, self.data.swap_directions[index], clock)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{events::{Event, PoolStatusChangedEvent}, PinocchioError, Pool, SignerAccount};
+
+pub struct SetPoolStatusAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetPoolStatusAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            authority,
+            pool,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(authority)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { authority, pool })
+    }
+}
+
+pub struct SetPoolStatusData {
+    pub status: u8,
+}
+
+impl TryFrom<&[u8]> for SetPoolStatusData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let status = data[0];
+
+        if status & !Pool::STATUS_MASK != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self { status })
+    }
+}
+
+pub struct SetPoolStatus<'a> {
+    pub accounts: SetPoolStatusAccounts<'a>,
+    pub data: SetPoolStatusData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for SetPoolStatus<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = SetPoolStatusAccounts::try_from(accounts)?;
+        let data = SetPoolStatusData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> SetPoolStatus<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &8;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if pool.authority != *self.accounts.authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+
+        let old_status = pool.status;
+        pool.status = self.data.status;
+
+        Event::PoolStatusChanged(PoolStatusChangedEvent {
+            pool: *self.accounts.pool.key(),
+            old_status,
+            new_status: pool.status,
+        }).emit();
+
+        Ok(())
+    }
+}
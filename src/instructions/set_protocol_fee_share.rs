@@ -0,0 +1,94 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, ProgramResult};
+
+use crate::{events::{Event, FeeChangedEvent}, PinocchioError, Pool, SignerAccount};
+
+pub struct SetProtocolFeeShareAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetProtocolFeeShareAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            authority,
+            pool,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(authority)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self { authority, pool })
+    }
+}
+
+pub struct SetProtocolFeeShareData {
+    pub protocol_fee_share: u16,
+}
+
+impl TryFrom<&[u8]> for SetProtocolFeeShareData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 2 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let protocol_fee_share = u16::from_le_bytes([data[0], data[1]]);
+
+        if protocol_fee_share > Pool::MAX_PROTOCOL_FEE_SHARE {
+            return Err(PinocchioError::InvalidFeeRate.into());
+        }
+
+        Ok(Self { protocol_fee_share })
+    }
+}
+
+pub struct SetProtocolFeeShare<'a> {
+    pub accounts: SetProtocolFeeShareAccounts<'a>,
+    pub data: SetProtocolFeeShareData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for SetProtocolFeeShare<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = SetProtocolFeeShareAccounts::try_from(accounts)?;
+        let data = SetProtocolFeeShareData::try_from(data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> SetProtocolFeeShare<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &9;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if pool.authority != *self.accounts.authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+
+        let old_protocol_fee_share = pool.protocol_fee_share;
+        pool.protocol_fee_share = self.data.protocol_fee_share;
+
+        Event::FeeChanged(FeeChangedEvent {
+            pool: *self.accounts.pool.key(),
+            old_fee_rate: pool.fee_rate,
+            new_fee_rate: pool.fee_rate,
+            old_protocol_fee_share,
+            new_protocol_fee_share: pool.protocol_fee_share,
+        }).emit();
+
+        Ok(())
+    }
+}
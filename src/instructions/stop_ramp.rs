@@ -0,0 +1,52 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{
+    events::{AmplificationRampEvent, Event}, instructions::RampAmplificationAccounts, PinocchioError, Pool,
+};
+
+/// Ends an amplification ramp early, holding `A` at its current value. Takes
+/// the same accounts as `RampAmplification` and no data.
+pub struct StopRamp<'a> {
+    pub accounts: RampAmplificationAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for StopRamp<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, _data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = RampAmplificationAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> StopRamp<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &21;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+        let pool = Pool::load_mut(pool_data.as_mut())?;
+
+        if pool.authority != *self.accounts.authority.key() {
+            return Err(PinocchioError::Unauthorized.into());
+        }
+
+        let now = clock.unix_timestamp;
+        pool.stop_ramp(now)?;
+
+        Event::AmplificationRamp(AmplificationRampEvent {
+            pool: *self.accounts.pool.key(),
+            start_amplification: pool.amplification,
+            target_amplification: pool.target_amplification,
+            start_timestamp: pool.ramp_start_timestamp,
+            end_timestamp: pool.ramp_end_timestamp,
+        }).emit();
+
+        Ok(())
+    }
+}
@@ -1,7 +1,6 @@
-use pinocchio::{account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::find_program_address, ProgramResult};
-use pinocchio_token::instructions::Transfer;
+use pinocchio::{account_info::AccountInfo, instruction::Signer, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
 
-use crate::{AssociatedTokenAccount, AssociatedTokenAccountCheck, MintInterface, PinocchioError, Pool, SignerAccount};
+use crate::{events::{Event, SwapEvent}, math, token_interface::{active_transfer_fee, TokenTransfer, TransferFee}, Deadline, AssociatedTokenAccount, AssociatedTokenAccountCheck, MintInterface, Observations, PinocchioError, Pool, PoolAccount, PoolAccountCheck, SignerAccount, TokenProgram, TokenProgramCheck};
 
 pub struct SwapAccounts<'a> {
     pub user: &'a AccountInfo, 
@@ -15,7 +14,12 @@ pub struct SwapAccounts<'a> {
 
     pub token_a_mint: &'a AccountInfo,
     pub token_b_mint: &'a AccountInfo,
-    pub token_program: &'a AccountInfo,
+
+    pub token_a_program: &'a AccountInfo,
+    pub token_b_program: &'a AccountInfo,
+
+    /// The pool's oracle account, if the caller passes one after the fixed accounts.
+    pub observations: Option<&'a AccountInfo>,
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for SwapAccounts<'a> {
@@ -31,18 +35,59 @@ impl<'a> TryFrom<&'a [AccountInfo]> for SwapAccounts<'a> {
             user_token_b,
             token_a_mint,
             token_b_mint,
-            token_program,
-            _
+            token_a_program,
+            token_b_program,
+            _,
+            optional @ ..
         ] = value else {
             return Err(ProgramError::NotEnoughAccountKeys);
         };
 
+        let mut accounts = Self::new(
+            user,
+            pool,
+            token_a_vault,
+            token_b_vault,
+            user_token_a,
+            user_token_b,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+        )?;
+
+        if let Some(observations) = optional.first() {
+            PoolAccount::check_observations(pool, observations)?;
+            accounts.observations = Some(observations);
+        }
+
+        Ok(accounts)
+    }
+}
+
+impl<'a> SwapAccounts<'a> {
+    /// Validates one pool's swap accounts. Shared by `Swap` and every hop of `RouteSwap`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user: &'a AccountInfo,
+        pool: &'a AccountInfo,
+        token_a_vault: &'a AccountInfo,
+        token_b_vault: &'a AccountInfo,
+        user_token_a: &'a AccountInfo,
+        user_token_b: &'a AccountInfo,
+        token_a_mint: &'a AccountInfo,
+        token_b_mint: &'a AccountInfo,
+        token_a_program: &'a AccountInfo,
+        token_b_program: &'a AccountInfo,
+    ) -> Result<Self, ProgramError> {
         <&AccountInfo as SignerAccount>::check(user)?;
         <&AccountInfo as MintInterface>::check(token_a_mint)?;
         <&AccountInfo as MintInterface>::check(token_b_mint)?;
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
 
-        AssociatedTokenAccount::check(token_a_vault, pool, token_a_mint)?;
-        AssociatedTokenAccount::check(token_b_vault, pool, token_b_mint)?;
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
 
         AssociatedTokenAccount::check(user_token_a, user, token_a_mint)?;
         AssociatedTokenAccount::check(user_token_b, user, token_b_mint)?;
@@ -56,15 +101,82 @@ impl<'a> TryFrom<&'a [AccountInfo]> for SwapAccounts<'a> {
             user_token_b,
             token_a_mint,
             token_b_mint,
-            token_program
+            token_a_program,
+            token_b_program,
+            observations: None,
         })
     }
+
+    /// Output mint for a swap in `swap_direction` through this pool.
+    pub fn output_mint(&self, swap_direction: bool) -> &'a AccountInfo {
+        if swap_direction { self.token_b_mint } else { self.token_a_mint }
+    }
+
+    /// Input mint for a swap in `swap_direction` through this pool.
+    pub fn input_mint(&self, swap_direction: bool) -> &'a AccountInfo {
+        if swap_direction { self.token_a_mint } else { self.token_b_mint }
+    }
+
+    /// Brings `pool`'s oracle up to `clock` and returns the (input, output)
+    /// reserves for `swap_direction`. Unclaimed protocol fees sit in the vaults
+    /// but are not part of the curve.
+    pub(crate) fn sync_reserves(&self, pool: &mut Pool, swap_direction: bool, clock: &Clock) -> Result<(u64, u64), ProgramError> {
+        let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+            self.pool,
+            self.token_a_vault,
+            self.token_b_vault,
+            self.token_a_mint,
+            self.token_b_mint,
+        )?;
+
+        let (reserve_a, reserve_b) = pool.pricing_reserves(vault_a_amount, vault_b_amount)?;
+        pool.update_price_accumulators(reserve_a, reserve_b, clock.unix_timestamp)?;
+        if let Some(observations) = self.observations {
+            let mut observations_data = observations.try_borrow_mut_data()?;
+            Observations::load_mut(&mut observations_data)?.sync(pool, reserve_a, reserve_b, clock);
+        }
+
+        Ok(if swap_direction { (reserve_a, reserve_b) } else { (reserve_b, reserve_a) })
+    }
+
+    /// Moves `amount_in` from the user into the input vault, then `amount_out`
+    /// from the output vault to the user with the pool's signature.
+    pub(crate) fn settle(&self, swap_direction: bool, amount_in: u64, amount_out: u64, pool_bump: u8) -> ProgramResult {
+        let (user_in, vault_in, program_in, vault_out, user_out, program_out) = if swap_direction {
+                (self.user_token_a, self.token_a_vault, self.token_a_program, self.token_b_vault, self.user_token_b, self.token_b_program)
+            } else {
+                (self.user_token_b, self.token_b_vault, self.token_b_program, self.token_a_vault, self.user_token_a, self.token_a_program)
+            };
+
+        TokenTransfer {
+            from: user_in,
+            mint: self.input_mint(swap_direction),
+            to: vault_in,
+            authority: self.user,
+            token_program: program_in,
+            amount: amount_in,
+        }.invoke()?;
+
+        let pool_bump_binding = [pool_bump];
+        let seeds = PoolAccount::signer_seeds(self.token_a_mint, self.token_b_mint, &pool_bump_binding);
+        let signers = [Signer::from(&seeds)];
+
+        TokenTransfer {
+            from: vault_out,
+            mint: self.output_mint(swap_direction),
+            to: user_out,
+            authority: self.pool,
+            token_program: program_out,
+            amount: amount_out,
+        }.invoke_signed(&signers)
+    }
 }
 
 pub struct SwapData {
     pub amount_in: u64,
     pub min_amount_out: u64,
     pub swap_direction: bool, // true for A to B, false for B to A
+    pub deadline: Option<Deadline>,
 }
 
 impl TryFrom<&[u8]> for SwapData {
@@ -77,11 +189,13 @@ impl TryFrom<&[u8]> for SwapData {
         let amount_in = u64::from_le_bytes(value[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
         let min_amount_out = u64::from_le_bytes(value[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
         let swap_direction = value[16] != 0;
+        let deadline = Deadline::parse(&value[17..])?;
 
         Ok(Self {
             amount_in,
             min_amount_out,
             swap_direction,
+            deadline,
         })
     }
 }
@@ -106,90 +220,120 @@ impl<'a> Swap<'a> {
     pub const DISCRIMINATOR: &'a u8 = &3;
 
     pub fn process(&mut self) -> ProgramResult {
-        let token_a_vault_data = self.accounts.token_a_vault.try_borrow_data()?;
-        let token_a_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_a_vault_data) };
-        
-        let token_b_vault_data = self.accounts.token_b_vault.try_borrow_data()?;
-        let token_b_vault = unsafe { pinocchio_token::state::TokenAccount::from_bytes(&token_b_vault_data) };
-
-        if token_a_vault.owner() != self.accounts.pool.key() || token_a_vault.mint() != self.accounts.token_a_mint.key() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if token_b_vault.owner() != self.accounts.pool.key() || token_b_vault.mint() != self.accounts.token_b_mint.key() {
-            return Err(ProgramError::InvalidAccountData);
-       }
+        self.process_at(&Clock::get()?)
+    }
 
-        let (reserve_a, reserve_b) = if self.data.swap_direction {
-                (token_a_vault.amount(), token_b_vault.amount())
-            } else {
-                (token_b_vault.amount(), token_a_vault.amount())
-            };
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
+
+        execute_swap(&self.accounts, self.data.amount_in, self.data.min_amount_out, self.data.swap_direction, clock)?;
+
+        Ok(())
+    }
+}
+
+/// An exact-input swap priced against the current reserves. `Swap` executes it and
+/// `QuoteSwap` returns it, so both run the same math.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Sent by the output vault.
+    pub amount_out: u64,
+    /// What reaches the user after the output mint's transfer fee.
+    pub amount_received: u64,
+    /// Swap fee in the input token, protocol share included.
+    pub fee: u64,
+    pub protocol_fee: u64,
+    /// Shortfall against the spot price, fees excluded, in basis points.
+    pub price_impact_bps: u16,
+}
+
+impl SwapQuote {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 2;
 
-        let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+    /// Quotes sending `amount_in` at unix time `now`. The curve only sees what reaches the input vault.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        pool: &Pool,
+        now: i64,
+        reserve_in: u64,
+        reserve_out: u64,
+        a_to_b: bool,
+        amount_in: u64,
+        input_transfer_fee: TransferFee,
+        output_transfer_fee: TransferFee,
+    ) -> Result<Self, ProgramError> {
+        let curve = pool.curve_at(now)?;
+        let amount_in_net = input_transfer_fee.net_amount(amount_in)?;
+        let (amount_out, fee) = curve.get_amount_out(amount_in_net, reserve_in, reserve_out, a_to_b, pool.fee_rate)?;
+        let spot_amount_out = curve.spot_amount_out((amount_in_net - fee) as u128, reserve_in, reserve_out, a_to_b)?;
+
+        Ok(Self {
+            amount_out,
+            amount_received: output_transfer_fee.net_amount(amount_out)?,
+            fee,
+            protocol_fee: pool.protocol_fee(fee)?,
+            price_impact_bps: math::shortfall_bps(spot_amount_out, amount_out),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut bytes = [0u8; Self::LEN];
+        bytes[0..8].copy_from_slice(&self.amount_out.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.amount_received.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.fee.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.protocol_fee.to_le_bytes());
+        bytes[32..34].copy_from_slice(&self.price_impact_bps.to_le_bytes());
+        bytes
+    }
+}
+
+/// Prices an exact-input swap against `accounts.pool`, books the protocol fee and
+/// moves the tokens. Returns the amount the user receives.
+///
+/// With transfer-fee mints the curve only sees what reaches the input vault, and
+/// `min_amount_out` is checked against what reaches the user.
+pub(crate) fn execute_swap(
+    accounts: &SwapAccounts,
+    amount_in: u64,
+    min_amount_out: u64,
+    swap_direction: bool,
+    clock: &Clock,
+) -> Result<u64, ProgramError> {
+    let input_transfer_fee = active_transfer_fee(accounts.input_mint(swap_direction), clock)?;
+    let output_transfer_fee = active_transfer_fee(accounts.output_mint(swap_direction), clock)?;
+
+    let (quote, pool_bump) = {
+        let mut pool_data = accounts.pool.try_borrow_mut_data()?;
         let pool = Pool::load_mut(&mut pool_data)?;
-        let fee_rate = pool.fee_rate as u64;
 
-        let amount_in_with_fee = self.data.amount_in
-            .checked_mul(10000 - fee_rate)
-            .ok_or(PinocchioError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(PinocchioError::MathOverflow)?;
+        if pool.is_disabled(Pool::SWAP_DISABLED) {
+            return Err(PinocchioError::SwapDisabled.into());
+        }
 
-        let amount_out = amount_in_with_fee
-            .checked_mul(reserve_b)
-            .ok_or(PinocchioError::MathOverflow)?
-            .checked_div(reserve_a + amount_in_with_fee)
-            .ok_or(PinocchioError::MathOverflow)?;
+        let (reserve_in, reserve_out) = accounts.sync_reserves(pool, swap_direction, clock)?;
 
-        if amount_out < self.data.min_amount_out {
+        let quote = SwapQuote::new(pool, clock.unix_timestamp, reserve_in, reserve_out, swap_direction, amount_in, input_transfer_fee, output_transfer_fee)?;
+        if quote.amount_received < min_amount_out {
             return Err(PinocchioError::SlippageExceeded.into());
         }
 
-        let (_, pool_bump) = find_program_address(
-            &[b"pool", self.accounts.token_a_mint.key().as_ref(), self.accounts.token_b_mint.key().as_ref()], 
-            &crate::ID
-        );
-        
-        let pool_bump_binding = [pool_bump];
-        let seeds = [
-            Seed::from("pool".as_bytes()),
-            Seed::from(self.accounts.token_a_mint.key().as_ref()),
-            Seed::from(self.accounts.token_b_mint.key().as_ref()),
-            Seed::from(pool_bump_binding.as_ref()),
-        ];
+        pool.accrue_protocol_fee(quote.fee, swap_direction)?;
 
-        let signers = [Signer::from(&seeds)];
+        (quote, pool.bump)
+    };
 
-        if self.data.swap_direction {
-            Transfer {
-                from: self.accounts.user_token_a,
-                to: self.accounts.token_b_vault,
-                authority: self.accounts.user,
-                amount: self.data.amount_in,
-            }.invoke()?;
-
-            Transfer {
-                from: self.accounts.token_b_vault,
-                to: self.accounts.user_token_b,
-                authority: self.accounts.pool,
-                amount: amount_out,
-            }.invoke_signed(&signers)?;
-        }else {
-            Transfer {
-                from: self.accounts.user_token_b,
-                to: self.accounts.token_a_vault,
-                authority: self.accounts.user,
-                amount: self.data.amount_in,
-            }.invoke()?;
-
-            Transfer {
-                from: self.accounts.token_a_vault,
-                to: self.accounts.user_token_a,
-                authority: self.accounts.pool,
-                amount: amount_out,
-            }.invoke_signed(&signers)?;
-        }
+    accounts.settle(swap_direction, amount_in, quote.amount_out, pool_bump)?;
 
-        Ok(())
-    }
-}
\ No newline at end of file
+    Event::Swap(SwapEvent {
+        pool: *accounts.pool.key(),
+        user: *accounts.user.key(),
+        a_to_b: swap_direction,
+        amount_in,
+        amount_out: quote.amount_out,
+        fee: quote.fee,
+        protocol_fee: quote.protocol_fee,
+    }).emit();
+
+    Ok(quote.amount_received)
+}
@@ -0,0 +1,113 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+
+use crate::{events::{Event, SwapEvent}, instructions::SwapAccounts, token_interface::active_transfer_fee, Deadline, PinocchioError, Pool};
+
+pub struct SwapExactOutData {
+    pub amount_out: u64,
+    pub max_amount_in: u64,
+    pub swap_direction: bool, // true for A to B, false for B to A
+    pub deadline: Option<Deadline>,
+}
+
+impl TryFrom<&[u8]> for SwapExactOutData {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        if value.len() < 17 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let amount_out = u64::from_le_bytes(value[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let max_amount_in = u64::from_le_bytes(value[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let swap_direction = value[16] != 0;
+        let deadline = Deadline::parse(&value[17..])?;
+
+        if amount_out == 0 {
+            return Err(PinocchioError::InvalidAmount.into());
+        }
+
+        Ok(Self {
+            amount_out,
+            max_amount_in,
+            swap_direction,
+            deadline,
+        })
+    }
+}
+
+/// Exact-output swap: the user receives exactly `amount_out` and pays whatever
+/// the curve requires, up to `max_amount_in`. Takes the same accounts as `Swap`.
+pub struct SwapExactOut<'a> {
+    pub accounts: SwapAccounts<'a>,
+    pub data: SwapExactOutData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for SwapExactOut<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, instruction_data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = SwapAccounts::try_from(accounts)?;
+        let data = SwapExactOutData::try_from(instruction_data)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> SwapExactOut<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &11;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
+
+        let input_transfer_fee = active_transfer_fee(self.accounts.input_mint(self.data.swap_direction), clock)?;
+        let output_transfer_fee = active_transfer_fee(self.accounts.output_mint(self.data.swap_direction), clock)?;
+
+        let (amount_in, amount_out, fee, protocol_fee, pool_bump) = {
+            let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+            let pool = Pool::load_mut(&mut pool_data)?;
+
+            if pool.is_disabled(Pool::SWAP_DISABLED) {
+                return Err(PinocchioError::SwapDisabled.into());
+            }
+
+            let (reserve_in, reserve_out) = self.accounts.sync_reserves(pool, self.data.swap_direction, clock)?;
+
+            // The vault sends enough for `amount_out` to arrive after the output
+            // mint's transfer fee, and the user sends enough for the curve's input
+            // to arrive after the input mint's.
+            let amount_out = output_transfer_fee.gross_amount(self.data.amount_out)?;
+
+            // Rounded up, so the pool never gives away more than the curve allows.
+            let curve = pool.curve_at(clock.unix_timestamp)?;
+            let amount_in_net = curve.get_amount_in(amount_out, reserve_in, reserve_out, self.data.swap_direction, pool.fee_rate)?;
+            let amount_in = input_transfer_fee.gross_amount(amount_in_net)?;
+            if amount_in > self.data.max_amount_in {
+                return Err(PinocchioError::SlippageExceeded.into());
+            }
+
+            // Book the fee exactly as an exact-input swap of `amount_in_net` would.
+            let (_, fee) = curve.get_amount_out(amount_in_net, reserve_in, reserve_out, self.data.swap_direction, pool.fee_rate)?;
+            pool.accrue_protocol_fee(fee, self.data.swap_direction)?;
+
+            (amount_in, amount_out, fee, pool.protocol_fee(fee)?, pool.bump)
+        };
+
+        self.accounts.settle(self.data.swap_direction, amount_in, amount_out, pool_bump)?;
+
+        Event::Swap(SwapEvent {
+            pool: *self.accounts.pool.key(),
+            user: *self.accounts.user.key(),
+            a_to_b: self.data.swap_direction,
+            amount_in,
+            amount_out,
+            fee,
+            protocol_fee,
+        }).emit();
+
+        Ok(())
+    }
+}
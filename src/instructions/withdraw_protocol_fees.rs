@@ -0,0 +1,153 @@
+use pinocchio::{account_info::AccountInfo, instruction::Signer, program_error::ProgramError, ProgramResult};
+
+use crate::{events::{Event, ProtocolFeesWithdrawnEvent}, instructions::TokenAccount, token_interface::TokenTransfer, AccountCheck, MintInterface, PinocchioError, Pool, PoolAccount, SignerAccount, TokenProgram, TokenProgramCheck};
+
+pub struct WithdrawProtocolFeesAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub pool: &'a AccountInfo,
+
+    pub token_a_vault: &'a AccountInfo,
+    pub token_b_vault: &'a AccountInfo,
+
+    pub treasury_token_a: &'a AccountInfo,
+    pub treasury_token_b: &'a AccountInfo,
+
+    pub token_a_mint: &'a AccountInfo,
+    pub token_b_mint: &'a AccountInfo,
+
+    pub token_a_program: &'a AccountInfo,
+    pub token_b_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawProtocolFeesAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            authority,
+            pool,
+            token_a_vault,
+            token_b_vault,
+            treasury_token_a,
+            treasury_token_b,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+            _
+        ] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(authority)?;
+        <&AccountInfo as MintInterface>::check(token_a_mint)?;
+        <&AccountInfo as MintInterface>::check(token_b_mint)?;
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
+
+        if !pool.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        TokenAccount::check(treasury_token_a)?;
+        TokenAccount::check(treasury_token_b)?;
+
+        Ok(Self {
+            authority,
+            pool,
+            token_a_vault,
+            token_b_vault,
+            treasury_token_a,
+            treasury_token_b,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+        })
+    }
+}
+
+pub struct WithdrawProtocolFees<'a> {
+    pub accounts: WithdrawProtocolFeesAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for WithdrawProtocolFees<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, _data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = WithdrawProtocolFeesAccounts::try_from(accounts)?;
+
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> WithdrawProtocolFees<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &10;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let (amount_a, amount_b, pool_bump) = {
+            let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+            let pool = Pool::load_mut(pool_data.as_mut())?;
+
+            if pool.authority != *self.accounts.authority.key() {
+                return Err(PinocchioError::Unauthorized.into());
+            }
+
+            if pool.token_a_vault != *self.accounts.token_a_vault.key() || pool.token_b_vault != *self.accounts.token_b_vault.key() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if pool.token_a_mint != *self.accounts.token_a_mint.key() || pool.token_b_mint != *self.accounts.token_b_mint.key() {
+                return Err(PinocchioError::InvalidTokenMint.into());
+            }
+
+            let amount_a = pool.protocol_fees_a;
+            let amount_b = pool.protocol_fees_b;
+
+            if amount_a == 0 && amount_b == 0 {
+                return Err(PinocchioError::InvalidAmount.into());
+            }
+
+            pool.protocol_fees_a = 0;
+            pool.protocol_fees_b = 0;
+
+            (amount_a, amount_b, pool.bump)
+        };
+
+        let pool_bump_binding = [pool_bump];
+        let seeds = PoolAccount::signer_seeds(self.accounts.token_a_mint, self.accounts.token_b_mint, &pool_bump_binding);
+
+        let signers = [Signer::from(&seeds)];
+
+        if amount_a > 0 {
+            TokenTransfer {
+                from: self.accounts.token_a_vault,
+                mint: self.accounts.token_a_mint,
+                to: self.accounts.treasury_token_a,
+                authority: self.accounts.pool,
+                token_program: self.accounts.token_a_program,
+                amount: amount_a,
+            }.invoke_signed(&signers)?;
+        }
+
+        if amount_b > 0 {
+            TokenTransfer {
+                from: self.accounts.token_b_vault,
+                mint: self.accounts.token_b_mint,
+                to: self.accounts.treasury_token_b,
+                authority: self.accounts.pool,
+                token_program: self.accounts.token_b_program,
+                amount: amount_b,
+            }.invoke_signed(&signers)?;
+        }
+
+        Event::ProtocolFeesWithdrawn(ProtocolFeesWithdrawnEvent {
+            pool: *self.accounts.pool.key(),
+            treasury_token_a: *self.accounts.treasury_token_a.key(),
+            treasury_token_b: *self.accounts.treasury_token_b.key(),
+            amount_a,
+            amount_b,
+        }).emit();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,279 @@
+use pinocchio::{account_info::AccountInfo, instruction::Signer, program_error::ProgramError, sysvars::{clock::Clock, Sysvar}, ProgramResult};
+use pinocchio_token::instructions::Burn;
+
+use crate::{
+    events::{Event, WithdrawEvent}, math, token_interface::{active_transfer_fee, TokenTransfer}, AssociatedTokenAccount, AssociatedTokenAccountCheck, Deadline, MintInterface,
+    Observations, PinocchioError, Pool, PoolAccount, PoolAccountCheck, SignerAccount, TokenProgram, TokenProgramCheck,
+};
+
+pub struct WithdrawSingleAccounts<'a> {
+    pub user: &'a AccountInfo,
+
+    pub pool: &'a AccountInfo,
+    pub lp_mint: &'a AccountInfo,
+
+    pub token_a_vault: &'a AccountInfo,
+    pub token_b_vault: &'a AccountInfo,
+
+    /// The user's account for the side being withdrawn.
+    pub user_token: &'a AccountInfo,
+    pub user_lp_token: &'a AccountInfo,
+
+    pub token_a_mint: &'a AccountInfo,
+    pub token_b_mint: &'a AccountInfo,
+
+    pub token_a_program: &'a AccountInfo,
+    pub token_b_program: &'a AccountInfo,
+
+    pub token_program: &'a AccountInfo,
+
+    /// The pool's oracle account, if the caller passes one after the fixed accounts.
+    pub observations: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawSingleAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(value: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [
+            user,
+            pool,
+            lp_mint,
+            token_a_vault,
+            token_b_vault,
+            user_token,
+            user_lp_token,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+            token_program,
+            _,
+            optional @ ..
+        ] = value else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        <&AccountInfo as SignerAccount>::check(user)?;
+        <&AccountInfo as MintInterface>::check(token_a_mint)?;
+        <&AccountInfo as MintInterface>::check(token_b_mint)?;
+        TokenProgram::check(token_a_program, token_a_mint)?;
+        TokenProgram::check(token_b_program, token_b_mint)?;
+
+        PoolAccount::check(pool, token_a_mint, token_b_mint)?;
+        PoolAccount::check_vaults(pool, token_a_vault, token_b_vault)?;
+        PoolAccount::check_lp_mint(pool, lp_mint)?;
+
+        let observations = optional.first();
+        if let Some(observations) = observations {
+            PoolAccount::check_observations(pool, observations)?;
+        }
+
+        AssociatedTokenAccount::check(user_lp_token, user, lp_mint)?;
+
+        Ok(Self {
+            user,
+            pool,
+            lp_mint,
+            token_a_vault,
+            token_b_vault,
+            user_token,
+            user_lp_token,
+            token_a_mint,
+            token_b_mint,
+            token_a_program,
+            token_b_program,
+            token_program,
+            observations,
+        })
+    }
+}
+
+pub struct WithdrawSingleData {
+    pub lp_tokens: u64,
+    pub min_amount_out: u64,
+    pub withdraw_a: bool, // true to receive token A, false to receive token B
+    pub deadline: Option<Deadline>,
+}
+
+impl TryFrom<&[u8]> for WithdrawSingleData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() < 17 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let lp_tokens = u64::from_le_bytes(data[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let min_amount_out = u64::from_le_bytes(data[8..16].try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+        let withdraw_a = data[16] != 0;
+        let deadline = Deadline::parse(&data[17..])?;
+
+        if lp_tokens == 0 {
+            return Err(PinocchioError::InvalidAmount.into());
+        }
+
+        Ok(Self {
+            lp_tokens,
+            min_amount_out,
+            withdraw_a,
+            deadline,
+        })
+    }
+}
+
+/// Single-sided withdrawal ("zap out"): burns LP for its proportional share of
+/// both reserves, then swaps the unwanted side back into the pool through the
+/// curve, fee included, and pays out only the requested token.
+///
+/// The swapped side never leaves its vault, so the only transfer is the payout.
+pub struct WithdrawSingle<'a> {
+    pub accounts: WithdrawSingleAccounts<'a>,
+    pub data: WithdrawSingleData,
+}
+
+impl<'a> TryFrom<(&'a [AccountInfo], &'a [u8])> for WithdrawSingle<'a> {
+    type Error = ProgramError;
+
+    fn try_from((accounts, data): (&'a [AccountInfo], &'a [u8])) -> Result<Self, Self::Error> {
+        let accounts = WithdrawSingleAccounts::try_from(accounts)?;
+        let data = WithdrawSingleData::try_from(data)?;
+
+        let output_mint = if data.withdraw_a { accounts.token_a_mint } else { accounts.token_b_mint };
+        AssociatedTokenAccount::check(accounts.user_token, accounts.user, output_mint)?;
+
+        Ok(Self { accounts, data })
+    }
+}
+
+impl<'a> WithdrawSingle<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &14;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.process_at(&Clock::get()?)
+    }
+
+    /// [`Self::process`] at `clock`'s time.
+    pub fn process_at(&mut self, clock: &Clock) -> ProgramResult {
+        Deadline::enforce(self.data.deadline, clock)?;
+
+        let output_mint = if self.data.withdraw_a { self.accounts.token_a_mint } else { self.accounts.token_b_mint };
+        let transfer_fee = active_transfer_fee(output_mint, clock)?;
+
+        let (amount_out, pool_bump) = {
+            let mut pool_data = self.accounts.pool.try_borrow_mut_data()?;
+            let pool = Pool::load_mut(&mut pool_data)?;
+
+            if pool.is_disabled(Pool::REMOVE_LIQUIDITY_DISABLED) {
+                return Err(PinocchioError::RemoveLiquidityDisabled.into());
+            }
+            if pool.is_disabled(Pool::SWAP_DISABLED) {
+                return Err(PinocchioError::SwapDisabled.into());
+            }
+
+            let (vault_a_amount, vault_b_amount) = PoolAccount::vault_amounts(
+                self.accounts.pool,
+                self.accounts.token_a_vault,
+                self.accounts.token_b_vault,
+                self.accounts.token_a_mint,
+                self.accounts.token_b_mint,
+            )?;
+
+            let lp_mint_data = self.accounts.lp_mint.try_borrow_data()?;
+            let lp_mint = unsafe { pinocchio_token::state::Mint::from_bytes(&lp_mint_data) };
+            if lp_mint.mint_authority() != Some(self.accounts.pool.key()) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            let (reserve_a, reserve_b) = pool.pricing_reserves(vault_a_amount, vault_b_amount)?;
+            pool.update_price_accumulators(reserve_a, reserve_b, clock.unix_timestamp)?;
+            if let Some(observations) = self.accounts.observations {
+                let mut observations_data = observations.try_borrow_mut_data()?;
+                Observations::load_mut(&mut observations_data)?.sync(pool, reserve_a, reserve_b, clock);
+            }
+
+            let lp_supply = pool.total_lp_supply(lp_mint.supply())?;
+            if lp_supply == 0 {
+                return Err(ProgramError::InsufficientFunds);
+            }
+            if lp_supply <= self.data.lp_tokens {
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            let (reserve_keep, reserve_swap) = if self.data.withdraw_a {
+                    (reserve_a, reserve_b)
+                } else {
+                    (reserve_b, reserve_a)
+                };
+
+            let amount_keep = math::reserve_share(self.data.lp_tokens, reserve_keep, lp_supply)?;
+            let amount_swap = math::reserve_share(self.data.lp_tokens, reserve_swap, lp_supply)?;
+
+            // Sell the unwanted share back into the reserves left after the burn.
+            let (swap_output, fee) = if amount_swap > 0 {
+                pool.curve_at(clock.unix_timestamp)?.get_amount_out(
+                    amount_swap,
+                    reserve_swap - amount_swap,
+                    reserve_keep - amount_keep,
+                    !self.data.withdraw_a,
+                    pool.fee_rate,
+                )?
+            } else {
+                (0, 0)
+            };
+
+            let amount_out = amount_keep
+                .checked_add(swap_output)
+                .ok_or(PinocchioError::MathOverflow)?;
+
+            if amount_out == 0 {
+                return Err(ProgramError::InvalidArgument);
+            }
+            // Slippage is checked on what reaches the user after the transfer fee.
+            if transfer_fee.net_amount(amount_out)? < self.data.min_amount_out {
+                return Err(PinocchioError::SlippageExceeded.into());
+            }
+
+            pool.accrue_protocol_fee(fee, !self.data.withdraw_a)?;
+
+            (amount_out, pool.bump)
+        };
+
+        Burn {
+            mint: self.accounts.lp_mint,
+            authority: self.accounts.user,
+            amount: self.data.lp_tokens,
+            account: self.accounts.user_lp_token
+        }.invoke()?;
+
+        let pool_bump_binding = [pool_bump];
+        let seeds = PoolAccount::signer_seeds(self.accounts.token_a_mint, self.accounts.token_b_mint, &pool_bump_binding);
+
+        let signers = [Signer::from(&seeds)];
+
+        let (vault, token_program) = if self.data.withdraw_a {
+                (self.accounts.token_a_vault, self.accounts.token_a_program)
+            } else {
+                (self.accounts.token_b_vault, self.accounts.token_b_program)
+            };
+        TokenTransfer {
+            from: vault,
+            mint: output_mint,
+            to: self.accounts.user_token,
+            authority: self.accounts.pool,
+            token_program,
+            amount: amount_out,
+        }.invoke_signed(&signers)?;
+
+        let (amount_a, amount_b) = if self.data.withdraw_a { (amount_out, 0) } else { (0, amount_out) };
+        Event::Withdraw(WithdrawEvent {
+            pool: *self.accounts.pool.key(),
+            user: *self.accounts.user.key(),
+            amount_a,
+            amount_b,
+            lp_tokens: self.data.lp_tokens,
+        }).emit();
+
+        Ok(())
+    }
+}
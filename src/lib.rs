@@ -7,19 +7,31 @@ use pinocchio::entrypoint;
 #[cfg(not(test))]
 entrypoint!(process_instruction);
 
+pub mod curve;
+pub mod deadline;
 pub mod error;
+pub mod events;
+pub mod math;
 pub mod states;
 pub mod instructions;
+pub mod token_interface;
 
 pub use instructions::{
-    SignerAccount, MintInterface, AccountCheck, ProgramAccount, ProgramAccountInit,
-    AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit,
+    SignerAccount, MintInterface, MintAccount, MintInit, AccountCheck, ProgramAccount, ProgramAccountInit,
+    AssociatedTokenAccount, AssociatedTokenAccountCheck, AssociatedTokenAccountInit, PoolAccount, PoolAccountCheck,
+    TokenProgram, TokenProgramCheck,
 };
-pub use states::Pool;
+pub use states::{Observation, Observations, Pool};
 pub use error::PinocchioError;
+pub use curve::Curve;
+pub use deadline::Deadline;
 
 #[cfg(not(test))]
-use crate::instructions::{AddLiquidity, InitializePool, RemoveLiquidity, Swap};
+use crate::instructions::{
+    AcceptAuthority, AddLiquidity, CancelAuthorityTransfer, DepositSingle, IncreaseObservationCardinality, InitializePool, Observe,
+    ProposeAuthority, QuoteAddLiquidity, QuoteRemoveLiquidity, QuoteSwap, RampAmplification, RemoveLiquidity, RouteSwap, SetFeeRate,
+    SetPoolStatus, SetProtocolFeeShare, StopRamp, Swap, SwapExactOut, WithdrawProtocolFees, WithdrawSingle,
+};
 
 // pub const ID: Pubkey = [
 //     0x1f, 0x2e, 0x3d, 0x4c, 0x5b, 0x6a, 0x7b, 0x8c,
@@ -36,10 +48,28 @@ fn process_instruction(
     instruction_data: &[u8],
 ) -> ProgramResult {
     match instruction_data.split_first() {
-        Some((InitializePool::DISCRIMINATOR, _)) =>  InitializePool::try_from((accounts, instruction_data))?.process()?,
-        Some((AddLiquidity::DISCRIMINATOR, _)) =>  AddLiquidity::try_from((accounts, instruction_data))?.process()?,
-        Some((RemoveLiquidity::DISCRIMINATOR, _)) => RemoveLiquidity::try_from((accounts,instruction_data))?.process()?,
-        Some((Swap::DISCRIMINATOR, _)) => Swap::try_from((accounts, instruction_data))?.process()?,
+        Some((InitializePool::DISCRIMINATOR, data)) =>  InitializePool::try_from((accounts, data))?.process()?,
+        Some((AddLiquidity::DISCRIMINATOR, data)) =>  AddLiquidity::try_from((accounts, data))?.process()?,
+        Some((RemoveLiquidity::DISCRIMINATOR, data)) => RemoveLiquidity::try_from((accounts, data))?.process()?,
+        Some((Swap::DISCRIMINATOR, data)) => Swap::try_from((accounts, data))?.process()?,
+        Some((SetFeeRate::DISCRIMINATOR, data)) => SetFeeRate::try_from((accounts, data))?.process()?,
+        Some((ProposeAuthority::DISCRIMINATOR, data)) => ProposeAuthority::try_from((accounts, data))?.process()?,
+        Some((AcceptAuthority::DISCRIMINATOR, data)) => AcceptAuthority::try_from((accounts, data))?.process()?,
+        Some((CancelAuthorityTransfer::DISCRIMINATOR, data)) => CancelAuthorityTransfer::try_from((accounts, data))?.process()?,
+        Some((SetPoolStatus::DISCRIMINATOR, data)) => SetPoolStatus::try_from((accounts, data))?.process()?,
+        Some((SetProtocolFeeShare::DISCRIMINATOR, data)) => SetProtocolFeeShare::try_from((accounts, data))?.process()?,
+        Some((WithdrawProtocolFees::DISCRIMINATOR, data)) => WithdrawProtocolFees::try_from((accounts, data))?.process()?,
+        Some((SwapExactOut::DISCRIMINATOR, data)) => SwapExactOut::try_from((accounts, data))?.process()?,
+        Some((RouteSwap::DISCRIMINATOR, data)) => RouteSwap::try_from((accounts, data))?.process()?,
+        Some((DepositSingle::DISCRIMINATOR, data)) => DepositSingle::try_from((accounts, data))?.process()?,
+        Some((WithdrawSingle::DISCRIMINATOR, data)) => WithdrawSingle::try_from((accounts, data))?.process()?,
+        Some((IncreaseObservationCardinality::DISCRIMINATOR, data)) => IncreaseObservationCardinality::try_from((accounts, data))?.process()?,
+        Some((Observe::DISCRIMINATOR, data)) => Observe::try_from((accounts, data))?.process()?,
+        Some((QuoteSwap::DISCRIMINATOR, data)) => QuoteSwap::try_from((accounts, data))?.process()?,
+        Some((QuoteAddLiquidity::DISCRIMINATOR, data)) => QuoteAddLiquidity::try_from((accounts, data))?.process()?,
+        Some((QuoteRemoveLiquidity::DISCRIMINATOR, data)) => QuoteRemoveLiquidity::try_from((accounts, data))?.process()?,
+        Some((RampAmplification::DISCRIMINATOR, data)) => RampAmplification::try_from((accounts, data))?.process()?,
+        Some((StopRamp::DISCRIMINATOR, data)) => StopRamp::try_from((accounts, data))?.process()?,
         _ => Err(ProgramError::InvalidInstructionData)?,
     }
     Ok(())
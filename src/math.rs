@@ -0,0 +1,849 @@
+use crate::PinocchioError;
+
+/// Denominator for `fee_rate`, which is expressed in basis points.
+pub const FEE_DENOMINATOR: u128 = 10_000;
+
+/// Floor of the square root of `value`, computed with Newton's method in
+/// integer arithmetic so the result is identical on every validator.
+///
+/// The starting guess is a power of two at or above the root, so the
+/// iteration decreases monotonically and no intermediate step can overflow.
+pub fn sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    let bits = 128 - value.leading_zeros();
+    let mut x = 1u128 << bits.div_ceil(2);
+
+    loop {
+        let y = (x + value / x) >> 1;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Constant-product output for an exact `amount_in`, with the swap fee taken
+/// from the input. Rounds down, so the pool never pays out more than the curve
+/// allows. Returns `(amount_out, fee)`, where `fee` is the input the curve
+/// doesn't see.
+pub fn get_amount_out(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_rate: u16,
+) -> Result<(u64, u64), PinocchioError> {
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    let amount_in_with_fee = (amount_in as u128)
+        .checked_mul(fee_multiplier)
+        .ok_or(PinocchioError::MathOverflow)?
+        / FEE_DENOMINATOR;
+
+    let amount_out = amount_in_with_fee
+        .checked_mul(reserve_out as u128)
+        .ok_or(PinocchioError::MathOverflow)?
+        .checked_div((reserve_in as u128) + amount_in_with_fee)
+        .ok_or(PinocchioError::MathOverflow)?;
+
+    Ok((amount_out as u64, amount_in - amount_in_with_fee as u64))
+}
+
+/// Input needed to receive exactly `amount_out`, the inverse of
+/// [`get_amount_out`]. Both the curve step and the fee gross-up round up, so
+/// feeding the result back through [`get_amount_out`] yields at least
+/// `amount_out`.
+pub fn get_amount_in(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_rate: u16,
+) -> Result<u64, PinocchioError> {
+    if reserve_in == 0 || amount_out >= reserve_out {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    // A 100% fee leaves nothing for the curve, so no input is enough.
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .filter(|multiplier| *multiplier > 0)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    let amount_in_with_fee = (amount_out as u128)
+        .checked_mul(reserve_in as u128)
+        .ok_or(PinocchioError::MathOverflow)?
+        .div_ceil((reserve_out - amount_out) as u128);
+
+    let amount_in = amount_in_with_fee
+        .checked_mul(FEE_DENOMINATOR)
+        .ok_or(PinocchioError::MathOverflow)?
+        .div_ceil(fee_multiplier);
+
+    u64::try_from(amount_in).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// Portion of a one-sided deposit of `amount_in` to swap through the pool so
+/// that what's left matches the post-swap reserve ratio. This is the closed-form
+/// root of the constant-product zap, with `g = 10000 - fee_rate`:
+///
+/// `s = (sqrt(((10000 + g) * R)^2 + 4 * g * 10000 * amount_in * R) - (10000 + g) * R) / (2 * g)`
+///
+/// Rounds down, leaving any rounding dust on the deposit side rather than
+/// over-swapping. Inputs too large for the discriminant to fit in a `u128` are
+/// solved by bisection on the same quadratic, tested at 256 bits with [`mul_div`].
+pub fn optimal_swap_amount(amount_in: u64, reserve_in: u64, fee_rate: u16) -> Result<u64, PinocchioError> {
+    if reserve_in == 0 {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .filter(|multiplier| *multiplier > 0)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    // Keeps `((10000 + g) * R)^2` under 2^118 and `4 * g * 10000 * amount * R` under 2^127.
+    if reserve_in < 1 << 44 && amount_in < 1 << 53 {
+        let (amount, reserve) = (amount_in as u128, reserve_in as u128);
+        let linear = (FEE_DENOMINATOR + fee_multiplier) * reserve;
+        let discriminant = linear * linear + 4 * fee_multiplier * FEE_DENOMINATOR * amount * reserve;
+        return Ok(((sqrt(discriminant) - linear) / (2 * fee_multiplier)) as u64);
+    }
+
+    // The root is the largest `s` with `g * s^2 + (10000 + g) * R * s <= 10000 * amount * R`,
+    // i.e. `g * s^2 / R <= 10000 * amount - (10000 + g) * s`.
+    let fits = |swap_amount: u64| {
+        let swap_amount = swap_amount as u128;
+        (FEE_DENOMINATOR * amount_in as u128)
+            .checked_sub((FEE_DENOMINATOR + fee_multiplier) * swap_amount)
+            .is_some_and(|room| {
+                mul_div(swap_amount * swap_amount, fee_multiplier, reserve_in as u128)
+                    .is_some_and(|curve| curve <= room)
+            })
+    };
+
+    let (mut low, mut high) = (0, amount_in);
+    while low < high {
+        let middle = low + (high - low).div_ceil(2);
+        if fits(middle) {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// LP minted for adding `amount` against `reserve`, rounded down.
+pub fn lp_for_amount(amount: u64, reserve: u64, lp_supply: u64) -> Result<u64, PinocchioError> {
+    let lp = (amount as u128)
+        .checked_mul(lp_supply as u128)
+        .ok_or(PinocchioError::MathOverflow)?
+        .checked_div(reserve as u128)
+        .ok_or(PinocchioError::InvalidPoolState)?;
+
+    u64::try_from(lp).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// Tokens needed against `reserve` to back `lp` new LP tokens, rounded up.
+pub fn amount_for_lp(lp: u64, reserve: u64, lp_supply: u64) -> Result<u64, PinocchioError> {
+    if lp_supply == 0 {
+        return Err(PinocchioError::InvalidPoolState);
+    }
+
+    let amount = (lp as u128)
+        .checked_mul(reserve as u128)
+        .ok_or(PinocchioError::MathOverflow)?
+        .div_ceil(lp_supply as u128);
+
+    u64::try_from(amount).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// Share of `reserve` paid out for burning `lp` LP tokens, rounded down.
+pub fn reserve_share(lp: u64, reserve: u64, lp_supply: u64) -> Result<u64, PinocchioError> {
+    let amount = (lp as u128)
+        .checked_mul(reserve as u128)
+        .ok_or(PinocchioError::MathOverflow)?
+        .checked_div(lp_supply as u128)
+        .ok_or(PinocchioError::InvalidPoolState)?;
+
+    u64::try_from(amount).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// Ratio-matched deposit into a pool with existing liquidity. Mints LP for the
+/// scarcer side and charges only what backs it on each side, rounded up, so
+/// neither amount exceeds its maximum and no surplus is donated to the pool.
+/// Returns `(lp, amount_a, amount_b)`.
+pub fn proportional_deposit(
+    max_amount_a: u64,
+    max_amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+) -> Result<(u64, u64, u64), PinocchioError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(PinocchioError::InvalidPoolState);
+    }
+
+    let lp = lp_for_amount(max_amount_a, reserve_a, lp_supply)?
+        .min(lp_for_amount(max_amount_b, reserve_b, lp_supply)?);
+
+    // `lp <= max * lp_supply / reserve`, so rounding up never passes `max`.
+    let amount_a = amount_for_lp(lp, reserve_a, lp_supply)?;
+    let amount_b = amount_for_lp(lp, reserve_b, lp_supply)?;
+
+    Ok((lp, amount_a, amount_b))
+}
+
+/// How far `amount_out` falls short of trading `amount_in` at the spot price,
+/// in basis points. Pass the input after the swap fee to measure the curve alone.
+pub fn price_impact_bps(amount_in: u64, amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<u16, PinocchioError> {
+    let spot_amount_out = (amount_in as u128)
+        .checked_mul(reserve_out as u128)
+        .ok_or(PinocchioError::MathOverflow)?
+        .checked_div(reserve_in as u128)
+        .ok_or(PinocchioError::InsufficientLiquidity)?;
+
+    Ok(shortfall_bps(spot_amount_out, amount_out))
+}
+
+/// `a * b / denominator`, rounded down. The product is taken at 256 bits, so
+/// this only fails when `denominator` is zero or the quotient doesn't fit in a
+/// `u128`. Products that already fit skip the long division.
+pub fn mul_div(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    mul_div_rem(a, b, denominator).map(|(quotient, _)| quotient)
+}
+
+/// [`mul_div`], rounded up.
+pub fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Option<u128> {
+    let (quotient, remainder) = mul_div_rem(a, b, denominator)?;
+    if remainder == 0 { Some(quotient) } else { quotient.checked_add(1) }
+}
+
+/// Quotient and remainder of `a * b / denominator`.
+fn mul_div_rem(a: u128, b: u128, denominator: u128) -> Option<(u128, u128)> {
+    if denominator == 0 {
+        return None;
+    }
+    if let Some(product) = a.checked_mul(b) {
+        return Some((product / denominator, product % denominator));
+    }
+
+    // Schoolbook product of the 64-bit halves: `a * b = high * 2^128 + low`.
+    const LOW_MASK: u128 = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & LOW_MASK);
+    let (b_high, b_low) = (b >> 64, b & LOW_MASK);
+    let (cross_1, cross_2) = (a_low * b_high, a_high * b_low);
+
+    let (low, carry_1) = (a_low * b_low).overflowing_add(cross_1 << 64);
+    let (low, carry_2) = low.overflowing_add(cross_2 << 64);
+    let high = a_high * b_high + (cross_1 >> 64) + (cross_2 >> 64) + carry_1 as u128 + carry_2 as u128;
+
+    if high >= denominator {
+        return None;
+    }
+
+    // Bit-by-bit long division of `high:low`. `remainder` stays below
+    // `denominator`, and a bit shifted out of it means it was above anyway.
+    let mut remainder = high;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let overflow = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if overflow == 1 || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+
+    Some((quotient, remainder))
+}
+
+/// Cap on Newton iterations for the StableSwap solvers. Balanced pools converge
+/// in a handful; the cap only bounds compute on pathological inputs.
+pub const STABLE_SWAP_MAX_ITERATIONS: usize = 255;
+
+/// `D^3 / (4 * x * y)`, the product term of the two-token StableSwap invariant,
+/// rounded down or up. The smaller reserve divides first, so the rounding is the
+/// same whichever way round the reserves are passed.
+fn stable_product_term(invariant: u128, reserve_x: u128, reserve_y: u128, round_up: bool) -> Result<u128, PinocchioError> {
+    let divide = if round_up { mul_div_ceil } else { mul_div };
+    let (reserve_x, reserve_y) = (reserve_x.min(reserve_y), reserve_x.max(reserve_y));
+    let term = divide(invariant, invariant, reserve_x * 2).ok_or(PinocchioError::MathOverflow)?;
+    divide(term, invariant, reserve_y * 2).ok_or(PinocchioError::MathOverflow)
+}
+
+/// Compares `(4A - 1)D + D^3 / 4xy` with `4A(x + y)`. The two sides are equal
+/// exactly at the invariant, and the left one grows with `D` and shrinks as
+/// either reserve grows.
+///
+/// With the product term rounded up, anything but `Greater` proves `D` is at
+/// most the exact invariant. Rounded down, anything but `Less` proves it is at
+/// least the exact invariant.
+fn stable_compare(invariant: u128, reserve_x: u128, reserve_y: u128, ann: u128, round_up: bool) -> Result<core::cmp::Ordering, PinocchioError> {
+    let product_term = stable_product_term(invariant, reserve_x, reserve_y, round_up)?;
+    let left = (ann - 1)
+        .checked_mul(invariant)
+        .and_then(|linear| linear.checked_add(product_term))
+        .ok_or(PinocchioError::MathOverflow)?;
+    Ok(left.cmp(&(ann * (reserve_x + reserve_y))))
+}
+
+/// Steps from `start`, a Newton estimate, to the smallest value for which
+/// `reached` holds. `reached` must be false below that value and true from it on.
+fn stable_step_to(start: u128, reached: impl Fn(u128) -> Result<bool, PinocchioError>) -> Result<u128, PinocchioError> {
+    let mut value = start;
+    for _ in 0..STABLE_SWAP_MAX_ITERATIONS {
+        if !reached(value)? {
+            value = value.checked_add(1).ok_or(PinocchioError::MathOverflow)?;
+        } else if value > 0 && reached(value - 1)? {
+            value -= 1;
+        } else {
+            return Ok(value);
+        }
+    }
+
+    Err(PinocchioError::CurveNotConverged)
+}
+
+/// StableSwap invariant `D` for two reserves and amplification `A`, the root of
+///
+/// `4A(x + y) + D = 4AD + D^3 / (4xy)`
+///
+/// rounded down: the largest `D` that provably doesn't exceed the root. It is
+/// estimated with Curve's Newton iteration, starting from `D = x + y` and
+/// stopping once a step moves it by at most one unit, then stepped onto the
+/// rounded root. Both reserves must be non-zero.
+pub fn stable_invariant(reserve_a: u64, reserve_b: u64, amplification: u64) -> Result<u128, PinocchioError> {
+    let estimate = stable_invariant_estimate(reserve_a, reserve_b, amplification)?;
+    let (reserve_a, reserve_b, ann) = (reserve_a as u128, reserve_b as u128, amplification as u128 * 4);
+
+    let above = stable_step_to(estimate, |invariant| {
+        Ok(stable_compare(invariant, reserve_a, reserve_b, ann, true)?.is_gt())
+    })?;
+    Ok(above - 1)
+}
+
+/// [`stable_invariant`] rounded up instead: the smallest `D` that provably
+/// isn't below the root. Swaps price against it, so the exact invariant never
+/// drops on a trade.
+fn stable_invariant_up(reserve_a: u64, reserve_b: u64, amplification: u64) -> Result<u128, PinocchioError> {
+    let estimate = stable_invariant_estimate(reserve_a, reserve_b, amplification)?;
+    let (reserve_a, reserve_b, ann) = (reserve_a as u128, reserve_b as u128, amplification as u128 * 4);
+
+    stable_step_to(estimate, |invariant| {
+        Ok(stable_compare(invariant, reserve_a, reserve_b, ann, false)?.is_ge())
+    })
+}
+
+/// Newton estimate of the StableSwap invariant, within a few units of the root.
+fn stable_invariant_estimate(reserve_a: u64, reserve_b: u64, amplification: u64) -> Result<u128, PinocchioError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let (reserve_a, reserve_b) = (reserve_a as u128, reserve_b as u128);
+    let sum = reserve_a + reserve_b;
+    let ann = amplification as u128 * 4;
+
+    let mut invariant = sum;
+    for _ in 0..STABLE_SWAP_MAX_ITERATIONS {
+        let product_term = stable_product_term(invariant, reserve_a, reserve_b, false)?;
+        let previous = invariant;
+
+        // D = (4A * S + 2 * D_P) * D / ((4A - 1) * D + 3 * D_P)
+        let numerator = (ann * sum)
+            .checked_add(product_term * 2)
+            .ok_or(PinocchioError::MathOverflow)?;
+        let denominator = ((ann - 1) * invariant)
+            .checked_add(product_term.checked_mul(3).ok_or(PinocchioError::MathOverflow)?)
+            .ok_or(PinocchioError::MathOverflow)?;
+        invariant = mul_div(numerator, invariant, denominator).ok_or(PinocchioError::MathOverflow)?;
+
+        if invariant.abs_diff(previous) <= 1 {
+            return Ok(invariant);
+        }
+    }
+
+    Err(PinocchioError::CurveNotConverged)
+}
+
+/// The other reserve of a StableSwap pool with invariant `D` once one reserve
+/// is `reserve_x`, rounded up: the smallest reserve that provably keeps the
+/// exact invariant at `D` or above. It is estimated by solving
+/// `y^2 + (b - D)y = c` with Newton's method, with `b = x + D / 4A` and
+/// `c = D^3 / (16A * x)`, then stepped onto the rounded root.
+pub fn stable_reserve(reserve_x: u128, invariant: u128, amplification: u64) -> Result<u128, PinocchioError> {
+    if reserve_x == 0 {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let ann = amplification as u128 * 4;
+    let c = mul_div(invariant, invariant, reserve_x * 2).ok_or(PinocchioError::MathOverflow)?;
+    let c = mul_div(c, invariant, ann * 2).ok_or(PinocchioError::MathOverflow)?;
+    let b = reserve_x
+        .checked_add(invariant / ann)
+        .ok_or(PinocchioError::MathOverflow)?;
+
+    let mut reserve_y = invariant;
+    for _ in 0..STABLE_SWAP_MAX_ITERATIONS {
+        let previous = reserve_y;
+
+        // y = (y^2 + c) / (2y + b - D), with `y^2` kept below 2^128 by dividing first.
+        let denominator = (reserve_y * 2 + b)
+            .checked_sub(invariant)
+            .filter(|denominator| *denominator > 0)
+            .ok_or(PinocchioError::InvalidPoolState)?;
+        reserve_y = mul_div(reserve_y, reserve_y, denominator)
+            .ok_or(PinocchioError::MathOverflow)?
+            .checked_add(c / denominator)
+            .ok_or(PinocchioError::MathOverflow)?;
+
+        if reserve_y.abs_diff(previous) <= 1 {
+            return stable_step_to(reserve_y, |reserve_y| {
+                Ok(reserve_y > 0 && !stable_compare(invariant, reserve_x, reserve_y, ann, true)?.is_gt())
+            });
+        }
+    }
+
+    Err(PinocchioError::CurveNotConverged)
+}
+
+/// StableSwap counterpart of [`get_amount_out`], with the same fee taken from
+/// the input. The pool's invariant is rounded up and the new output reserve is
+/// rounded up against it, so the exact invariant never drops and a round trip
+/// never returns more than it put in.
+pub fn stable_get_amount_out(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amplification: u64,
+    fee_rate: u16,
+) -> Result<(u64, u64), PinocchioError> {
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    let amount_in_with_fee = (amount_in as u128)
+        .checked_mul(fee_multiplier)
+        .ok_or(PinocchioError::MathOverflow)?
+        / FEE_DENOMINATOR;
+
+    let invariant = stable_invariant_up(reserve_in, reserve_out, amplification)?;
+    let new_reserve_out = stable_reserve(reserve_in as u128 + amount_in_with_fee, invariant, amplification)?;
+    let amount_out = (reserve_out as u128).saturating_sub(new_reserve_out);
+
+    Ok((amount_out as u64, amount_in - amount_in_with_fee as u64))
+}
+
+/// StableSwap counterpart of [`get_amount_in`]. The new input reserve is rounded
+/// up against the rounded-up invariant and the fee gross-up rounds up, so the
+/// exact invariant never drops.
+pub fn stable_get_amount_in(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amplification: u64,
+    fee_rate: u16,
+) -> Result<u64, PinocchioError> {
+    if reserve_in == 0 || amount_out >= reserve_out {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .filter(|multiplier| *multiplier > 0)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    let invariant = stable_invariant_up(reserve_in, reserve_out, amplification)?;
+    let new_reserve_in = stable_reserve((reserve_out - amount_out) as u128, invariant, amplification)?;
+    let amount_in_with_fee = new_reserve_in.saturating_sub(reserve_in as u128);
+
+    let amount_in = amount_in_with_fee
+        .checked_mul(FEE_DENOMINATOR)
+        .ok_or(PinocchioError::MathOverflow)?
+        .div_ceil(fee_multiplier);
+
+    u64::try_from(amount_in).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// What `amount_in` would buy at the StableSwap marginal price, fees excluded.
+/// Differentiating the invariant gives `dy/dx = y(4Ax + D_P) / (x(4Ay + D_P))`
+/// with `D_P = D^3 / 4xy`, which is exactly 1 for a balanced pool.
+pub fn stable_spot_amount_out(
+    amount_in: u128,
+    reserve_in: u64,
+    reserve_out: u64,
+    amplification: u64,
+) -> Result<u128, PinocchioError> {
+    let invariant = stable_invariant(reserve_in, reserve_out, amplification)?;
+    let (reserve_in, reserve_out) = (reserve_in as u128, reserve_out as u128);
+    let ann = amplification as u128 * 4;
+    let product_term = stable_product_term(invariant, reserve_in, reserve_out, false)?;
+
+    let scaled = mul_div(amount_in, ann * reserve_in + product_term, ann * reserve_out + product_term)
+        .ok_or(PinocchioError::MathOverflow)?;
+    mul_div(scaled, reserve_out, reserve_in).ok_or(PinocchioError::MathOverflow)
+}
+
+/// LP minted for depositing `amount_a` and `amount_b`, in any ratio, into a
+/// StableSwap pool, following Curve's `add_liquidity`.
+///
+/// LP is the deposit's share of invariant growth. Deposits that move the pool
+/// off balance pay half the swap fee on each side's deviation from the
+/// proportional amount, so depositing one side and withdrawing both is never
+/// cheaper than swapping. That fee stays in the reserves for LPs.
+pub fn stable_deposit(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+    amplification: u64,
+    fee_rate: u16,
+) -> Result<u64, PinocchioError> {
+    let new_reserve_a = reserve_a.checked_add(amount_a).ok_or(PinocchioError::MathOverflow)?;
+    let new_reserve_b = reserve_b.checked_add(amount_b).ok_or(PinocchioError::MathOverflow)?;
+
+    // The pool's invariant is rounded up and the deposit's down, so rounding
+    // never mints LP the reserves don't back.
+    let invariant_before = stable_invariant_up(reserve_a, reserve_b, amplification)?;
+    let invariant_after = stable_invariant(new_reserve_a, new_reserve_b, amplification)?;
+    if invariant_after <= invariant_before {
+        return Err(PinocchioError::InvalidAmount);
+    }
+
+    let imbalance_fee = |reserve: u64, new_reserve: u64| -> Result<u64, PinocchioError> {
+        let ideal = mul_div(invariant_after, reserve as u128, invariant_before).ok_or(PinocchioError::MathOverflow)?;
+        let fee = ideal
+            .abs_diff(new_reserve as u128)
+            .checked_mul(fee_rate as u128)
+            .ok_or(PinocchioError::MathOverflow)?
+            .div_ceil(2 * FEE_DENOMINATOR);
+        u64::try_from(fee).map_err(|_| PinocchioError::MathOverflow)
+    };
+    let charged_reserve_a = new_reserve_a.saturating_sub(imbalance_fee(reserve_a, new_reserve_a)?);
+    let charged_reserve_b = new_reserve_b.saturating_sub(imbalance_fee(reserve_b, new_reserve_b)?);
+
+    let invariant_charged = stable_invariant(charged_reserve_a, charged_reserve_b, amplification)?;
+    let lp = mul_div(
+        lp_supply as u128,
+        invariant_charged.saturating_sub(invariant_before),
+        invariant_before,
+    ).ok_or(PinocchioError::MathOverflow)?;
+
+    u64::try_from(lp).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// One in the Q64.64 fixed point used by the weighted-pool math.
+const Q64_ONE: u128 = 1 << 64;
+
+/// `ln 2` in Q64.64, rounded down.
+const LN_2_Q64: i128 = 12_786_308_645_202_655_659;
+
+/// Bits of relative precision [`pow_q64`] is guaranteed to keep. Weighted-pool
+/// math widens its results by this margin in the pool's favour.
+pub const POW_PRECISION_BITS: u32 = 46;
+
+/// Natural logarithm of a positive Q64.64 value, as signed Q64.64.
+///
+/// `x` is split into `2^k * m` with `m` in `[1, 2)`, and `ln m` is summed from
+/// the series `2 * (z + z^3/3 + z^5/5 + ...)` with `z = (m - 1) / (m + 1)`.
+/// `z` is at most 1/3, so each term is under a ninth of the last and the sum
+/// ends within about twenty terms.
+pub fn ln_q64(x: u128) -> Result<i128, PinocchioError> {
+    if x == 0 {
+        return Err(PinocchioError::MathOverflow);
+    }
+
+    let exponent = 63 - x.leading_zeros() as i32;
+    let mantissa = if exponent >= 0 { x >> exponent } else { x << -exponent };
+
+    let z = ((mantissa - Q64_ONE) << 64) / (mantissa + Q64_ONE);
+    let z_squared = (z * z) >> 64;
+
+    let mut term = z;
+    let mut sum = 0u128;
+    let mut divisor = 1u128;
+    while term > 0 {
+        sum += term / divisor;
+        term = (term * z_squared) >> 64;
+        divisor += 2;
+    }
+
+    Ok(exponent as i128 * LN_2_Q64 + 2 * sum as i128)
+}
+
+/// `e^x` for a signed Q64.64 `x`, as Q64.64. Fails with `MathOverflow` once the
+/// result reaches 2^64.
+///
+/// `x` is split into `k * ln 2 + r` with `r` in `[0, ln 2)`, so `e^x = 2^k * e^r`
+/// and the Taylor series for `e^r` ends within about twenty terms.
+pub fn exp_q64(x: i128) -> Result<u128, PinocchioError> {
+    let exponent = x.div_euclid(LN_2_Q64);
+    let remainder = x.rem_euclid(LN_2_Q64) as u128;
+
+    let mut term = Q64_ONE;
+    let mut sum = Q64_ONE;
+    let mut divisor = 1u128;
+    while term > 0 {
+        term = ((term * remainder) >> 64) / divisor;
+        sum += term;
+        divisor += 1;
+    }
+
+    if exponent >= 0 {
+        if exponent > sum.leading_zeros() as i128 {
+            return Err(PinocchioError::MathOverflow);
+        }
+        Ok(sum << exponent)
+    } else {
+        Ok(sum.checked_shr(u32::try_from(-exponent).unwrap_or(u32::MAX)).unwrap_or(0))
+    }
+}
+
+/// `base^(numerator / denominator)` for a Q64.64 `base`, as Q64.64. Accurate to
+/// [`POW_PRECISION_BITS`] bits, and exact when the exponent is one.
+pub fn pow_q64(base: u128, numerator: u128, denominator: u128) -> Result<u128, PinocchioError> {
+    if numerator == denominator {
+        return Ok(base);
+    }
+
+    let exponent = ln_q64(base)?
+        .checked_mul(numerator as i128)
+        .ok_or(PinocchioError::MathOverflow)?
+        / denominator as i128;
+    exp_q64(exponent)
+}
+
+/// Largest error [`pow_q64`] can make on `value`, plus one unit.
+fn pow_error(value: u128) -> u128 {
+    (value >> POW_PRECISION_BITS) + 1
+}
+
+/// `(w_a * ln x_a + w_b * ln x_b) / (w_a + w_b)` for Q64.64 `x_a` and `x_b`:
+/// the log of their weighted geometric mean.
+fn weighted_ln(x_a: u128, x_b: u128, weight_a: u16, weight_b: u16) -> Result<i128, PinocchioError> {
+    // Logs of Q64.64 values stay under 2^70 in magnitude, so the weighted sum fits.
+    let sum = ln_q64(x_a)? * weight_a as i128 + ln_q64(x_b)? * weight_b as i128;
+    Ok(sum / (weight_a as i128 + weight_b as i128))
+}
+
+/// Weighted-pool invariant `V = a^w_a * b^w_b`, with the weights normalised to
+/// sum to one, rounded down. Equal weights give `sqrt(a * b)`, the first-deposit
+/// LP of a constant-product pool. Both reserves must be non-zero.
+pub fn weighted_invariant(reserve_a: u64, reserve_b: u64, weight_a: u16, weight_b: u16) -> Result<u128, PinocchioError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let invariant = exp_q64(weighted_ln(
+        (reserve_a as u128) << 64,
+        (reserve_b as u128) << 64,
+        weight_a,
+        weight_b,
+    )?)?;
+    Ok(invariant.saturating_sub(pow_error(invariant)) >> 64)
+}
+
+/// Weighted-pool counterpart of [`get_amount_out`], following Balancer:
+///
+/// `amount_out = reserve_out * (1 - (reserve_in / (reserve_in + amount_in))^(w_in / w_out))`
+///
+/// with the fee taken from the input. The power is rounded up, so the pool
+/// never pays out more than the curve allows.
+pub fn weighted_get_amount_out(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u16,
+    weight_out: u16,
+    fee_rate: u16,
+) -> Result<(u64, u64), PinocchioError> {
+    if reserve_in == 0 {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    let amount_in_with_fee = (amount_in as u128)
+        .checked_mul(fee_multiplier)
+        .ok_or(PinocchioError::MathOverflow)?
+        / FEE_DENOMINATOR;
+
+    let base = ((reserve_in as u128) << 64).div_ceil(reserve_in as u128 + amount_in_with_fee);
+    let power = pow_q64(base, weight_in as u128, weight_out as u128)?;
+    let power = if weight_in == weight_out { power } else { power + pow_error(power) };
+
+    let amount_out = mul_div(reserve_out as u128, Q64_ONE.saturating_sub(power), Q64_ONE)
+        .ok_or(PinocchioError::MathOverflow)?;
+
+    Ok((amount_out as u64, amount_in - amount_in_with_fee as u64))
+}
+
+/// Weighted-pool counterpart of [`get_amount_in`]:
+///
+/// `amount_in = reserve_in * ((reserve_out / (reserve_out - amount_out))^(w_out / w_in) - 1)`
+///
+/// The power, the curve's input and the fee gross-up all round up.
+pub fn weighted_get_amount_in(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u16,
+    weight_out: u16,
+    fee_rate: u16,
+) -> Result<u64, PinocchioError> {
+    if reserve_in == 0 || amount_out >= reserve_out {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .filter(|multiplier| *multiplier > 0)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+
+    let base = ((reserve_out as u128) << 64).div_ceil((reserve_out - amount_out) as u128);
+    let power = pow_q64(base, weight_out as u128, weight_in as u128)?;
+    let power = if weight_in == weight_out { power } else { power + pow_error(power) };
+
+    let amount_in_with_fee = mul_div(reserve_in as u128, power - Q64_ONE, Q64_ONE)
+        .ok_or(PinocchioError::MathOverflow)?
+        + 1;
+
+    let amount_in = amount_in_with_fee
+        .checked_mul(FEE_DENOMINATOR)
+        .ok_or(PinocchioError::MathOverflow)?
+        .div_ceil(fee_multiplier);
+
+    u64::try_from(amount_in).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// What `amount_in` buys at a weighted pool's spot price, fees excluded:
+/// `amount_in * (reserve_out / w_out) / (reserve_in / w_in)`.
+pub fn weighted_spot_amount_out(
+    amount_in: u128,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u16,
+    weight_out: u16,
+) -> Result<u128, PinocchioError> {
+    mul_div(
+        amount_in,
+        reserve_out as u128 * weight_in as u128,
+        reserve_in as u128 * weight_out as u128,
+    ).ok_or(PinocchioError::InsufficientLiquidity)
+}
+
+/// LP minted for depositing `amount_a` and `amount_b`, in any ratio, into a
+/// weighted pool, following Balancer's `calcBptOutGivenExactTokensIn`.
+///
+/// LP is the deposit's share of invariant growth, `lp_supply * (V' / V - 1)`.
+/// Whatever a side adds beyond the deposit's weighted-average growth is in
+/// effect swapped into the other token, so that excess pays the swap fee,
+/// which stays in the reserves for LPs.
+#[allow(clippy::too_many_arguments)]
+pub fn weighted_deposit(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+    weight_a: u16,
+    weight_b: u16,
+    fee_rate: u16,
+) -> Result<u64, PinocchioError> {
+    if reserve_a == 0 || reserve_b == 0 {
+        return Err(PinocchioError::InsufficientLiquidity);
+    }
+
+    let fee_multiplier = FEE_DENOMINATOR
+        .checked_sub(fee_rate as u128)
+        .ok_or(PinocchioError::InvalidFeeRate)?;
+    let total_weight = weight_a as u128 + weight_b as u128;
+
+    // Each reserve's growth as a Q64.64 ratio, rounded down.
+    let growth = |amount: u128, reserve: u64| -> Result<u128, PinocchioError> {
+        mul_div(reserve as u128 + amount, Q64_ONE, reserve as u128).ok_or(PinocchioError::MathOverflow)
+    };
+    let growth_a = growth(amount_a as u128, reserve_a)?;
+    let growth_b = growth(amount_b as u128, reserve_b)?;
+    let average_growth = mul_div(growth_a, weight_a as u128, total_weight)
+        .zip(mul_div(growth_b, weight_b as u128, total_weight))
+        .and_then(|(growth_a, growth_b)| growth_a.checked_add(growth_b))
+        .ok_or(PinocchioError::MathOverflow)?;
+
+    let charged = |amount: u64, reserve: u64, growth: u128| -> Result<u128, PinocchioError> {
+        if growth <= average_growth {
+            return Ok(amount as u128);
+        }
+        // Rounding can leave the average a hair under one on a dust deposit into
+        // a deep pool, in which case the whole amount is taxable.
+        let proportional = mul_div(reserve as u128, average_growth.saturating_sub(Q64_ONE), Q64_ONE)
+            .ok_or(PinocchioError::MathOverflow)?;
+        let taxable = (amount as u128).saturating_sub(proportional);
+        Ok(proportional + taxable * fee_multiplier / FEE_DENOMINATOR)
+    };
+    let charged_growth_a = growth(charged(amount_a, reserve_a, growth_a)?, reserve_a)?;
+    let charged_growth_b = growth(charged(amount_b, reserve_b, growth_b)?, reserve_b)?;
+
+    let invariant_ratio = exp_q64(weighted_ln(charged_growth_a, charged_growth_b, weight_a, weight_b)?)?;
+    let invariant_ratio = invariant_ratio.saturating_sub(pow_error(invariant_ratio));
+
+    let lp = mul_div(lp_supply as u128, invariant_ratio.saturating_sub(Q64_ONE), Q64_ONE)
+        .ok_or(PinocchioError::MathOverflow)?;
+
+    u64::try_from(lp).map_err(|_| PinocchioError::MathOverflow)
+}
+
+/// How far `amount_out` falls short of `spot_amount_out`, in basis points.
+pub fn shortfall_bps(spot_amount_out: u128, amount_out: u64) -> u16 {
+    if spot_amount_out == 0 {
+        return 0;
+    }
+
+    let shortfall = spot_amount_out.saturating_sub(amount_out as u128);
+    let impact = match shortfall.checked_mul(FEE_DENOMINATOR) {
+        Some(scaled) => scaled / spot_amount_out,
+        // Only near the top of the range, where `spot_amount_out` is far above the denominator.
+        None => shortfall / (spot_amount_out / FEE_DENOMINATOR),
+    };
+    impact.min(FEE_DENOMINATOR) as u16
+}
+
+/// Fractional bits of the fixed-point prices kept by the TWAP accumulators.
+pub const PRICE_FRACTIONAL_BITS: u32 = 64;
+
+/// Spot price of the base token in units of the quote token, as Q64.64 fixed
+/// point. A `u64` reserve shifted by 64 bits always fits in a `u128`. Zero when
+/// the base reserve is empty.
+pub fn q64_price(reserve_base: u64, reserve_quote: u64) -> u128 {
+    ((reserve_quote as u128) << PRICE_FRACTIONAL_BITS)
+        .checked_div(reserve_base as u128)
+        .unwrap_or(0)
+}
+
+/// Time-weighted average Q64.64 price between two readings of a cumulative
+/// price accumulator.
+///
+/// Accumulators wrap on overflow, so the difference is taken with wrapping
+/// arithmetic and stays exact as long as the window's true sum fits in a `u128`.
+pub fn twap(
+    cumulative_start: u128,
+    cumulative_end: u128,
+    timestamp_start: i64,
+    timestamp_end: i64,
+) -> Result<u128, PinocchioError> {
+    let elapsed = timestamp_end
+        .checked_sub(timestamp_start)
+        .filter(|elapsed| *elapsed > 0)
+        .ok_or(PinocchioError::InvalidAmount)?;
+
+    Ok(cumulative_end.wrapping_sub(cumulative_start) / elapsed as u128)
+}
@@ -1,4 +1,10 @@
-use pinocchio::pubkey::Pubkey;
+use pinocchio::{
+    program_error::ProgramError,
+    pubkey::{find_program_address, Pubkey},
+    sysvars::clock::Clock,
+};
+
+use crate::{math, Curve, PinocchioError};
 
 #[repr(C)]
 pub struct Pool {
@@ -11,11 +17,58 @@ pub struct Pool {
     pub fee_rate: u16,
     pub bump: u8,
     pub lp_mint_bump: u8,
+    pub pending_authority: Pubkey,
+    pub status: u8,
+    pub _padding: [u8; 1],
+    pub protocol_fee_share: u16,
+    pub protocol_fees_a: u64,
+    pub protocol_fees_b: u64,
+    pub locked_liquidity: u64,
+    /// Running sums of each token's Q64.64 spot price times the seconds it held.
+    /// Stored as little-endian bytes to keep the struct free of 16-byte alignment.
+    pub price_a_cumulative: [u8; 16],
+    pub price_b_cumulative: [u8; 16],
+    pub last_update_timestamp: i64,
+    /// [`Curve`] tag and parameters; see [`Pool::curve`].
+    pub curve_type: u8,
+    pub _curve_padding: [u8; 3],
+    /// Weighted-pool token weights out of `Curve::WEIGHT_DENOMINATOR`. Zero on other curves.
+    pub weight_a: u16,
+    pub weight_b: u16,
+    /// StableSwap `A` ramps linearly from `amplification` at `ramp_start_timestamp`
+    /// to `target_amplification` at `ramp_end_timestamp`. Outside a ramp both are equal.
+    pub amplification: u64,
+    pub target_amplification: u64,
+    pub ramp_start_timestamp: i64,
+    pub ramp_end_timestamp: i64,
 }
 
 impl Pool {
-    pub const LEN: usize = 32 + 32 + 32 + 32 + 32 + 32 + 2 + 1 + 1; // 6 Pubkeys + u16 + 2 u8s
+    pub const LEN: usize = 32 + 32 + 32 + 32 + 32 + 32 + 2 + 1 + 1 + 32 + 1 + 1 + 2 + 8 + 8 + 8 + 16 + 16 + 8 + 1 + 3 + 2 + 2 + 8 + 8 + 8 + 8; // 6 Pubkeys + u16 + 2 u8s + pending authority + status + padding + protocol fee share + 2 u64 fee counters + locked liquidity + 2 u128 price accumulators + timestamp + curve type + padding + 2 weights + 2 amplifications + 2 ramp timestamps
+
+    /// Upper bound for `fee_rate`, in basis points (100%).
+    pub const MAX_FEE_RATE: u16 = 10000;
+
+    /// Upper bound for `protocol_fee_share`, in basis points of the swap fee (100%).
+    pub const MAX_PROTOCOL_FEE_SHARE: u16 = 10000;
+
+    /// Shortest amplification ramp, and the least time between the starts of two ramps.
+    pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+    /// Largest factor one ramp may raise or lower the amplification by. With
+    /// [`Self::MIN_RAMP_DURATION`] this caps how fast `A` can move.
+    pub const MAX_AMPLIFICATION_CHANGE: u64 = 10;
 
+    /// LP amount permanently locked on the first deposit so the LP supply never returns to zero.
+    pub const MINIMUM_LIQUIDITY: u64 = 1000;
+
+    /// `status` bits; a set bit disables the matching instruction.
+    pub const SWAP_DISABLED: u8 = 1 << 0;
+    pub const ADD_LIQUIDITY_DISABLED: u8 = 1 << 1;
+    pub const REMOVE_LIQUIDITY_DISABLED: u8 = 1 << 2;
+    pub const STATUS_MASK: u8 = Self::SWAP_DISABLED | Self::ADD_LIQUIDITY_DISABLED | Self::REMOVE_LIQUIDITY_DISABLED;
+
+    #[allow(clippy::too_many_arguments)]
     pub fn set_inner_full(
         &mut self,
         authority: Pubkey,
@@ -37,6 +90,227 @@ impl Pool {
         self.fee_rate = fee_rate;
         self.bump = bump;
         self.lp_mint_bump = lp_mint_bump;
+        self.pending_authority = Pubkey::default();
+        self.status = 0;
+        self.protocol_fee_share = 0;
+        self.protocol_fees_a = 0;
+        self.protocol_fees_b = 0;
+        self.locked_liquidity = 0;
+        self.price_a_cumulative = [0; 16];
+        self.price_b_cumulative = [0; 16];
+        self.last_update_timestamp = 0;
+        self.set_curve(Curve::ConstantProduct);
+    }
+
+    /// The curve in effect at `now`, with the amplification interpolated along any ramp.
+    pub fn curve_at(&self, now: i64) -> Result<Curve, PinocchioError> {
+        match self.curve_type {
+            Curve::WEIGHTED_TAG => Curve::weighted(self.weight_a, self.weight_b),
+            curve_type => Curve::from_parts(curve_type, self.amplification_at(now)),
+        }
+    }
+
+    pub fn amplification_at(&self, now: i64) -> u64 {
+        if now >= self.ramp_end_timestamp {
+            return self.target_amplification;
+        }
+        if now <= self.ramp_start_timestamp {
+            return self.amplification;
+        }
+
+        // Both amplifications are at most `Curve::MAX_AMPLIFICATION`, so this fits easily.
+        let change = self.target_amplification as i128 - self.amplification as i128;
+        let elapsed = (now - self.ramp_start_timestamp) as i128;
+        let duration = (self.ramp_end_timestamp - self.ramp_start_timestamp) as i128;
+        (self.amplification as i128 + change * elapsed / duration) as u64
+    }
+
+    pub fn set_curve(&mut self, curve: Curve) {
+        let (amplification, weight_a, weight_b) = match curve {
+            Curve::ConstantProduct => (0, 0, 0),
+            Curve::StableSwap { amplification } => (amplification, 0, 0),
+            Curve::Weighted { weight_a, weight_b } => (0, weight_a, weight_b),
+        };
+
+        self.curve_type = curve.tag();
+        self._curve_padding = [0; 3];
+        self.weight_a = weight_a;
+        self.weight_b = weight_b;
+        self.amplification = amplification;
+        self.target_amplification = amplification;
+        self.ramp_start_timestamp = 0;
+        self.ramp_end_timestamp = 0;
+    }
+
+    /// Starts moving a StableSwap pool's amplification from its value at `now`
+    /// to `target_amplification` at `ramp_end_timestamp`.
+    ///
+    /// The ramp must last at least [`Self::MIN_RAMP_DURATION`], start at least
+    /// that long after the previous one started, and change `A` by at most
+    /// [`Self::MAX_AMPLIFICATION_CHANGE`] times either way.
+    pub fn start_ramp(&mut self, target_amplification: u64, ramp_end_timestamp: i64, now: i64) -> Result<(), PinocchioError> {
+        let Curve::StableSwap { amplification } = self.curve_at(now)? else {
+            return Err(PinocchioError::InvalidPoolState);
+        };
+
+        if now < self.ramp_start_timestamp.saturating_add(Self::MIN_RAMP_DURATION) {
+            return Err(PinocchioError::InvalidRamp);
+        }
+        if ramp_end_timestamp < now.saturating_add(Self::MIN_RAMP_DURATION) {
+            return Err(PinocchioError::InvalidRamp);
+        }
+
+        Curve::from_parts(Curve::STABLE_SWAP_TAG, target_amplification)?;
+        if target_amplification > amplification.saturating_mul(Self::MAX_AMPLIFICATION_CHANGE)
+            || amplification > target_amplification.saturating_mul(Self::MAX_AMPLIFICATION_CHANGE)
+        {
+            return Err(PinocchioError::InvalidAmplification);
+        }
+
+        self.amplification = amplification;
+        self.target_amplification = target_amplification;
+        self.ramp_start_timestamp = now;
+        self.ramp_end_timestamp = ramp_end_timestamp;
+        Ok(())
+    }
+
+    /// Freezes the amplification at its value at `now`.
+    pub fn stop_ramp(&mut self, now: i64) -> Result<(), PinocchioError> {
+        let Curve::StableSwap { amplification } = self.curve_at(now)? else {
+            return Err(PinocchioError::InvalidPoolState);
+        };
+
+        self.amplification = amplification;
+        self.target_amplification = amplification;
+        self.ramp_start_timestamp = now;
+        self.ramp_end_timestamp = now;
+        Ok(())
+    }
+
+    /// Orders a mint pair the way pool PDAs are seeded (`token_a_mint < token_b_mint`).
+    /// The flag is `true` when the inputs were flipped, i.e. `mint_x` is the pool's token B.
+    pub fn sort_mints<'a>(mint_x: &'a Pubkey, mint_y: &'a Pubkey) -> (&'a Pubkey, &'a Pubkey, bool) {
+        if mint_x <= mint_y {
+            (mint_x, mint_y, false)
+        } else {
+            (mint_y, mint_x, true)
+        }
+    }
+
+    /// Derives the pool address and bump for a mint pair given in either order.
+    pub fn find_address(mint_x: &Pubkey, mint_y: &Pubkey) -> (Pubkey, u8) {
+        let (token_a_mint, token_b_mint, _) = Self::sort_mints(mint_x, mint_y);
+        find_program_address(&[b"pool", token_a_mint, token_b_mint], &crate::ID)
+    }
+
+    /// Swap direction for a trade paying in `input_mint`: `true` for A to B, `false` for B to A.
+    pub fn swap_direction(&self, input_mint: &Pubkey) -> Option<bool> {
+        if *input_mint == self.token_a_mint {
+            Some(true)
+        } else if *input_mint == self.token_b_mint {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    pub fn has_pending_authority(&self) -> bool {
+        self.pending_authority != Pubkey::default()
+    }
+
+    pub fn is_disabled(&self, flag: u8) -> bool {
+        self.status & flag != 0
+    }
+
+    /// Vault balances minus unclaimed protocol fees, i.e. the reserves that back LP shares and pricing.
+    pub fn pricing_reserves(&self, vault_a_amount: u64, vault_b_amount: u64) -> Result<(u64, u64), PinocchioError> {
+        let reserve_a = vault_a_amount
+            .checked_sub(self.protocol_fees_a)
+            .ok_or(PinocchioError::InvalidPoolState)?;
+        let reserve_b = vault_b_amount
+            .checked_sub(self.protocol_fees_b)
+            .ok_or(PinocchioError::InvalidPoolState)?;
+
+        Ok((reserve_a, reserve_b))
+    }
+
+    /// LP supply used for share math: minted LP plus the locked minimum liquidity.
+    pub fn total_lp_supply(&self, lp_mint_supply: u64) -> Result<u64, PinocchioError> {
+        lp_mint_supply
+            .checked_add(self.locked_liquidity)
+            .ok_or(PinocchioError::MathOverflow)
+    }
+
+    /// Protocol's cut of a swap fee taken in the input token.
+    pub fn protocol_fee(&self, fee: u64) -> Result<u64, PinocchioError> {
+        let protocol_fee = (fee as u128)
+            .checked_mul(self.protocol_fee_share as u128)
+            .ok_or(PinocchioError::MathOverflow)?
+            / 10000;
+
+        Ok(protocol_fee as u64)
+    }
+
+    /// Books the protocol's cut of a swap fee paid in token A (`a_to_b`) or token B.
+    pub fn accrue_protocol_fee(&mut self, fee: u64, a_to_b: bool) -> Result<(), PinocchioError> {
+        let protocol_fee = self.protocol_fee(fee)?;
+        let protocol_fees = if a_to_b { &mut self.protocol_fees_a } else { &mut self.protocol_fees_b };
+        *protocol_fees = protocol_fees
+            .checked_add(protocol_fee)
+            .ok_or(PinocchioError::MathOverflow)?;
+
+        Ok(())
+    }
+
+    pub fn price_a_cumulative(&self) -> u128 {
+        u128::from_le_bytes(self.price_a_cumulative)
+    }
+
+    pub fn price_b_cumulative(&self) -> u128 {
+        u128::from_le_bytes(self.price_b_cumulative)
+    }
+
+    /// The accumulators as they would read at `now`, extended from the last update
+    /// with the curve's marginal price at `reserve_a`/`reserve_b`. Empty pools have
+    /// no price and add nothing. Returns `(price_a_cumulative, price_b_cumulative)`.
+    pub fn cumulative_prices_at(&self, reserve_a: u64, reserve_b: u64, now: i64) -> Result<(u128, u128), PinocchioError> {
+        let mut price_a_cumulative = self.price_a_cumulative();
+        let mut price_b_cumulative = self.price_b_cumulative();
+
+        let elapsed = now.saturating_sub(self.last_update_timestamp);
+        if elapsed > 0 && reserve_a != 0 && reserve_b != 0 {
+            // The price over the window is taken at its end, with the amplification in effect then.
+            let curve = self.curve_at(now)?;
+            let elapsed = elapsed as u128;
+            price_a_cumulative = price_a_cumulative.wrapping_add(curve.q64_price(reserve_a, reserve_b, true)?.wrapping_mul(elapsed));
+            price_b_cumulative = price_b_cumulative.wrapping_add(curve.q64_price(reserve_b, reserve_a, false)?.wrapping_mul(elapsed));
+        }
+
+        Ok((price_a_cumulative, price_b_cumulative))
+    }
+
+    /// Advances the accumulators to `now` using the reserves as they stood since the
+    /// last update. Must run before an instruction moves the reserves. A clock that
+    /// hasn't moved forward leaves the pool untouched.
+    pub fn update_price_accumulators(&mut self, reserve_a: u64, reserve_b: u64, now: i64) -> Result<(), PinocchioError> {
+        if now <= self.last_update_timestamp {
+            return Ok(());
+        }
+
+        let (price_a_cumulative, price_b_cumulative) = self.cumulative_prices_at(reserve_a, reserve_b, now)?;
+        self.price_a_cumulative = price_a_cumulative.to_le_bytes();
+        self.price_b_cumulative = price_b_cumulative.to_le_bytes();
+        self.last_update_timestamp = now;
+        Ok(())
+    }
+
+    pub fn load(data: &[u8]) -> Result<&Self, pinocchio::program_error::ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(pinocchio::program_error::ProgramError::InvalidAccountData);
+        }
+
+        let pool = unsafe { &*(data.as_ptr() as *const Self) };
+        Ok(pool)
     }
 
     pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, pinocchio::program_error::ProgramError> {
@@ -47,4 +321,202 @@ impl Pool {
         let pool = unsafe { &mut *(data.as_mut_ptr() as *mut Self) };
         Ok(pool)
     }
-}
\ No newline at end of file
+}
+
+/// One oracle reading: the pool's accumulators at `timestamp` and the
+/// liquidity (`sqrt(reserve_a * reserve_b)`) the pool held going into it.
+/// A zero timestamp marks a slot that has never been written.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_a_cumulative: [u8; 16],
+    pub price_b_cumulative: [u8; 16],
+    pub liquidity: u64,
+}
+
+impl Observation {
+    pub const LEN: usize = 8 + 16 + 16 + 8;
+
+    pub const EMPTY: Self = Self {
+        timestamp: 0,
+        price_a_cumulative: [0; 16],
+        price_b_cumulative: [0; 16],
+        liquidity: 0,
+    };
+
+    /// Snapshot of a pool whose accumulators were just brought up to date.
+    pub fn from_pool(pool: &Pool, reserve_a: u64, reserve_b: u64) -> Self {
+        Self {
+            timestamp: pool.last_update_timestamp,
+            price_a_cumulative: pool.price_a_cumulative,
+            price_b_cumulative: pool.price_b_cumulative,
+            liquidity: math::sqrt(reserve_a as u128 * reserve_b as u128) as u64,
+        }
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.timestamp != 0
+    }
+
+    pub fn price_a_cumulative(&self) -> u128 {
+        u128::from_le_bytes(self.price_a_cumulative)
+    }
+
+    pub fn price_b_cumulative(&self) -> u128 {
+        u128::from_le_bytes(self.price_b_cumulative)
+    }
+}
+
+/// Optional per-pool oracle account at `["observations", pool]`: a ring buffer of
+/// [`Observation`]s written by swaps, at most one per slot.
+///
+/// The account is allocated at full capacity up front. `cardinality` is how many
+/// slots the ring currently cycles through; raising `cardinality_next` grows it
+/// the next time the write index reaches the end, so older readings are never
+/// overwritten out of order.
+#[repr(C)]
+pub struct Observations {
+    pub pool: Pubkey,
+    pub bump: u8,
+    pub _padding: [u8; 1],
+    pub index: u16,
+    pub cardinality: u16,
+    pub cardinality_next: u16,
+    pub last_slot: u64,
+    pub observations: [Observation; Observations::MAX_CARDINALITY],
+}
+
+impl Observations {
+    pub const LEN: usize = 32 + 1 + 1 + 2 + 2 + 2 + 8 + Observation::LEN * Self::MAX_CARDINALITY; // pool + bump + padding + index + 2 cardinalities + last slot + ring buffer
+
+    /// Ring buffer capacity, chosen so the account fits in a single `CreateAccount`.
+    pub const MAX_CARDINALITY: usize = 128;
+
+    pub fn find_address(pool: &Pubkey) -> (Pubkey, u8) {
+        find_program_address(&[b"observations", pool], &crate::ID)
+    }
+
+    pub fn set_inner(&mut self, pool: Pubkey, bump: u8) {
+        self.pool = pool;
+        self.bump = bump;
+        self.index = 0;
+        self.cardinality = 0;
+        self.cardinality_next = 1;
+        self.last_slot = 0;
+        self.observations = [Observation::EMPTY; Self::MAX_CARDINALITY];
+    }
+
+    /// Raises the target ring size. Requests at or below the current target are no-ops.
+    pub fn grow(&mut self, cardinality_next: u16) -> Result<(), ProgramError> {
+        if cardinality_next == 0 || cardinality_next as usize > Self::MAX_CARDINALITY {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        self.cardinality_next = self.cardinality_next.max(cardinality_next);
+        Ok(())
+    }
+
+    /// Appends `observation` unless one was already written in `slot`.
+    pub fn write(&mut self, observation: Observation, slot: u64) {
+        if self.cardinality > 0 && slot == self.last_slot {
+            return;
+        }
+
+        if self.cardinality == 0 {
+            self.index = 0;
+            self.cardinality = 1;
+        } else {
+            if self.cardinality_next > self.cardinality && self.index == self.cardinality - 1 {
+                self.cardinality = self.cardinality_next;
+            }
+            self.index = (self.index + 1) % self.cardinality;
+        }
+
+        self.observations[self.index as usize] = observation;
+        self.last_slot = slot;
+    }
+
+    /// Records `pool`'s current accumulators at `clock`'s slot. Call it right
+    /// after [`Pool::update_price_accumulators`].
+    pub fn sync(&mut self, pool: &Pool, reserve_a: u64, reserve_b: u64, clock: &Clock) {
+        self.write(Observation::from_pool(pool, reserve_a, reserve_b), clock.slot);
+    }
+
+    /// Cumulative prices `seconds_ago` before `now`, as `(price_a_cumulative, price_b_cumulative)`.
+    ///
+    /// Times after the pool's last update are extended from the current reserves;
+    /// earlier times are interpolated between the surrounding readings, with the
+    /// pool itself as the newest one. Fails with `ObservationTooOld` before the
+    /// oldest reading. Two calls give a TWAP through [`math::twap`].
+    pub fn observe(
+        &self,
+        pool: &Pool,
+        reserve_a: u64,
+        reserve_b: u64,
+        now: i64,
+        seconds_ago: u32,
+    ) -> Result<(u128, u128), PinocchioError> {
+        let target = now
+            .checked_sub(seconds_ago as i64)
+            .ok_or(PinocchioError::MathOverflow)?;
+
+        if target >= pool.last_update_timestamp {
+            return pool.cumulative_prices_at(reserve_a, reserve_b, target);
+        }
+
+        let mut newer = (pool.last_update_timestamp, pool.price_a_cumulative(), pool.price_b_cumulative());
+        let cardinality = self.cardinality as usize;
+
+        // Newest to oldest.
+        for offset in 0..cardinality {
+            let observation = &self.observations[(self.index as usize + cardinality - offset) % cardinality];
+            if !observation.is_initialized() {
+                break;
+            }
+
+            let older = (observation.timestamp, observation.price_a_cumulative(), observation.price_b_cumulative());
+            if older.0 <= target {
+                return Ok((
+                    interpolate(older.1, newer.1, older.0, newer.0, target),
+                    interpolate(older.2, newer.2, older.0, newer.0, target),
+                ));
+            }
+            newer = older;
+        }
+
+        Err(PinocchioError::ObservationTooOld)
+    }
+
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let observations = unsafe { &*(data.as_ptr() as *const Self) };
+        Ok(observations)
+    }
+
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let observations = unsafe { &mut *(data.as_mut_ptr() as *mut Self) };
+        Ok(observations)
+    }
+}
+
+// Accounts created through CPI are limited to 10 KiB.
+const _: () = assert!(Observations::LEN <= 10 * 1024);
+
+/// Accumulator value at `target` on the straight line between two readings,
+/// with `older_timestamp <= target < newer_timestamp` or an exact hit.
+fn interpolate(older: u128, newer: u128, older_timestamp: i64, newer_timestamp: i64, target: i64) -> u128 {
+    if target == older_timestamp {
+        return older;
+    }
+
+    let rate = newer.wrapping_sub(older) / (newer_timestamp - older_timestamp) as u128;
+    older.wrapping_add(rate.wrapping_mul((target - older_timestamp) as u128))
+}
@@ -0,0 +1,234 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    cpi::invoke_signed,
+    instruction::{AccountMeta, Instruction, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvars::clock::Clock,
+    ProgramResult,
+};
+use pinocchio_token::state::{Mint, TokenAccount};
+
+use crate::PinocchioError;
+
+/// The Token-2022 (token extensions) program.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pinocchio_pubkey::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Token-2022 accounts with extensions are padded to the size of a token account,
+/// followed by a one-byte account type and the extension TLV entries.
+pub const ACCOUNT_TYPE_OFFSET: usize = TokenAccount::LEN;
+pub const MINT_ACCOUNT_TYPE: u8 = 1;
+pub const TOKEN_ACCOUNT_ACCOUNT_TYPE: u8 = 2;
+
+/// Offset of `decimals` in the base mint layout, shared by both programs.
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+/// Token-2022 extension type of `TransferFeeConfig`, and its size.
+pub const TRANSFER_FEE_CONFIG_EXTENSION: u16 = 1;
+pub const TRANSFER_FEE_CONFIG_LEN: usize = 108;
+
+const ONE_IN_BASIS_POINTS: u128 = 10_000;
+
+/// Whether `program_id` is the legacy SPL Token program or Token-2022.
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == pinocchio_token::ID || *program_id == TOKEN_2022_PROGRAM_ID
+}
+
+/// Checks an account's size against `base_len` for its owning token program.
+///
+/// Legacy accounts are exactly `base_len`. Token-2022 accounts are either exactly
+/// `base_len` (no extensions) or carry `account_type` after the padded base.
+pub fn has_token_layout(account: &AccountInfo, base_len: usize, account_type: u8) -> Result<bool, ProgramError> {
+    if account.is_owned_by(&pinocchio_token::ID) {
+        return Ok(account.data_len() == base_len);
+    }
+    if account.is_owned_by(&TOKEN_2022_PROGRAM_ID) {
+        if account.data_len() == base_len {
+            return Ok(true);
+        }
+        let data = account.try_borrow_data()?;
+        return Ok(data.len() > ACCOUNT_TYPE_OFFSET && data[ACCOUNT_TYPE_OFFSET] == account_type);
+    }
+
+    Err(ProgramError::InvalidAccountOwner)
+}
+
+/// Decimals of a mint owned by either token program.
+pub fn mint_decimals(mint: &AccountInfo) -> Result<u8, ProgramError> {
+    let data = mint.try_borrow_data()?;
+    if data.len() < Mint::LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(data[MINT_DECIMALS_OFFSET])
+}
+
+/// `TransferChecked` sent to `token_program`, which must own `mint`.
+///
+/// Both token programs accept `TransferChecked`, and Token-2022 requires it for
+/// mints with some extensions, so every vault transfer goes through it. The
+/// mint's decimals are read from the account.
+pub struct TokenTransfer<'a> {
+    pub from: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub to: &'a AccountInfo,
+    pub authority: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    pub amount: u64,
+}
+
+impl TokenTransfer<'_> {
+    pub const DISCRIMINATOR: u8 = 12;
+
+    #[inline(always)]
+    pub fn invoke(&self) -> ProgramResult {
+        self.invoke_signed(&[])
+    }
+
+    pub fn invoke_signed(&self, signers: &[Signer]) -> ProgramResult {
+        let account_metas: [AccountMeta; 4] = [
+            AccountMeta::writable(self.from.key()),
+            AccountMeta::readonly(self.mint.key()),
+            AccountMeta::writable(self.to.key()),
+            AccountMeta::readonly_signer(self.authority.key()),
+        ];
+
+        // [0]: discriminator, [1..9]: amount, [9]: decimals
+        let mut instruction_data = [0u8; 10];
+        instruction_data[0] = Self::DISCRIMINATOR;
+        instruction_data[1..9].copy_from_slice(&self.amount.to_le_bytes());
+        instruction_data[9] = mint_decimals(self.mint)?;
+
+        let instruction = Instruction {
+            program_id: self.token_program.key(),
+            accounts: &account_metas,
+            data: &instruction_data,
+        };
+
+        invoke_signed(&instruction, &[self.from, self.mint, self.to, self.authority], signers)
+    }
+}
+
+/// One epoch's transfer fee of a Token-2022 mint. The rounding matches the
+/// token program: fees round up and are capped at `maximum_fee`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub transfer_fee_basis_points: u16,
+}
+
+impl TransferFee {
+    /// No fee: legacy mints and Token-2022 mints without the extension.
+    pub const NONE: Self = Self { epoch: 0, maximum_fee: 0, transfer_fee_basis_points: 0 };
+
+    const LEN: usize = 8 + 8 + 2;
+
+    fn from_bytes(data: &[u8]) -> Self {
+        Self {
+            epoch: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+            maximum_fee: u64::from_le_bytes(data[8..16].try_into().unwrap()),
+            transfer_fee_basis_points: u16::from_le_bytes(data[16..18].try_into().unwrap()),
+        }
+    }
+
+    /// Fee withheld when sending `amount`.
+    pub fn calculate_fee(&self, amount: u64) -> Result<u64, PinocchioError> {
+        let basis_points = self.transfer_fee_basis_points as u128;
+        if basis_points == 0 || amount == 0 {
+            return Ok(0);
+        }
+
+        let fee = (amount as u128)
+            .checked_mul(basis_points)
+            .ok_or(PinocchioError::MathOverflow)?
+            .div_ceil(ONE_IN_BASIS_POINTS);
+
+        Ok(u64::try_from(fee).map_err(|_| PinocchioError::MathOverflow)?.min(self.maximum_fee))
+    }
+
+    /// What the recipient receives when `amount` is sent.
+    pub fn net_amount(&self, amount: u64) -> Result<u64, PinocchioError> {
+        Ok(amount - self.calculate_fee(amount)?)
+    }
+
+    /// Smallest amount to send so that at least `net_amount` arrives.
+    pub fn gross_amount(&self, net_amount: u64) -> Result<u64, PinocchioError> {
+        let basis_points = self.transfer_fee_basis_points as u128;
+        if basis_points == 0 || net_amount == 0 {
+            return Ok(net_amount);
+        }
+        if basis_points >= ONE_IN_BASIS_POINTS {
+            return net_amount.checked_add(self.maximum_fee).ok_or(PinocchioError::MathOverflow);
+        }
+
+        let gross = (net_amount as u128)
+            .checked_mul(ONE_IN_BASIS_POINTS)
+            .ok_or(PinocchioError::MathOverflow)?
+            .div_ceil(ONE_IN_BASIS_POINTS - basis_points);
+
+        if gross - net_amount as u128 >= self.maximum_fee as u128 {
+            return net_amount.checked_add(self.maximum_fee).ok_or(PinocchioError::MathOverflow);
+        }
+        u64::try_from(gross).map_err(|_| PinocchioError::MathOverflow)
+    }
+}
+
+/// The fee schedule in a mint's `TransferFeeConfig`: `newer` applies from its epoch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    /// Offset of `older_transfer_fee`, after the two authorities and the withheld amount.
+    const FEES_OFFSET: usize = 32 + 32 + 8;
+
+    /// Reads the extension from a mint, if it has one.
+    pub fn from_mint(mint: &AccountInfo) -> Result<Option<Self>, ProgramError> {
+        if !mint.is_owned_by(&TOKEN_2022_PROGRAM_ID) {
+            return Ok(None);
+        }
+
+        let data = mint.try_borrow_data()?;
+        if data.len() <= ACCOUNT_TYPE_OFFSET {
+            return Ok(None);
+        }
+
+        // Extensions are TLV entries: a u16 type, a u16 length, then the value.
+        let mut extensions = &data[ACCOUNT_TYPE_OFFSET + 1..];
+        while extensions.len() >= 4 {
+            let extension_type = u16::from_le_bytes([extensions[0], extensions[1]]);
+            let len = u16::from_le_bytes([extensions[2], extensions[3]]) as usize;
+            let value = extensions.get(4..4 + len).ok_or(ProgramError::InvalidAccountData)?;
+
+            if extension_type == TRANSFER_FEE_CONFIG_EXTENSION {
+                if len != TRANSFER_FEE_CONFIG_LEN {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                let fees = &value[Self::FEES_OFFSET..];
+                return Ok(Some(Self {
+                    older: TransferFee::from_bytes(&fees[..TransferFee::LEN]),
+                    newer: TransferFee::from_bytes(&fees[TransferFee::LEN..]),
+                }));
+            }
+
+            extensions = &extensions[4 + len..];
+        }
+
+        Ok(None)
+    }
+
+    pub fn epoch_fee(&self, epoch: u64) -> TransferFee {
+        if epoch >= self.newer.epoch { self.newer } else { self.older }
+    }
+}
+
+/// The transfer fee `mint` charges in `clock`'s epoch.
+pub fn active_transfer_fee(mint: &AccountInfo, clock: &Clock) -> Result<TransferFee, ProgramError> {
+    match TransferFeeConfig::from_mint(mint)? {
+        Some(config) => Ok(config.epoch_fee(clock.epoch)),
+        None => Ok(TransferFee::NONE),
+    }
+}
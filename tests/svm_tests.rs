@@ -0,0 +1,316 @@
+use solana_program_test::{BanksClient, ProgramTest};
+use solana_sdk::account::Account;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{keypair_from_seed, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use spl_token::solana_program::program_option::COption;
+use spl_token::solana_program::program_pack::Pack;
+
+use pinocchio_amm::{Pool, ID};
+
+const PROGRAM: Pubkey = Pubkey::new_from_array(ID);
+
+/// The program `cargo build-sbf` writes to `target/deploy`, which
+/// `.cargo/config.toml` points `SBF_OUT_DIR` at.
+const PROGRAM_NAME: &str = "pinocchio_amm";
+
+/// Upper bounds on the compute units of the hot paths, just above their measured
+/// cost with the fixed keys below: Swap 23_961 (A to B) and 23_768 (B to A),
+/// AddLiquidity 33_227 and RemoveLiquidity 30_754. A change that costs more
+/// fails here; update the baseline with it if the cost is intended.
+const SWAP_CU_LIMIT: u64 = 24_500;
+const ADD_LIQUIDITY_CU_LIMIT: u64 = 34_000;
+const REMOVE_LIQUIDITY_CU_LIMIT: u64 = 31_500;
+
+fn mint_account(mint_authority: &Pubkey) -> Account {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply: u64::MAX,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }.pack_into_slice(&mut data);
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+/// Keys of a pool over two fixed mints, derived the way `InitializePool` derives them.
+/// Fixed keys keep the PDA bumps, and so the compute units, the same on every run.
+struct PoolKeys {
+    pool: Pubkey,
+    pool_bump: u8,
+    lp_mint: Pubkey,
+    lp_mint_bump: u8,
+    token_a_mint: Pubkey,
+    token_b_mint: Pubkey,
+    token_a_vault: Pubkey,
+    token_b_vault: Pubkey,
+    user_token_a: Pubkey,
+    user_token_b: Pubkey,
+    user_lp_token: Pubkey,
+}
+
+impl PoolKeys {
+    fn new(user: &Pubkey) -> Self {
+        let (token_a_mint, token_b_mint) = (Pubkey::new_from_array([1; 32]), Pubkey::new_from_array([2; 32]));
+        let (pool, pool_bump) = Pubkey::find_program_address(&[b"pool", token_a_mint.as_ref(), token_b_mint.as_ref()], &PROGRAM);
+        let (lp_mint, lp_mint_bump) = Pubkey::find_program_address(&[b"lp_mint", pool.as_ref()], &PROGRAM);
+        let ata = spl_associated_token_account::get_associated_token_address;
+
+        Self {
+            pool,
+            pool_bump,
+            lp_mint,
+            lp_mint_bump,
+            token_a_mint,
+            token_b_mint,
+            token_a_vault: ata(&pool, &token_a_mint),
+            token_b_vault: ata(&pool, &token_b_mint),
+            user_token_a: ata(user, &token_a_mint),
+            user_token_b: ata(user, &token_b_mint),
+            user_lp_token: ata(user, &lp_mint),
+        }
+    }
+
+    fn initialize_pool(&self, authority: &Pubkey, fee_rate: u16, lp_decimals: u8) -> Instruction {
+        let mut data = vec![0];
+        data.extend_from_slice(&fee_rate.to_le_bytes());
+        data.push(lp_decimals);
+
+        Instruction::new_with_bytes(PROGRAM, &data, vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new_readonly(self.token_a_mint, false),
+            AccountMeta::new_readonly(self.token_b_mint, false),
+            AccountMeta::new(self.token_a_vault, false),
+            AccountMeta::new(self.token_b_vault, false),
+            AccountMeta::new(self.lp_mint, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::rent::ID, false),
+        ])
+    }
+
+    fn add_liquidity(&self, user: &Pubkey, max_amount_a: u64, max_amount_b: u64, min_lp_amount: u64) -> Instruction {
+        let mut data = vec![1];
+        data.extend_from_slice(&max_amount_a.to_le_bytes());
+        data.extend_from_slice(&max_amount_b.to_le_bytes());
+        data.extend_from_slice(&min_lp_amount.to_le_bytes());
+
+        Instruction::new_with_bytes(PROGRAM, &data, vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new(self.lp_mint, false),
+            AccountMeta::new(self.token_a_vault, false),
+            AccountMeta::new(self.token_b_vault, false),
+            AccountMeta::new(self.user_token_a, false),
+            AccountMeta::new(self.user_token_b, false),
+            AccountMeta::new(self.user_lp_token, false),
+            AccountMeta::new_readonly(self.token_a_mint, false),
+            AccountMeta::new_readonly(self.token_b_mint, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_associated_token_account::ID, false),
+            AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::rent::ID, false),
+        ])
+    }
+
+
+    fn remove_liquidity(&self, user: &Pubkey, lp_tokens: u64) -> Instruction {
+        let mut data = vec![2];
+        data.extend_from_slice(&lp_tokens.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+
+        Instruction::new_with_bytes(PROGRAM, &data, vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new(self.lp_mint, false),
+            AccountMeta::new(self.token_a_vault, false),
+            AccountMeta::new(self.token_b_vault, false),
+            AccountMeta::new(self.user_token_a, false),
+            AccountMeta::new(self.user_token_b, false),
+            AccountMeta::new(self.user_lp_token, false),
+            AccountMeta::new_readonly(self.token_a_mint, false),
+            AccountMeta::new_readonly(self.token_b_mint, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::rent::ID, false),
+        ])
+    }
+
+    fn swap(&self, user: &Pubkey, amount_in: u64, a_to_b: bool) -> Instruction {
+        let mut data = vec![3];
+        data.extend_from_slice(&amount_in.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(a_to_b as u8);
+
+        Instruction::new_with_bytes(PROGRAM, &data, vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(self.pool, false),
+            AccountMeta::new(self.token_a_vault, false),
+            AccountMeta::new(self.token_b_vault, false),
+            AccountMeta::new(self.user_token_a, false),
+            AccountMeta::new(self.user_token_b, false),
+            AccountMeta::new_readonly(self.token_a_mint, false),
+            AccountMeta::new_readonly(self.token_b_mint, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::rent::ID, false),
+        ])
+    }
+}
+
+/// Starts a bank with the program, two funded mints and a user holding both.
+/// The user every test signs with, fixed for the same reason as [`PoolKeys`].
+fn user() -> Keypair {
+    keypair_from_seed(&[7; 32]).expect("Should derive a keypair")
+}
+
+async fn start(user: &Keypair, keys: &PoolKeys, balance: u64) -> (BanksClient, Keypair, Hash) {
+    assert!(
+        solana_program_test::find_file(&format!("{PROGRAM_NAME}.so")).is_some(),
+        "{PROGRAM_NAME}.so not found; run `cargo build-sbf` before the SVM tests"
+    );
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(true);
+    program_test.add_program(PROGRAM_NAME, PROGRAM, None);
+    program_test.add_account(user.pubkey(), Account::new(10_000_000_000, 0, &solana_sdk::system_program::ID));
+    program_test.add_account(keys.token_a_mint, mint_account(&Pubkey::new_unique()));
+    program_test.add_account(keys.token_b_mint, mint_account(&Pubkey::new_unique()));
+    program_test.add_account(keys.user_token_a, token_account(&keys.token_a_mint, &user.pubkey(), balance));
+    program_test.add_account(keys.user_token_b, token_account(&keys.token_b_mint, &user.pubkey(), balance));
+
+    program_test.start().await
+}
+
+/// Signs `instruction` with the payer and `user` and runs it, returning the compute units it used.
+async fn process(banks_client: &mut BanksClient, payer: &Keypair, user: &Keypair, instruction: Instruction) -> u64 {
+    let recent_blockhash = banks_client.get_latest_blockhash().await.expect("Should get a blockhash");
+    let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &[payer, user], recent_blockhash);
+    let result = banks_client.process_transaction_with_metadata(transaction).await.expect("Should reach the bank");
+    let metadata = result.metadata.expect("Should return metadata");
+    result.result.unwrap_or_else(|error| panic!("Transaction failed: {error:?}\n{}", metadata.log_messages.join("\n")));
+    metadata.compute_units_consumed
+}
+
+async fn token_balance(banks_client: &mut BanksClient, account: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*account).await.expect("Should reach the bank").expect("Token account should exist");
+    spl_token::state::Account::unpack(&account.data).expect("Should be a token account").amount
+}
+
+async fn mint_supply(banks_client: &mut BanksClient, mint: &Pubkey) -> u64 {
+    let account = banks_client.get_account(*mint).await.expect("Should reach the bank").expect("Mint should exist");
+    spl_token::state::Mint::unpack(&account.data).expect("Should be a mint").supply
+}
+
+#[tokio::test]
+async fn test_svm_initialize_pool_then_first_deposit() {
+    let user = user();
+    let keys = PoolKeys::new(&user.pubkey());
+    let (mut banks_client, payer, _) = start(&user, &keys, 10_000_000).await;
+
+    process(&mut banks_client, &payer, &user, keys.initialize_pool(&user.pubkey(), 30, 6)).await;
+
+    let pool_account = banks_client.get_account(keys.pool).await.unwrap().expect("Pool should exist");
+    assert_eq!((pool_account.owner, pool_account.data.len()), (PROGRAM, Pool::LEN));
+    let pool = Pool::load(&pool_account.data).expect("Should load pool");
+    assert_eq!(pool.authority, user.pubkey().to_bytes());
+    assert_eq!((pool.token_a_mint, pool.token_b_mint), (keys.token_a_mint.to_bytes(), keys.token_b_mint.to_bytes()));
+    assert_eq!((pool.token_a_vault, pool.token_b_vault), (keys.token_a_vault.to_bytes(), keys.token_b_vault.to_bytes()));
+    assert_eq!(pool.lp_mint, keys.lp_mint.to_bytes());
+    assert_eq!((pool.fee_rate, pool.bump, pool.lp_mint_bump), (30, keys.pool_bump, keys.lp_mint_bump));
+
+    for (vault, mint) in [(keys.token_a_vault, keys.token_a_mint), (keys.token_b_vault, keys.token_b_mint)] {
+        let account = banks_client.get_account(vault).await.unwrap().expect("Vault should exist");
+        let vault = spl_token::state::Account::unpack(&account.data).expect("Vault should be a token account");
+        assert_eq!((account.owner, vault.mint, vault.owner, vault.amount), (spl_token::ID, mint, keys.pool, 0));
+    }
+
+    let account = banks_client.get_account(keys.lp_mint).await.unwrap().expect("LP mint should exist");
+    let lp_mint = spl_token::state::Mint::unpack(&account.data).expect("LP mint should be a mint");
+    assert_eq!(account.owner, spl_token::ID);
+    assert_eq!((lp_mint.mint_authority, lp_mint.decimals, lp_mint.supply), (COption::Some(keys.pool), 6, 0));
+
+    // The first deposit is taken in full and creates the user's LP account.
+    process(&mut banks_client, &payer, &user, keys.add_liquidity(&user.pubkey(), 1_000_000, 2_000_000, 0)).await;
+
+    assert_eq!(token_balance(&mut banks_client, &keys.token_a_vault).await, 1_000_000);
+    assert_eq!(token_balance(&mut banks_client, &keys.token_b_vault).await, 2_000_000);
+    assert_eq!(token_balance(&mut banks_client, &keys.user_token_a).await, 9_000_000);
+    assert_eq!(token_balance(&mut banks_client, &keys.user_token_b).await, 8_000_000);
+}
+
+#[tokio::test]
+async fn test_svm_first_deposit_mints_lp_to_user() {
+    let user = user();
+    let keys = PoolKeys::new(&user.pubkey());
+    let (mut banks_client, payer, _) = start(&user, &keys, 10_000_000).await;
+
+    process(&mut banks_client, &payer, &user, keys.initialize_pool(&user.pubkey(), 30, 6)).await;
+    process(&mut banks_client, &payer, &user, keys.add_liquidity(&user.pubkey(), 1_000_000, 4_000_000, 0)).await;
+
+    // sqrt(1_000_000 * 4_000_000) = 2_000_000, less the locked minimum, which
+    // the pool records instead of minting.
+    let lp_tokens = 2_000_000 - Pool::MINIMUM_LIQUIDITY;
+    assert_eq!(token_balance(&mut banks_client, &keys.user_lp_token).await, lp_tokens);
+    assert_eq!(mint_supply(&mut banks_client, &keys.lp_mint).await, lp_tokens);
+
+    let pool_account = banks_client.get_account(keys.pool).await.unwrap().expect("Pool should exist");
+    let pool = Pool::load(&pool_account.data).expect("Should load pool");
+    assert_eq!(pool.locked_liquidity, Pool::MINIMUM_LIQUIDITY);
+    assert_eq!(pool.total_lp_supply(lp_tokens), Ok(2_000_000));
+}
+
+#[tokio::test]
+async fn test_svm_hot_paths_stay_within_compute_budget() {
+    let user = user();
+    let keys = PoolKeys::new(&user.pubkey());
+    let (mut banks_client, payer, _) = start(&user, &keys, 10_000_000).await;
+
+    process(&mut banks_client, &payer, &user, keys.initialize_pool(&user.pubkey(), 30, 6)).await;
+    process(&mut banks_client, &payer, &user, keys.add_liquidity(&user.pubkey(), 1_000_000, 2_000_000, 0)).await;
+
+    // Measured once the user's LP account exists, as on every deposit after the first.
+    let add = process(&mut banks_client, &payer, &user, keys.add_liquidity(&user.pubkey(), 100_000, 200_000, 0)).await;
+    let swap_a_to_b = process(&mut banks_client, &payer, &user, keys.swap(&user.pubkey(), 10_000, true)).await;
+    let swap_b_to_a = process(&mut banks_client, &payer, &user, keys.swap(&user.pubkey(), 10_000, false)).await;
+    let remove = process(&mut banks_client, &payer, &user, keys.remove_liquidity(&user.pubkey(), 10_000)).await;
+    assert!(add <= ADD_LIQUIDITY_CU_LIMIT, "AddLiquidity used {add} CU");
+    assert!(swap_a_to_b <= SWAP_CU_LIMIT, "Swap used {swap_a_to_b} CU");
+    assert!(swap_b_to_a <= SWAP_CU_LIMIT, "Swap used {swap_b_to_a} CU");
+    assert!(remove <= REMOVE_LIQUIDITY_CU_LIMIT, "RemoveLiquidity used {remove} CU");
+}
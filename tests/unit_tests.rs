@@ -2,13 +2,17 @@ use solana_sdk::pubkey;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::rent::Rent;
 use solana_sdk::sysvar::Sysvar;
-use spl_token;
-use spl_associated_token_account;
+use spl_token::solana_program::program_option::COption;
+use spl_token::solana_program::program_pack::Pack;
 extern crate alloc;
 use alloc::vec;
 
+use pinocchio::account_info::AccountInfo;
+use pinocchio::program_error::ProgramError;
+use pinocchio::sysvars::clock::Clock;
 use pinocchio_amm::ID;
 use pinocchio_amm::Pool;
+use pinocchio_amm::PinocchioError;
 
 pub const PROGRAM: Pubkey = Pubkey::new_from_array(ID);
 
@@ -16,6 +20,8 @@ pub const RENT: Pubkey = pubkey!("SysvarRent111111111111111111111111111111111");
 
 pub const PAYER: Pubkey = pubkey!("Bv1vrbzogVpKNW2iRYJXLRUEVv6gD8xd9gid1Yh6hoiQ");
 
+pub const TOKEN_2022: Pubkey = Pubkey::new_from_array(pinocchio_amm::token_interface::TOKEN_2022_PROGRAM_ID);
+
 pub fn get_rent_data() -> Vec<u8> {
     let rent = Rent::default();
     unsafe {
@@ -23,6 +29,465 @@ pub fn get_rent_data() -> Vec<u8> {
     }
 }
 
+/// The clock host tests run instructions at; host builds have no `Clock` sysvar.
+pub fn test_clock() -> Clock {
+    Clock { slot: 100, epoch: 1, unix_timestamp: 1_700_000_000, ..Default::default() }
+}
+
+/// Size of the account header that precedes the data in the runtime input
+/// buffer (borrow state, flags, original data length, key, owner, lamports
+/// and data length).
+const ACCOUNT_HEADER_LEN: usize = 88;
+
+/// An account laid out the way the runtime serializes it, so instructions that
+/// don't CPI can be driven directly through their `TryFrom`/`process` pair.
+pub struct TestAccount {
+    buffer: Vec<u64>,
+}
+
+impl TestAccount {
+    pub fn new(key: Pubkey, owner: Pubkey, is_signer: bool, data: &[u8]) -> Self {
+        let mut account = Self {
+            buffer: vec![0u64; (ACCOUNT_HEADER_LEN + data.len()).div_ceil(8)],
+        };
+        let bytes = account.bytes_mut();
+        bytes[1] = is_signer as u8;
+        bytes[2] = 1;
+        bytes[8..40].copy_from_slice(key.as_ref());
+        bytes[40..72].copy_from_slice(owner.as_ref());
+        bytes[80..88].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes[ACCOUNT_HEADER_LEN..ACCOUNT_HEADER_LEN + data.len()].copy_from_slice(data);
+        account
+    }
+
+    pub fn info(&mut self) -> AccountInfo {
+        unsafe { core::mem::transmute::<*mut u64, AccountInfo>(self.buffer.as_mut_ptr()) }
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes_mut()[ACCOUNT_HEADER_LEN..]
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut u8, self.buffer.len() * 8) }
+    }
+}
+
+/// Builds a program-owned pool account with `authority` as admin and the given fee rate.
+pub fn pool_account(authority: &Pubkey, fee_rate: u16) -> TestAccount {
+    let (token_a_mint, token_b_mint) = sorted_mints();
+    pool_account_for_mints(authority, fee_rate, &token_a_mint, &token_b_mint)
+}
+
+/// Like [`pool_account`], for a given (canonically ordered) mint pair.
+pub fn pool_account_for_mints(authority: &Pubkey, fee_rate: u16, token_a_mint: &Pubkey, token_b_mint: &Pubkey) -> TestAccount {
+    let (pool_pda, pool_bump) = Pubkey::find_program_address(
+        &[b"pool", token_a_mint.as_ref(), token_b_mint.as_ref()],
+        &PROGRAM,
+    );
+    let (lp_mint_pda, lp_mint_bump) = Pubkey::find_program_address(
+        &[b"lp_mint", pool_pda.as_ref()],
+        &PROGRAM,
+    );
+
+    let mut pool_data = vec![0u8; Pool::LEN];
+    Pool::load_mut(&mut pool_data).expect("Should load pool").set_inner_full(
+        authority.to_bytes(),
+        token_a_mint.to_bytes(),
+        token_b_mint.to_bytes(),
+        spl_associated_token_account::get_associated_token_address(&pool_pda, token_a_mint).to_bytes(),
+        spl_associated_token_account::get_associated_token_address(&pool_pda, token_b_mint).to_bytes(),
+        lp_mint_pda.to_bytes(),
+        fee_rate,
+        pool_bump,
+        lp_mint_bump,
+    );
+
+    TestAccount::new(pool_pda, PROGRAM, false, &pool_data)
+}
+
+/// Two fresh mint keys in the canonical `token_a_mint < token_b_mint` order.
+pub fn sorted_mints() -> (Pubkey, Pubkey) {
+    let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+    if mint_x < mint_y { (mint_x, mint_y) } else { (mint_y, mint_x) }
+}
+
+/// Serialized SPL token account holding `amount` of `mint` for `owner`.
+pub fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }.pack_into_slice(&mut data);
+    data
+}
+
+/// Token-2022 layout: `base` padded to a token account's size, the account type
+/// byte, then an opaque extension entry.
+pub fn with_extensions(mut base: Vec<u8>, account_type: u8) -> Vec<u8> {
+    base.resize(spl_token::state::Account::LEN, 0);
+    base.push(account_type);
+    // Type 3 (close authority), 32 bytes.
+    base.extend_from_slice(&[3, 0, 32, 0]);
+    base.extend_from_slice(&[0; 32]);
+    base
+}
+
+/// Serialized SPL mint with `mint_authority` and the given supply.
+pub fn mint_data(mint_authority: &Pubkey, supply: u64) -> Vec<u8> {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    data
+}
+
+/// A pool with funded vaults, its LP mint and a depositor, for driving
+/// `process` directly. CPIs are no-ops on the host, so balances only change
+/// when a test writes them.
+pub struct PoolFixture {
+    pub pool: TestAccount,
+    pub lp_mint: TestAccount,
+    pub token_a_vault: TestAccount,
+    pub token_b_vault: TestAccount,
+    pub token_a_mint: TestAccount,
+    pub token_b_mint: TestAccount,
+    pub user: TestAccount,
+    pub user_token_a: TestAccount,
+    pub user_token_b: TestAccount,
+    pub user_lp_token: TestAccount,
+    pub token_program: TestAccount,
+    /// Passed to every instruction the fixture runs.
+    pub clock: Clock,
+}
+
+impl PoolFixture {
+    pub fn new(reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Self {
+        let (token_a_mint, token_b_mint) = sorted_mints();
+        Self::with_mints(&token_a_mint, &token_b_mint, reserve_a, reserve_b, lp_supply)
+    }
+
+    pub fn with_mints(token_a_mint: &Pubkey, token_b_mint: &Pubkey, reserve_a: u64, reserve_b: u64, lp_supply: u64) -> Self {
+        let mut pool = pool_account_for_mints(&Pubkey::new_unique(), 30, token_a_mint, token_b_mint);
+        let pool_key = Pubkey::new_from_array(*pool.info().key());
+        let (token_a_mint, token_b_mint, token_a_vault, token_b_vault, lp_mint) = {
+            let state = Pool::load_mut(pool.data_mut()).expect("Should load pool");
+            (
+                Pubkey::new_from_array(state.token_a_mint),
+                Pubkey::new_from_array(state.token_b_mint),
+                Pubkey::new_from_array(state.token_a_vault),
+                Pubkey::new_from_array(state.token_b_vault),
+                Pubkey::new_from_array(state.lp_mint),
+            )
+        };
+        let user = Pubkey::new_unique();
+
+        Self {
+            pool,
+            lp_mint: TestAccount::new(lp_mint, spl_token::ID, false, &mint_data(&pool_key, lp_supply)),
+            token_a_vault: TestAccount::new(token_a_vault, spl_token::ID, false, &token_account_data(&token_a_mint, &pool_key, reserve_a)),
+            token_b_vault: TestAccount::new(token_b_vault, spl_token::ID, false, &token_account_data(&token_b_mint, &pool_key, reserve_b)),
+            token_a_mint: TestAccount::new(token_a_mint, spl_token::ID, false, &mint_data(&PAYER, 0)),
+            token_b_mint: TestAccount::new(token_b_mint, spl_token::ID, false, &mint_data(&PAYER, 0)),
+            user: TestAccount::new(user, solana_sdk::system_program::ID, true, &[]),
+            user_token_a: TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &token_account_data(&token_a_mint, &user, u64::MAX)),
+            user_token_b: TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &token_account_data(&token_b_mint, &user, u64::MAX)),
+            user_lp_token: TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &token_account_data(&lp_mint, &user, 0)),
+            token_program: TestAccount::new(spl_token::ID, solana_sdk::bpf_loader::ID, false, &[]),
+            clock: test_clock(),
+        }
+    }
+
+    pub fn pool_mut(&mut self) -> &mut Pool {
+        Pool::load_mut(self.pool.data_mut()).expect("Should load pool")
+    }
+
+    /// Overwrites the vault balances and LP supply, standing in for the token CPIs.
+    pub fn set_balances(&mut self, vault_a: u64, vault_b: u64, lp_supply: u64) {
+        let pool_key = Pubkey::new_from_array(*self.pool.info().key());
+        let token_a_mint = Pubkey::new_from_array(*self.token_a_mint.info().key());
+        let token_b_mint = Pubkey::new_from_array(*self.token_b_mint.info().key());
+        self.token_a_vault.data_mut()[..spl_token::state::Account::LEN]
+            .copy_from_slice(&token_account_data(&token_a_mint, &pool_key, vault_a));
+        self.token_b_vault.data_mut()[..spl_token::state::Account::LEN]
+            .copy_from_slice(&token_account_data(&token_b_mint, &pool_key, vault_b));
+        self.lp_mint.data_mut()[..spl_token::state::Mint::LEN]
+            .copy_from_slice(&mint_data(&pool_key, lp_supply));
+    }
+
+    pub fn add_liquidity(&mut self, data: pinocchio_amm::instructions::AddLiquidityData) -> Result<(), ProgramError> {
+        use pinocchio_amm::instructions::{AddLiquidity, AddLiquidityAccounts};
+
+        let user = self.user.info();
+        let pool = self.pool.info();
+        let lp_mint = self.lp_mint.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let user_token_a = self.user_token_a.info();
+        let user_token_b = self.user_token_b.info();
+        let user_lp_token = self.user_lp_token.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+        let token_program = self.token_program.info();
+
+        AddLiquidity {
+            accounts: AddLiquidityAccounts {
+                user: &user,
+                pool: &pool,
+                lp_mint: &lp_mint,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                user_token_a: &user_token_a,
+                user_token_b: &user_token_b,
+                user_lp_token: &user_lp_token,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+                token_a_program: &token_program,
+                token_b_program: &token_program,
+                token_program: &token_program,
+                associated_token_program: &token_program,
+                system_program: &token_program,
+            },
+            data,
+        }.process_at(&self.clock)
+    }
+
+    pub fn swap(&mut self, data: pinocchio_amm::instructions::SwapData) -> Result<(), ProgramError> {
+        use pinocchio_amm::instructions::{Swap, SwapAccounts};
+
+        let user = self.user.info();
+        let pool = self.pool.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let user_token_a = self.user_token_a.info();
+        let user_token_b = self.user_token_b.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+        let token_program = self.token_program.info();
+
+        Swap {
+            accounts: SwapAccounts {
+                user: &user,
+                pool: &pool,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                user_token_a: &user_token_a,
+                user_token_b: &user_token_b,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+                token_a_program: &token_program,
+                token_b_program: &token_program,
+                observations: None,
+            },
+            data,
+        }.process_at(&self.clock)
+    }
+
+    pub fn swap_exact_out(&mut self, data: pinocchio_amm::instructions::SwapExactOutData) -> Result<(), ProgramError> {
+        use pinocchio_amm::instructions::{SwapAccounts, SwapExactOut};
+
+        let user = self.user.info();
+        let pool = self.pool.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let user_token_a = self.user_token_a.info();
+        let user_token_b = self.user_token_b.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+        let token_program = self.token_program.info();
+
+        SwapExactOut {
+            accounts: SwapAccounts {
+                user: &user,
+                pool: &pool,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                user_token_a: &user_token_a,
+                user_token_b: &user_token_b,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+                token_a_program: &token_program,
+                token_b_program: &token_program,
+                observations: None,
+            },
+            data,
+        }.process_at(&self.clock)
+    }
+
+    pub fn remove_liquidity(&mut self, data: pinocchio_amm::instructions::RemoveLiquidityData) -> Result<(), ProgramError> {
+        use pinocchio_amm::instructions::{RemoveLiquidity, RemoveLiquidityAccounts};
+
+        let user = self.user.info();
+        let pool = self.pool.info();
+        let lp_mint = self.lp_mint.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let user_token_a = self.user_token_a.info();
+        let user_token_b = self.user_token_b.info();
+        let user_lp_token = self.user_lp_token.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+        let token_program = self.token_program.info();
+
+        RemoveLiquidity {
+            accounts: RemoveLiquidityAccounts {
+                user: &user,
+                pool: &pool,
+                lp_mint: &lp_mint,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                user_token_a: &user_token_a,
+                user_token_b: &user_token_b,
+                user_lp_token: &user_lp_token,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+                token_a_program: &token_program,
+                token_b_program: &token_program,
+                token_program: &token_program,
+            },
+            data,
+        }.process_at(&self.clock)
+    }
+
+    pub fn deposit_single(&mut self, data: pinocchio_amm::instructions::DepositSingleData) -> Result<(), ProgramError> {
+        use pinocchio_amm::instructions::{DepositSingle, DepositSingleAccounts};
+
+        let user = self.user.info();
+        let pool = self.pool.info();
+        let lp_mint = self.lp_mint.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let user_token = if data.deposit_a { self.user_token_a.info() } else { self.user_token_b.info() };
+        let user_lp_token = self.user_lp_token.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+        let token_program = self.token_program.info();
+
+        DepositSingle {
+            accounts: DepositSingleAccounts {
+                user: &user,
+                pool: &pool,
+                lp_mint: &lp_mint,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                user_token: &user_token,
+                user_lp_token: &user_lp_token,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+                token_a_program: &token_program,
+                token_b_program: &token_program,
+                token_program: &token_program,
+                associated_token_program: &token_program,
+                system_program: &token_program,
+                observations: None,
+            },
+            data,
+        }.process_at(&self.clock)
+    }
+
+    pub fn withdraw_single(&mut self, data: pinocchio_amm::instructions::WithdrawSingleData) -> Result<(), ProgramError> {
+        use pinocchio_amm::instructions::{WithdrawSingle, WithdrawSingleAccounts};
+
+        let user = self.user.info();
+        let pool = self.pool.info();
+        let lp_mint = self.lp_mint.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let user_token = if data.withdraw_a { self.user_token_a.info() } else { self.user_token_b.info() };
+        let user_lp_token = self.user_lp_token.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+        let token_program = self.token_program.info();
+
+        WithdrawSingle {
+            accounts: WithdrawSingleAccounts {
+                user: &user,
+                pool: &pool,
+                lp_mint: &lp_mint,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                user_token: &user_token,
+                user_lp_token: &user_lp_token,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+                token_a_program: &token_program,
+                token_b_program: &token_program,
+                token_program: &token_program,
+                observations: None,
+            },
+            data,
+        }.process_at(&self.clock)
+    }
+}
+
+impl PoolFixture {
+    pub fn quote_swap(&mut self, amount_in: u64, swap_direction: bool) -> Result<pinocchio_amm::instructions::SwapQuote, ProgramError> {
+        use pinocchio_amm::instructions::{QuoteSwap, QuoteSwapAccounts, QuoteSwapData};
+
+        let pool = self.pool.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+
+        QuoteSwap {
+            accounts: QuoteSwapAccounts {
+                pool: &pool,
+                token_a_vault: &token_a_vault,
+                token_b_vault: &token_b_vault,
+                token_a_mint: &token_a_mint,
+                token_b_mint: &token_b_mint,
+            },
+            data: QuoteSwapData { amount_in, swap_direction },
+        }.quote(&self.clock)
+    }
+
+    fn quote_liquidity_accounts<R>(&mut self, quote: impl FnOnce(pinocchio_amm::instructions::QuoteLiquidityAccounts) -> R) -> R {
+        use pinocchio_amm::instructions::QuoteLiquidityAccounts;
+
+        let pool = self.pool.info();
+        let lp_mint = self.lp_mint.info();
+        let token_a_vault = self.token_a_vault.info();
+        let token_b_vault = self.token_b_vault.info();
+        let token_a_mint = self.token_a_mint.info();
+        let token_b_mint = self.token_b_mint.info();
+
+        quote(QuoteLiquidityAccounts {
+            pool: &pool,
+            lp_mint: &lp_mint,
+            token_a_vault: &token_a_vault,
+            token_b_vault: &token_b_vault,
+            token_a_mint: &token_a_mint,
+            token_b_mint: &token_b_mint,
+        })
+    }
+
+    pub fn quote_add_liquidity(&mut self, max_amount_a: u64, max_amount_b: u64) -> Result<pinocchio_amm::instructions::AddLiquidityQuote, ProgramError> {
+        use pinocchio_amm::instructions::{QuoteAddLiquidity, QuoteAddLiquidityData};
+
+        let clock = self.clock;
+        self.quote_liquidity_accounts(|accounts| {
+            QuoteAddLiquidity { accounts, data: QuoteAddLiquidityData { max_amount_a, max_amount_b } }.quote(&clock)
+        })
+    }
+
+    pub fn quote_remove_liquidity(&mut self, lp_tokens: u64) -> Result<pinocchio_amm::instructions::RemoveLiquidityQuote, ProgramError> {
+        use pinocchio_amm::instructions::{QuoteRemoveLiquidity, QuoteRemoveLiquidityData};
+
+        let clock = self.clock;
+        self.quote_liquidity_accounts(|accounts| {
+            QuoteRemoveLiquidity { accounts, data: QuoteRemoveLiquidityData { lp_tokens } }.quote(&clock)
+        })
+    }
+}
+
 #[test]
 fn test_initialize_pool_complete() {
     let token_program = spl_token::ID;
@@ -60,7 +525,7 @@ fn test_initialize_pool_complete() {
 
     assert!(fee_rate <= 10000);
 
-    let addresses = vec![
+    let addresses = [
         pool_pda, lp_mint_pda, token_a_vault, token_b_vault, 
         token_a_mint, token_b_mint, token_program, associated_token_program
     ];
@@ -144,9 +609,28 @@ fn test_pool_state_size() {
     // - fee_rate: u16 (2 bytes)
     // - bump: u8 (1 byte)
     // - lp_mint_bump: u8 (1 byte)
-    // Total: 6*32 + 2 + 1 + 1 = 196 bytes
+    // - pending_authority: Pubkey (32 bytes)
+    // - status: u8 (1 byte)
+    // - _padding: [u8; 1] (1 byte)
+    // - protocol_fee_share: u16 (2 bytes)
+    // - protocol_fees_a: u64 (8 bytes)
+    // - protocol_fees_b: u64 (8 bytes)
+    // - locked_liquidity: u64 (8 bytes)
+    // - price_a_cumulative: [u8; 16] (16 bytes)
+    // - price_b_cumulative: [u8; 16] (16 bytes)
+    // - last_update_timestamp: i64 (8 bytes)
+    // - curve_type: u8 (1 byte)
+    // - _curve_padding: [u8; 3] (3 bytes)
+    // - weight_a: u16 (2 bytes)
+    // - weight_b: u16 (2 bytes)
+    // - amplification: u64 (8 bytes)
+    // - target_amplification: u64 (8 bytes)
+    // - ramp_start_timestamp: i64 (8 bytes)
+    // - ramp_end_timestamp: i64 (8 bytes)
+    // Total: 6*32 + 2 + 1 + 1 + 32 + 1 + 1 + 2 + 8 + 8 + 8 + 16 + 16 + 8 + 1 + 3 + 2 + 2 + 8 + 8 + 8 + 8 = 336 bytes
     
-    assert_eq!(Pool::LEN, 196);
+    assert_eq!(Pool::LEN, 336);
+    assert_eq!(Pool::LEN, core::mem::size_of::<Pool>());
 }
 
 #[test]
@@ -375,7 +859,7 @@ fn test_add_liquidity_edge_cases() {
     let geometric_mean = product.sqrt() as u64;
     assert_eq!(geometric_mean, 1);
     
-    let short_data = vec![1, 0, 0, 0]; 
+    let short_data = [1, 0, 0, 0]; 
     assert_eq!(short_data.len(), 4);
     assert!(short_data.len() < 25); 
     
@@ -391,4 +875,2392 @@ fn test_add_liquidity_edge_cases() {
         zero_instruction_data[5], zero_instruction_data[6], zero_instruction_data[7], zero_instruction_data[8],
     ]);
     assert_eq!(parsed_zero_a, 0);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_set_fee_rate_by_authority() {
+    use pinocchio_amm::instructions::SetFeeRate;
+
+    let authority = Pubkey::new_unique();
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [authority_account.info(), pool_account.info(), extra_account.info()];
+    let new_fee_rate: u16 = 100;
+    let data = new_fee_rate.to_le_bytes();
+
+    SetFeeRate::try_from((&accounts[..], &data[..]))
+        .expect("Should parse set fee rate")
+        .process()
+        .expect("Authority should be able to update the fee");
+
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert_eq!(pool.fee_rate, new_fee_rate);
+}
+
+#[test]
+fn test_set_fee_rate_unauthorized_signer() {
+    use pinocchio_amm::instructions::SetFeeRate;
+
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let mut attacker_account = TestAccount::new(attacker, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [attacker_account.info(), pool_account.info(), extra_account.info()];
+    let new_fee_rate: u16 = 100;
+    let data = new_fee_rate.to_le_bytes();
+
+    let result = SetFeeRate::try_from((&accounts[..], &data[..]))
+        .expect("Should parse set fee rate")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::Unauthorized)));
+
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert_eq!(pool.fee_rate, 30);
+}
+
+#[test]
+fn test_set_fee_rate_requires_signature() {
+    use pinocchio_amm::instructions::SetFeeRate;
+
+    let authority = Pubkey::new_unique();
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, false, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [authority_account.info(), pool_account.info(), extra_account.info()];
+    let new_fee_rate: u16 = 100;
+    let data = new_fee_rate.to_le_bytes();
+
+    let result = SetFeeRate::try_from((&accounts[..], &data[..]));
+    assert_eq!(result.err(), Some(ProgramError::MissingRequiredSignature));
+}
+
+#[test]
+fn test_set_fee_rate_bound() {
+    use pinocchio_amm::instructions::SetFeeRateData;
+
+    let max_fee = Pool::MAX_FEE_RATE.to_le_bytes();
+    assert_eq!(SetFeeRateData::try_from(&max_fee[..]).map(|data| data.fee_rate), Ok(Pool::MAX_FEE_RATE));
+
+    let invalid_fee = (Pool::MAX_FEE_RATE + 1).to_le_bytes();
+    assert_eq!(
+        SetFeeRateData::try_from(&invalid_fee[..]).err(),
+        Some(ProgramError::from(PinocchioError::InvalidFeeRate))
+    );
+    assert_eq!(SetFeeRateData::try_from(&[0u8][..]).err(), Some(ProgramError::InvalidInstructionData));
+}
+
+#[test]
+fn test_authority_transfer_propose_and_accept() {
+    use pinocchio_amm::instructions::{AcceptAuthority, ProposeAuthority};
+
+    let authority = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, true, &[]);
+    let mut new_authority_account = TestAccount::new(new_authority, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [authority_account.info(), pool_account.info(), extra_account.info()];
+    let data = new_authority.to_bytes();
+    ProposeAuthority::try_from((&accounts[..], &data[..]))
+        .expect("Should parse propose authority")
+        .process()
+        .expect("Authority should be able to propose a successor");
+
+    {
+        let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+        assert_eq!(pool.authority, authority.to_bytes());
+        assert_eq!(pool.pending_authority, new_authority.to_bytes());
+    }
+
+    let accounts = [new_authority_account.info(), pool_account.info(), extra_account.info()];
+    AcceptAuthority::try_from((&accounts[..], &[][..]))
+        .expect("Should parse accept authority")
+        .process()
+        .expect("Pending authority should be able to accept");
+
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert_eq!(pool.authority, new_authority.to_bytes());
+    assert!(!pool.has_pending_authority());
+}
+
+#[test]
+fn test_authority_transfer_rejects_wrong_signers() {
+    use pinocchio_amm::instructions::{AcceptAuthority, ProposeAuthority};
+
+    let authority = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, true, &[]);
+    let mut attacker_account = TestAccount::new(attacker, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let data = new_authority.to_bytes();
+    let accounts = [attacker_account.info(), pool_account.info(), extra_account.info()];
+    let result = ProposeAuthority::try_from((&accounts[..], &data[..]))
+        .expect("Should parse propose authority")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::Unauthorized)));
+
+    let accounts = [authority_account.info(), pool_account.info(), extra_account.info()];
+    ProposeAuthority::try_from((&accounts[..], &data[..]))
+        .expect("Should parse propose authority")
+        .process()
+        .expect("Authority should be able to propose a successor");
+
+    // Only the proposed key can complete the transfer, not the current authority or anyone else.
+    for signer in [&mut attacker_account, &mut authority_account] {
+        let accounts = [signer.info(), pool_account.info(), extra_account.info()];
+        let result = AcceptAuthority::try_from((&accounts[..], &[][..]))
+            .expect("Should parse accept authority")
+            .process();
+        assert_eq!(result, Err(ProgramError::from(PinocchioError::Unauthorized)));
+    }
+
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert_eq!(pool.authority, authority.to_bytes());
+}
+
+#[test]
+fn test_authority_transfer_cancel() {
+    use pinocchio_amm::instructions::{AcceptAuthority, CancelAuthorityTransfer, ProposeAuthority};
+
+    let authority = Pubkey::new_unique();
+    let new_authority = Pubkey::new_unique();
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, true, &[]);
+    let mut new_authority_account = TestAccount::new(new_authority, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [authority_account.info(), pool_account.info(), extra_account.info()];
+    let result = CancelAuthorityTransfer::try_from((&accounts[..], &[][..]))
+        .expect("Should parse cancel authority transfer")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::InvalidPoolState)));
+
+    let data = new_authority.to_bytes();
+    ProposeAuthority::try_from((&accounts[..], &data[..]))
+        .expect("Should parse propose authority")
+        .process()
+        .expect("Authority should be able to propose a successor");
+    CancelAuthorityTransfer::try_from((&accounts[..], &[][..]))
+        .expect("Should parse cancel authority transfer")
+        .process()
+        .expect("Authority should be able to cancel the proposal");
+
+    let accounts = [new_authority_account.info(), pool_account.info(), extra_account.info()];
+    let result = AcceptAuthority::try_from((&accounts[..], &[][..]))
+        .expect("Should parse accept authority")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::InvalidPoolState)));
+
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert_eq!(pool.authority, authority.to_bytes());
+}
+
+#[test]
+fn test_propose_authority_rejects_default_key() {
+    use pinocchio_amm::instructions::ProposeAuthorityData;
+
+    assert_eq!(
+        ProposeAuthorityData::try_from(&[0u8; 32][..]).err(),
+        Some(ProgramError::InvalidInstructionData)
+    );
+    assert!(ProposeAuthorityData::try_from(&Pubkey::new_unique().to_bytes()[..]).is_ok());
+}
+
+#[test]
+fn test_set_pool_status_unauthorized_signer() {
+    use pinocchio_amm::instructions::SetPoolStatus;
+
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let mut attacker_account = TestAccount::new(attacker, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [attacker_account.info(), pool_account.info(), extra_account.info()];
+    let data = [Pool::STATUS_MASK];
+    let result = SetPoolStatus::try_from((&accounts[..], &data[..]))
+        .expect("Should parse set pool status")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::Unauthorized)));
+
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert_eq!(pool.status, 0);
+}
+
+#[test]
+fn test_set_pool_status_rejects_unknown_bits() {
+    use pinocchio_amm::instructions::SetPoolStatusData;
+
+    assert_eq!(SetPoolStatusData::try_from(&[Pool::STATUS_MASK][..]).map(|data| data.status), Ok(Pool::STATUS_MASK));
+    assert_eq!(SetPoolStatusData::try_from(&[1 << 7][..]).err(), Some(ProgramError::InvalidInstructionData));
+    assert_eq!(SetPoolStatusData::try_from(&[][..]).err(), Some(ProgramError::InvalidInstructionData));
+}
+
+#[test]
+fn test_paused_swaps_keep_withdrawals_open() {
+    use pinocchio_amm::instructions::{
+        AddLiquidity, AddLiquidityAccounts, AddLiquidityData, RemoveLiquidity, RemoveLiquidityAccounts,
+        RemoveLiquidityData, SetPoolStatus, Swap, SwapAccounts, SwapData,
+    };
+
+    let authority = Pubkey::new_unique();
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, true, &[]);
+    let mut pool_account = pool_account(&authority, 30);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let accounts = [authority_account.info(), pool_account.info(), extra_account.info()];
+    let data = [Pool::SWAP_DISABLED | Pool::ADD_LIQUIDITY_DISABLED];
+    SetPoolStatus::try_from((&accounts[..], &data[..]))
+        .expect("Should parse set pool status")
+        .process()
+        .expect("Authority should be able to pause the pool");
+
+    let mut user_account = TestAccount::new(Pubkey::new_unique(), solana_sdk::system_program::ID, true, &[]);
+    let mut token_account = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &[0u8; 165]);
+    let mut mint_account = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &[0u8; 82]);
+    let user = user_account.info();
+    let pool = pool_account.info();
+    let token = token_account.info();
+    let mint = mint_account.info();
+    let program = extra_account.info();
+
+    let result = Swap {
+        accounts: SwapAccounts {
+            user: &user,
+            pool: &pool,
+            token_a_vault: &token,
+            token_b_vault: &token,
+            user_token_a: &token,
+            user_token_b: &token,
+            token_a_mint: &mint,
+            token_b_mint: &mint,
+            token_a_program: &program,
+            token_b_program: &program,
+            observations: None,
+        },
+        data: SwapData { amount_in: 1_000, min_amount_out: 0, swap_direction: true, deadline: None },
+    }.process_at(&test_clock());
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SwapDisabled)));
+
+    let result = AddLiquidity {
+        accounts: AddLiquidityAccounts {
+            user: &user,
+            pool: &pool,
+            lp_mint: &mint,
+            token_a_vault: &token,
+            token_b_vault: &token,
+            user_token_a: &token,
+            user_token_b: &token,
+            user_lp_token: &token,
+            token_a_mint: &mint,
+            token_b_mint: &mint,
+            token_a_program: &program,
+            token_b_program: &program,
+            token_program: &program,
+            associated_token_program: &program,
+            system_program: &program,
+        },
+        data: AddLiquidityData { max_amount_a: 1_000, max_amount_b: 1_000, min_lp_amount: 0, deadline: None },
+    }.process_at(&test_clock());
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::AddLiquidityDisabled)));
+
+    // Withdrawals get past the status check and fail later on the dummy vault.
+    let result = RemoveLiquidity {
+        accounts: RemoveLiquidityAccounts {
+            user: &user,
+            pool: &pool,
+            lp_mint: &mint,
+            token_a_vault: &token,
+            token_b_vault: &token,
+            user_token_a: &token,
+            user_token_b: &token,
+            user_lp_token: &token,
+            token_a_mint: &mint,
+            token_b_mint: &mint,
+            token_a_program: &program,
+            token_b_program: &program,
+            token_program: &program,
+        },
+        data: RemoveLiquidityData { lp_tokens: 1_000, min_amount_a: 0, min_amount_b: 0, deadline: None },
+    }.process_at(&test_clock());
+    assert_eq!(result, Err(ProgramError::InvalidAccountData));
+}
+
+#[test]
+fn test_protocol_fee_split_and_pricing_reserves() {
+    let mut pool_account = pool_account(&Pubkey::new_unique(), 30);
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+
+    // No protocol cut by default: the whole fee stays with LPs.
+    assert_eq!(pool.protocol_fee(3_000), Ok(0));
+
+    pool.protocol_fee_share = 2_500;
+    assert_eq!(pool.protocol_fee(3_000), Ok(750));
+    assert_eq!(pool.protocol_fee(3), Ok(0));
+
+    pool.protocol_fee_share = Pool::MAX_PROTOCOL_FEE_SHARE;
+    assert_eq!(pool.protocol_fee(u64::MAX), Ok(u64::MAX));
+
+    pool.protocol_fees_a = 750;
+    pool.protocol_fees_b = 20;
+    assert_eq!(pool.pricing_reserves(1_000_750, 2_000_020), Ok((1_000_000, 2_000_000)));
+    assert!(pool.pricing_reserves(700, 2_000_020).is_err());
+}
+
+#[test]
+fn test_set_protocol_fee_share_bound() {
+    use pinocchio_amm::instructions::SetProtocolFeeShareData;
+
+    let max_share = Pool::MAX_PROTOCOL_FEE_SHARE.to_le_bytes();
+    assert!(SetProtocolFeeShareData::try_from(&max_share[..]).is_ok());
+
+    let invalid_share = (Pool::MAX_PROTOCOL_FEE_SHARE + 1).to_le_bytes();
+    assert_eq!(
+        SetProtocolFeeShareData::try_from(&invalid_share[..]).err(),
+        Some(ProgramError::from(PinocchioError::InvalidFeeRate))
+    );
+}
+
+#[test]
+fn test_withdraw_protocol_fees() {
+    use pinocchio_amm::instructions::WithdrawProtocolFees;
+
+    let authority = Pubkey::new_unique();
+    let attacker = Pubkey::new_unique();
+    let mut pool_account = pool_account(&authority, 30);
+
+    let (token_a_mint, token_b_mint, token_a_vault, token_b_vault) = {
+        let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+        pool.protocol_fees_a = 750;
+        pool.protocol_fees_b = 20;
+        (pool.token_a_mint, pool.token_b_mint, pool.token_a_vault, pool.token_b_vault)
+    };
+
+    let mut authority_account = TestAccount::new(authority, solana_sdk::system_program::ID, true, &[]);
+    let mut attacker_account = TestAccount::new(attacker, solana_sdk::system_program::ID, true, &[]);
+    let mut token_a_vault_account = TestAccount::new(Pubkey::new_from_array(token_a_vault), spl_token::ID, false, &[0u8; 165]);
+    let mut token_b_vault_account = TestAccount::new(Pubkey::new_from_array(token_b_vault), spl_token::ID, false, &[0u8; 165]);
+    let mut treasury_a_account = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &[0u8; 165]);
+    let mut treasury_b_account = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &[0u8; 165]);
+    let mut token_a_mint_account = TestAccount::new(Pubkey::new_from_array(token_a_mint), spl_token::ID, false, &[0u8; 82]);
+    let mut token_b_mint_account = TestAccount::new(Pubkey::new_from_array(token_b_mint), spl_token::ID, false, &[0u8; 82]);
+    let mut token_program_account = TestAccount::new(spl_token::ID, solana_sdk::bpf_loader::ID, false, &[]);
+    let mut extra_account = TestAccount::new(RENT, solana_sdk::sysvar::ID, false, &[]);
+
+    let mut accounts = [
+        attacker_account.info(),
+        pool_account.info(),
+        token_a_vault_account.info(),
+        token_b_vault_account.info(),
+        treasury_a_account.info(),
+        treasury_b_account.info(),
+        token_a_mint_account.info(),
+        token_b_mint_account.info(),
+        token_program_account.info(),
+        token_program_account.info(),
+        extra_account.info(),
+    ];
+
+    let result = WithdrawProtocolFees::try_from((&accounts[..], &[][..]))
+        .expect("Should parse withdraw protocol fees")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::Unauthorized)));
+
+    accounts[0] = authority_account.info();
+    WithdrawProtocolFees::try_from((&accounts[..], &[][..]))
+        .expect("Should parse withdraw protocol fees")
+        .process()
+        .expect("Authority should be able to withdraw protocol fees");
+
+    {
+        let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+        assert_eq!(pool.protocol_fees_a, 0);
+        assert_eq!(pool.protocol_fees_b, 0);
+    }
+
+    let result = WithdrawProtocolFees::try_from((&accounts[..], &[][..]))
+        .expect("Should parse withdraw protocol fees")
+        .process();
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::InvalidAmount)));
+}
+
+#[test]
+fn test_initialize_pool_data_lp_decimals() {
+    use pinocchio_amm::instructions::InitializePoolData;
+
+    let fee_rate: u16 = 30;
+    let lp_decimals: u8 = 6;
+    let mut data = fee_rate.to_le_bytes().to_vec();
+    data.push(lp_decimals);
+
+    let parsed = InitializePoolData::try_from(&data[..]).expect("Should parse initialize pool data");
+    assert_eq!(parsed.fee_rate, fee_rate);
+    assert_eq!(parsed.lp_decimals, lp_decimals);
+
+    // The LP decimals byte is required.
+    assert_eq!(
+        InitializePoolData::try_from(&fee_rate.to_le_bytes()[..]).err(),
+        Some(ProgramError::InvalidInstructionData)
+    );
+
+    let mut invalid = (Pool::MAX_FEE_RATE + 1).to_le_bytes().to_vec();
+    invalid.push(lp_decimals);
+    assert_eq!(
+        InitializePoolData::try_from(&invalid[..]).err(),
+        Some(ProgramError::from(PinocchioError::InvalidFeeRate))
+    );
+}
+
+#[test]
+fn test_add_liquidity_first_deposit_reaches_pool_signed_mint() {
+    use pinocchio_amm::instructions::AddLiquidityData;
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+
+    // The vaults, LP mint and pool are released before the token CPIs, so the
+    // runtime borrow checks on the Transfer and pool-signed MintTo pass.
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: 1_000_000, max_amount_b: 2_000_000, min_lp_amount: 1_400_000, deadline: None })
+        .expect("First deposit should reach the pool-signed LP mint");
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: 1_000_000, max_amount_b: 2_000_000, min_lp_amount: 1_500_000, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+}
+
+#[test]
+fn test_add_liquidity_rejects_foreign_lp_mint_authority() {
+    use pinocchio_amm::instructions::AddLiquidityData;
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    fixture.lp_mint.data_mut()[..spl_token::state::Mint::LEN]
+        .copy_from_slice(&mint_data(&Pubkey::new_unique(), 0));
+
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: 1_000_000, max_amount_b: 2_000_000, min_lp_amount: 0, deadline: None });
+    assert_eq!(result, Err(ProgramError::InvalidAccountData));
+}
+
+#[test]
+fn test_first_deposit_locks_minimum_liquidity() {
+    use pinocchio_amm::instructions::AddLiquidityData;
+
+    // sqrt(1_000_000 * 1_000_000) = 1_000_000, of which MINIMUM_LIQUIDITY stays locked.
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: 1_000_000, max_amount_b: 1_000_000, min_lp_amount: 999_001, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    assert_eq!(fixture.pool_mut().locked_liquidity, 0);
+
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: 1_000_000, max_amount_b: 1_000_000, min_lp_amount: 999_000, deadline: None })
+        .expect("First deposit should mint the liquidity above the locked minimum");
+    assert_eq!(fixture.pool_mut().locked_liquidity, Pool::MINIMUM_LIQUIDITY);
+    assert_eq!(fixture.pool_mut().total_lp_supply(999_000), Ok(1_000_000));
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: 1_000, max_amount_b: 1_000, min_lp_amount: 0, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::InsufficientLiquidity)));
+}
+
+#[test]
+fn test_locked_liquidity_blunts_donation_attack() {
+    use pinocchio_amm::instructions::AddLiquidityData;
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+
+    // Attacker seeds the pool, then burns everything but 1 LP so only the
+    // attacker's dust and the locked minimum back the reserves.
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: 1_000_000, max_amount_b: 1_000_000, min_lp_amount: 0, deadline: None })
+        .expect("Attacker deposit should succeed");
+    let attacker_lp = 1;
+    let remaining = Pool::MINIMUM_LIQUIDITY + attacker_lp;
+
+    // Donate straight into the vaults to inflate the price of a share.
+    let donation = 10_000_000;
+    fixture.set_balances(remaining + donation, remaining + donation, attacker_lp);
+
+    // Victim deposits the same amount as the donation. LP is priced off
+    // 1_001 shares, so the victim still receives 1_000 of them.
+    let deposit = 10_000_000;
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: deposit, max_amount_b: deposit, min_lp_amount: 1_001, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: deposit, max_amount_b: deposit, min_lp_amount: 1_000, deadline: None })
+        .expect("Victim deposit should mint LP");
+
+    let victim_lp = 1_000u128;
+    let total_supply = (remaining + victim_lp as u64) as u128;
+    let reserve_after = (remaining + donation + deposit) as u128;
+    let victim_claim = victim_lp * reserve_after / total_supply;
+    let attacker_claim = attacker_lp as u128 * reserve_after / total_supply;
+
+    // The victim loses under 0.1% to rounding and the attacker can't recover the donation.
+    assert!(victim_claim * 1000 >= deposit as u128 * 999, "victim claim {} too low", victim_claim);
+    assert!(attacker_claim < donation as u128 / 100, "attacker claim {} too high", attacker_claim);
+}
+
+/// `root` is the floor square root of `value` iff root^2 <= value < (root + 1)^2.
+fn is_floor_sqrt(value: u128, root: u128) -> bool {
+    let fits = root.checked_mul(root).is_some_and(|square| square <= value);
+    let next_exceeds = (root + 1).checked_mul(root + 1).is_none_or(|square| square > value);
+    fits && next_exceeds
+}
+
+#[test]
+fn test_integer_sqrt_exhaustive_small_values() {
+    use pinocchio_amm::math;
+
+    let mut root = 0u128;
+    for value in 0..=1_000_000u128 {
+        if (root + 1) * (root + 1) <= value {
+            root += 1;
+        }
+        assert_eq!(math::sqrt(value), root, "sqrt({})", value);
+    }
+}
+
+#[test]
+fn test_integer_sqrt_edge_values() {
+    use pinocchio_amm::math;
+
+    let max = u64::MAX as u128;
+    assert_eq!(math::sqrt(max * max), max);
+    assert_eq!(math::sqrt(max * max - 1), max - 1);
+    assert_eq!(math::sqrt(u128::MAX), max);
+    assert_eq!(math::sqrt(1u128 << 126), 1u128 << 63);
+
+    // Around perfect squares where f64 loses precision (beyond 2^53).
+    for root in [
+        (1u128 << 26) + 1,
+        (1u128 << 32) - 1,
+        1u128 << 32,
+        (1u128 << 53) + 1,
+        (1u128 << 63) - 1,
+        max - 1,
+        max,
+    ] {
+        let square = root * root;
+        assert_eq!(math::sqrt(square), root);
+        assert_eq!(math::sqrt(square - 1), root - 1);
+        if let Some(next) = square.checked_add(1) {
+            assert_eq!(math::sqrt(next), root);
+        }
+    }
+
+    // f64 rounds u64::MAX = (2^32)^2 - 1 up to 2^64, so its sqrt lands one too high.
+    assert_eq!((u64::MAX as f64).sqrt() as u128, 1u128 << 32);
+    assert_eq!(math::sqrt(u64::MAX as u128), (1u128 << 32) - 1);
+}
+
+#[test]
+fn test_integer_sqrt_matches_reference_on_pseudo_random_values() {
+    use pinocchio_amm::math;
+
+    // xorshift64* so the sample is deterministic without an extra dependency.
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut next = || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    };
+
+    for _ in 0..100_000 {
+        let a = next();
+        let b = next() >> (next() % 64);
+        let product = a as u128 * b as u128;
+        let root = math::sqrt(product);
+        assert!(is_floor_sqrt(product, root), "sqrt({}) = {}", product, root);
+        assert!(root <= u64::MAX as u128);
+    }
+}
+
+#[test]
+fn test_initial_lp_amount_uses_integer_sqrt() {
+    use pinocchio_amm::instructions::AddLiquidityData;
+
+    // sqrt(u64::MAX * u64::MAX) is exactly u64::MAX; the f64 path rounded this to 2^64 and saturated.
+    let amount = u64::MAX;
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: amount, max_amount_b: amount, min_lp_amount: amount - Pool::MINIMUM_LIQUIDITY + 1, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: amount, max_amount_b: amount, min_lp_amount: amount - Pool::MINIMUM_LIQUIDITY, deadline: None })
+        .expect("Initial LP amount should be exactly sqrt(a * b) - MINIMUM_LIQUIDITY");
+
+    // (2^32 + 1) * (2^32 - 1) = 2^64 - 1 has floor root 2^32 - 1; the f64 path minted 2^32.
+    let amount_a = (1u64 << 32) + 1;
+    let amount_b = (1u64 << 32) - 1;
+    let expected = (1u64 << 32) - 1 - Pool::MINIMUM_LIQUIDITY;
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: amount_a, max_amount_b: amount_b, min_lp_amount: expected + 1, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: amount_a, max_amount_b: amount_b, min_lp_amount: expected, deadline: None })
+        .expect("Initial LP amount should be the floor root");
+}
+
+#[test]
+fn test_hot_paths_sign_with_stored_bumps() {
+    use pinocchio_amm::instructions::{RemoveLiquidityData, SwapData};
+
+    // PDA searches are unavailable off-chain (`find_program_address` panics on
+    // the host), so these runs only complete if swap and withdraw sign with the
+    // bump stored in `Pool` instead of re-deriving it.
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+
+    fixture
+        .swap(SwapData { amount_in: 10_000, min_amount_out: 1, swap_direction: true, deadline: None })
+        .expect("Swap should sign with the stored pool bump");
+    fixture
+        .swap(SwapData { amount_in: 10_000, min_amount_out: 1, swap_direction: false, deadline: None })
+        .expect("Swap should sign with the stored pool bump");
+    fixture
+        .remove_liquidity(RemoveLiquidityData { lp_tokens: 1_000, min_amount_a: 1, min_amount_b: 1, deadline: None })
+        .expect("Withdraw should release its borrows and sign with the stored pool bump");
+}
+
+#[test]
+fn test_pool_account_checks_reject_mismatched_accounts() {
+    use pinocchio_amm::{PoolAccount, PoolAccountCheck};
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+    let pool_key = Pubkey::new_from_array(*fixture.pool.info().key());
+    let pool_data = fixture.pool.data_mut()[..Pool::LEN].to_vec();
+    let mut foreign_pool = TestAccount::new(pool_key, spl_token::ID, false, &pool_data);
+    let mut other_mint = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &mint_data(&PAYER, 0));
+    let other_mint_key = Pubkey::new_from_array(*other_mint.info().key());
+    let mut other_vault = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &token_account_data(&other_mint_key, &pool_key, 0));
+
+    let pool = fixture.pool.info();
+    let token_a_mint = fixture.token_a_mint.info();
+    let token_b_mint = fixture.token_b_mint.info();
+    let token_a_vault = fixture.token_a_vault.info();
+    let token_b_vault = fixture.token_b_vault.info();
+
+    assert_eq!(
+        PoolAccount::check(&foreign_pool.info(), &token_a_mint, &token_b_mint),
+        Err(ProgramError::InvalidAccountOwner)
+    );
+    assert_eq!(
+        PoolAccount::check(&pool, &token_b_mint, &token_a_mint),
+        Err(ProgramError::from(PinocchioError::InvalidTokenMint))
+    );
+    assert_eq!(
+        PoolAccount::check(&pool, &token_a_mint, &other_mint.info()),
+        Err(ProgramError::from(PinocchioError::InvalidTokenMint))
+    );
+    assert_eq!(
+        PoolAccount::check_lp_mint(&pool, &other_mint.info()),
+        Err(ProgramError::InvalidAccountData)
+    );
+
+    assert_eq!(PoolAccount::check_vaults(&pool, &token_a_vault, &token_b_vault), Ok(()));
+    assert_eq!(
+        PoolAccount::check_vaults(&pool, &token_b_vault, &token_a_vault),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(
+        PoolAccount::check_vaults(&pool, &token_a_vault, &other_vault.info()),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn test_initialize_pool_requires_canonical_mint_order() {
+    use pinocchio_amm::instructions::InitializePoolAccounts;
+
+    let (mint_low, mint_high) = sorted_mints();
+    let mut authority = TestAccount::new(PAYER, solana_sdk::system_program::ID, true, &[]);
+    let mut low = TestAccount::new(mint_low, spl_token::ID, false, &mint_data(&PAYER, 0));
+    let mut high = TestAccount::new(mint_high, spl_token::ID, false, &mint_data(&PAYER, 0));
+    let mut other = TestAccount::new(Pubkey::new_unique(), PROGRAM, false, &[]);
+    let mut token_program = TestAccount::new(spl_token::ID, solana_sdk::bpf_loader::ID, false, &[]);
+
+    let (authority, low, high, other, token_program) = (authority.info(), low.info(), high.info(), other.info(), token_program.info());
+    let accounts = |token_a_mint: &AccountInfo, token_b_mint: &AccountInfo| [
+        authority.clone(), other.clone(), token_a_mint.clone(), token_b_mint.clone(), other.clone(),
+        other.clone(), other.clone(), token_program.clone(), token_program.clone(), other.clone(),
+        other.clone(), other.clone(), other.clone(),
+    ];
+
+    assert!(InitializePoolAccounts::try_from(&accounts(&low, &high)[..]).is_ok());
+    assert_eq!(
+        InitializePoolAccounts::try_from(&accounts(&high, &low)[..]).err(),
+        Some(ProgramError::from(PinocchioError::UnsortedMints))
+    );
+    assert_eq!(
+        InitializePoolAccounts::try_from(&accounts(&low, &low)[..]).err(),
+        Some(ProgramError::from(PinocchioError::IdenticalMints))
+    );
+}
+
+#[test]
+fn test_unordered_pair_maps_to_one_pool() {
+    let (mint_low, mint_high) = sorted_mints();
+    let (low, high) = (mint_low.to_bytes(), mint_high.to_bytes());
+
+    assert_eq!(Pool::sort_mints(&low, &high), (&low, &high, false));
+    assert_eq!(Pool::sort_mints(&high, &low), (&low, &high, true));
+
+    // Clients derive the same address whichever order they hold the pair in.
+    let derive = |mint_x: &[u8; 32], mint_y: &[u8; 32]| {
+        let (token_a_mint, token_b_mint, _) = Pool::sort_mints(mint_x, mint_y);
+        Pubkey::find_program_address(&[b"pool", token_a_mint, token_b_mint], &PROGRAM)
+    };
+    assert_eq!(derive(&low, &high), derive(&high, &low));
+
+    let mut pool_account = pool_account(&Pubkey::new_unique(), 30);
+    let pool_key = Pubkey::new_from_array(*pool_account.info().key());
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    assert!(pool.token_a_mint < pool.token_b_mint);
+    assert_eq!(derive(&pool.token_b_mint, &pool.token_a_mint), (pool_key, pool.bump));
+
+    assert_eq!(pool.swap_direction(&pool.token_a_mint.clone()), Some(true));
+    assert_eq!(pool.swap_direction(&pool.token_b_mint.clone()), Some(false));
+    assert_eq!(pool.swap_direction(&Pubkey::new_unique().to_bytes()), None);
+}
+
+#[test]
+fn test_exact_out_input_is_minimal_and_rounds_for_the_pool() {
+    use pinocchio_amm::math::{get_amount_in, get_amount_out};
+
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..5_000 {
+        let reserve_in = next() % 1_000_000_000_000 + 1;
+        let reserve_out = next() % 1_000_000_000_000 + 2;
+        let amount_out = next() % (reserve_out - 1) + 1;
+        let fee_rate = (next() % 1_000) as u16;
+
+        let amount_in = match get_amount_in(amount_out, reserve_in, reserve_out, fee_rate) {
+            Ok(amount_in) => amount_in,
+            Err(error) => {
+                assert_eq!(error, PinocchioError::MathOverflow);
+                continue;
+            }
+        };
+
+        // Paying the quoted input always covers the requested output...
+        let (received, _) = get_amount_out(amount_in, reserve_in, reserve_out, fee_rate).expect("Should quote");
+        assert!(received >= amount_out, "{amount_in} in gave {received} < {amount_out}");
+
+        // ...and one unit less never does.
+        let (received, _) = get_amount_out(amount_in - 1, reserve_in, reserve_out, fee_rate).expect("Should quote");
+        assert!(received < amount_out, "{} in already gave {received} >= {amount_out}", amount_in - 1);
+    }
+
+    assert_eq!(get_amount_in(1_000, 1_000_000, 1_000, 30), Err(PinocchioError::InsufficientLiquidity));
+    assert_eq!(get_amount_in(1, 1_000_000, 1_000, 10_000), Err(PinocchioError::InvalidFeeRate));
+}
+
+#[test]
+fn test_swap_exact_out() {
+    use pinocchio_amm::instructions::SwapExactOutData;
+    use pinocchio_amm::math::get_amount_in;
+
+    let mut data = [0u8; 17];
+    data[0..8].copy_from_slice(&500u64.to_le_bytes());
+    data[8..16].copy_from_slice(&1_000u64.to_le_bytes());
+    data[16] = 1;
+    let parsed = SwapExactOutData::try_from(&data[..]).expect("Should parse exact-out data");
+    assert_eq!((parsed.amount_out, parsed.max_amount_in, parsed.swap_direction), (500, 1_000, true));
+    assert!(SwapExactOutData::try_from(&data[..16]).is_err());
+    data[0..8].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(
+        SwapExactOutData::try_from(&data[..]).err(),
+        Some(ProgramError::from(PinocchioError::InvalidAmount))
+    );
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+    fixture.pool_mut().protocol_fee_share = 5_000;
+    let amount_in = get_amount_in(10_000, 2_000_000, 1_000_000, 30).expect("Should quote");
+
+    assert_eq!(
+        fixture.swap_exact_out(SwapExactOutData { amount_out: 10_000, max_amount_in: amount_in - 1, swap_direction: false, deadline: None }),
+        Err(ProgramError::from(PinocchioError::SlippageExceeded))
+    );
+    assert_eq!(fixture.pool_mut().protocol_fees_b, 0);
+
+    fixture
+        .swap_exact_out(SwapExactOutData { amount_out: 10_000, max_amount_in: amount_in, swap_direction: false, deadline: None })
+        .expect("Swap should succeed at the quoted input");
+    // 0.3% of the input is fee, half of which goes to the protocol.
+    assert_eq!(fixture.pool_mut().protocol_fees_b, (amount_in - amount_in * 9_970 / 10_000) / 2);
+    assert_eq!(fixture.pool_mut().protocol_fees_a, 0);
+
+    assert_eq!(
+        fixture.swap_exact_out(SwapExactOutData { amount_out: 2_000_000, max_amount_in: u64::MAX, swap_direction: true, deadline: None }),
+        Err(ProgramError::from(PinocchioError::InsufficientLiquidity))
+    );
+}
+
+#[test]
+fn test_vaults_must_hold_their_sides_mint() {
+    use pinocchio_amm::instructions::{RemoveLiquidityData, SwapData, SwapExactOutData};
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+    let pool_key = Pubkey::new_from_array(*fixture.pool.info().key());
+    let token_a_mint = Pubkey::new_from_array(*fixture.token_a_mint.info().key());
+    fixture.token_b_vault.data_mut()[..spl_token::state::Account::LEN].copy_from_slice(&token_account_data(&token_a_mint, &pool_key, 2_000_000));
+
+    let invalid = Err(ProgramError::InvalidAccountData);
+    assert_eq!(fixture.swap(SwapData { amount_in: 10_000, min_amount_out: 0, swap_direction: true, deadline: None }), invalid);
+    assert_eq!(
+        fixture.swap_exact_out(SwapExactOutData { amount_out: 10_000, max_amount_in: u64::MAX, swap_direction: true, deadline: None }),
+        invalid
+    );
+    assert_eq!(
+        fixture.remove_liquidity(RemoveLiquidityData { lp_tokens: 1_000, min_amount_a: 0, min_amount_b: 0, deadline: None }),
+        invalid
+    );
+    assert_eq!(fixture.quote_swap(10_000, true).err(), invalid.err());
+}
+
+/// Per-hop accounts of a fixture, in `RouteSwap` order.
+fn route_hop_infos(fixture: &mut PoolFixture) -> [AccountInfo; 9] {
+    [
+        fixture.pool.info(),
+        fixture.token_a_vault.info(),
+        fixture.token_b_vault.info(),
+        fixture.user_token_a.info(),
+        fixture.user_token_b.info(),
+        fixture.token_a_mint.info(),
+        fixture.token_b_mint.info(),
+        fixture.token_program.info(),
+        fixture.token_program.info(),
+    ]
+}
+
+fn route_hop<'a>(user: &'a AccountInfo, hop: &'a [AccountInfo; 9]) -> Option<pinocchio_amm::instructions::SwapAccounts<'a>> {
+    Some(pinocchio_amm::instructions::SwapAccounts {
+        user,
+        pool: &hop[0],
+        token_a_vault: &hop[1],
+        token_b_vault: &hop[2],
+        user_token_a: &hop[3],
+        user_token_b: &hop[4],
+        token_a_mint: &hop[5],
+        token_b_mint: &hop[6],
+        token_a_program: &hop[7],
+        token_b_program: &hop[8],
+        observations: None,
+    })
+}
+
+#[test]
+fn test_route_swap_data_parsing() {
+    use pinocchio_amm::instructions::{RouteSwap, RouteSwapData};
+
+    let mut data = vec![0u8; 16];
+    data[0..8].copy_from_slice(&10_000u64.to_le_bytes());
+    data[8..16].copy_from_slice(&9_000u64.to_le_bytes());
+    assert!(RouteSwapData::try_from(&data[..]).is_err());
+
+    data.extend_from_slice(&[3, 1, 0, 1]);
+    let parsed = RouteSwapData::try_from(&data[..]).expect("Should parse route data");
+    assert_eq!((parsed.amount_in, parsed.min_amount_out, parsed.hop_count), (10_000, 9_000, 3));
+    assert_eq!(parsed.swap_directions, [true, false, true, false]);
+
+    // The hop count must be in range and match the direction bytes that follow.
+    let with_hops = |hops: &[u8]| [&data[..16], hops].concat();
+    for hops in [&[0][..], &[RouteSwap::MAX_HOPS as u8 + 1, 1, 1, 1, 1, 1], &[3, 1, 0], &[2, 1, 0, 1]] {
+        assert_eq!(RouteSwapData::try_from(&with_hops(hops)[..]).err(), Some(ProgramError::InvalidInstructionData));
+    }
+}
+
+#[test]
+fn test_route_swap_chains_hops_with_one_final_check() {
+    use pinocchio_amm::instructions::{RouteSwap, RouteSwapAccounts, RouteSwapData};
+    use pinocchio_amm::math::get_amount_out;
+
+    let (mint_a, mint_b) = sorted_mints();
+    let mint_c = core::iter::repeat_with(Pubkey::new_unique).find(|mint| *mint > mint_b).expect("Should find a mint");
+    let mut first = PoolFixture::with_mints(&mint_a, &mint_b, 1_000_000, 2_000_000, 1_000_000);
+    let mut second = PoolFixture::with_mints(&mint_b, &mint_c, 4_000_000, 1_000_000, 1_000_000);
+
+    let user = first.user.info();
+    let first_hop = route_hop_infos(&mut first);
+    let second_hop = route_hop_infos(&mut second);
+    let accounts = || RouteSwapAccounts {
+        user: &user,
+        hops: [route_hop(&user, &first_hop), route_hop(&user, &second_hop), None, None],
+    };
+    let data = |min_amount_out: u64, swap_directions: [bool; RouteSwap::MAX_HOPS], hop_count: usize| RouteSwapData {
+        amount_in: 10_000,
+        min_amount_out,
+        hop_count,
+        swap_directions,
+        deadline: None,
+    };
+
+    // A -> B through the first pool, then B -> C through the second.
+    let (b_out, _) = get_amount_out(10_000, 1_000_000, 2_000_000, 30).expect("Should quote");
+    let (c_out, _) = get_amount_out(b_out, 4_000_000, 1_000_000, 30).expect("Should quote");
+    let route = [true, true, false, false];
+
+    assert_eq!(accounts().check_route(&data(c_out, route, 2)), Ok(()));
+    assert_eq!(
+        accounts().check_route(&data(c_out, route, 1)),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    // Paying C into the second pool doesn't continue from the first hop's B output.
+    assert_eq!(
+        accounts().check_route(&data(c_out, [true, false, false, false], 2)),
+        Err(ProgramError::from(PinocchioError::InvalidTokenMint))
+    );
+
+    assert_eq!(
+        RouteSwap { accounts: accounts(), data: data(c_out + 1, route, 2) }.process_at(&test_clock()),
+        Err(ProgramError::from(PinocchioError::SlippageExceeded))
+    );
+    RouteSwap { accounts: accounts(), data: data(c_out, route, 2) }
+        .process_at(&test_clock())
+        .expect("Route should deliver the chained output");
+}
+
+#[test]
+fn test_deadline_parsing_and_expiry() {
+    use pinocchio::sysvars::clock::Clock;
+    use pinocchio_amm::Deadline;
+
+    assert_eq!(Deadline::parse(&[]), Ok(None));
+    for deadline in [Deadline::UnixTimestamp(1_700_000_000), Deadline::Slot(250_000_000)] {
+        assert_eq!(Deadline::parse(&deadline.to_bytes()), Ok(Some(deadline)));
+    }
+
+    let mut bytes = Deadline::Slot(1).to_bytes();
+    bytes[0] = 2;
+    assert_eq!(Deadline::parse(&bytes), Err(ProgramError::InvalidInstructionData));
+    assert_eq!(Deadline::parse(&bytes[..8]), Err(ProgramError::InvalidInstructionData));
+
+    let clock = Clock { slot: 100, unix_timestamp: 1_000, ..Default::default() };
+    assert_eq!(Deadline::UnixTimestamp(1_000).check(&clock), Ok(()));
+    assert_eq!(Deadline::UnixTimestamp(999).check(&clock), Err(PinocchioError::DeadlineExceeded));
+    assert_eq!(Deadline::Slot(100).check(&clock), Ok(()));
+    assert_eq!(Deadline::Slot(99).check(&clock), Err(PinocchioError::DeadlineExceeded));
+}
+
+#[test]
+fn test_deadline_enforcement() {
+    use pinocchio_amm::instructions::SwapData;
+    use pinocchio_amm::Deadline;
+
+    let clock = Clock { slot: 100, unix_timestamp: 1_000, ..Default::default() };
+
+    for deadline in [Deadline::UnixTimestamp(1_000), Deadline::UnixTimestamp(5_000), Deadline::Slot(100), Deadline::Slot(500)] {
+        assert_eq!(Deadline::enforce(Some(deadline), &clock), Ok(()));
+    }
+    for deadline in [Deadline::UnixTimestamp(999), Deadline::UnixTimestamp(i64::MIN), Deadline::Slot(99), Deadline::Slot(0)] {
+        assert_eq!(Deadline::enforce(Some(deadline), &clock), Err(PinocchioError::DeadlineExceeded.into()));
+    }
+    assert_eq!(Deadline::enforce(None, &clock), Ok(()));
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+    let swap = |deadline| SwapData { amount_in: 10_000, min_amount_out: 1, swap_direction: true, deadline: Some(deadline) };
+    let now = fixture.clock.unix_timestamp;
+    assert_eq!(fixture.swap(swap(Deadline::UnixTimestamp(now - 1))), Err(PinocchioError::DeadlineExceeded.into()));
+    fixture.swap(swap(Deadline::UnixTimestamp(now))).expect("Should swap before the deadline");
+}
+
+#[test]
+fn test_deadline_is_an_optional_trailing_field() {
+    use pinocchio_amm::instructions::{AddLiquidityData, RemoveLiquidityData, RouteSwapData, SwapData, SwapExactOutData};
+    use pinocchio_amm::Deadline;
+
+    let deadline = Deadline::UnixTimestamp(1_700_000_000);
+    let with_deadline = |data: &[u8]| [data, &deadline.to_bytes()[..]].concat();
+
+    let mut swap = vec![0u8; 17];
+    swap[0..8].copy_from_slice(&1_000u64.to_le_bytes());
+    swap[16] = 1;
+    assert_eq!(SwapData::try_from(&swap[..]).map(|data| data.deadline), Ok(None));
+    assert_eq!(SwapData::try_from(&with_deadline(&swap)[..]).map(|data| data.deadline), Ok(Some(deadline)));
+    assert_eq!(SwapExactOutData::try_from(&swap[..]).map(|data| data.deadline), Ok(None));
+    assert_eq!(SwapExactOutData::try_from(&with_deadline(&swap)[..]).map(|data| data.deadline), Ok(Some(deadline)));
+    assert!(SwapData::try_from(&[&swap[..], &[0u8; 3]].concat()[..]).is_err());
+
+    let mut liquidity = vec![0u8; 24];
+    liquidity[0..8].copy_from_slice(&1_000u64.to_le_bytes());
+    liquidity[8..16].copy_from_slice(&1_000u64.to_le_bytes());
+    assert_eq!(AddLiquidityData::try_from(&liquidity[..]).map(|data| data.deadline), Ok(None));
+    assert_eq!(AddLiquidityData::try_from(&with_deadline(&liquidity)[..]).map(|data| data.deadline), Ok(Some(deadline)));
+    assert_eq!(RemoveLiquidityData::try_from(&liquidity[..]).map(|data| data.deadline), Ok(None));
+    assert_eq!(RemoveLiquidityData::try_from(&with_deadline(&liquidity)[..]).map(|data| data.deadline), Ok(Some(deadline)));
+
+    let mut route = vec![0u8; 16];
+    route[0..8].copy_from_slice(&1_000u64.to_le_bytes());
+    route.extend_from_slice(&[2, 1, 0]);
+    let parsed = RouteSwapData::try_from(&route[..]).expect("Should parse route data");
+    assert_eq!((parsed.hop_count, parsed.deadline), (2, None));
+    let parsed = RouteSwapData::try_from(&with_deadline(&route)[..]).expect("Should parse route data");
+    assert_eq!((parsed.hop_count, parsed.swap_directions, parsed.deadline), (2, [true, false, false, false], Some(deadline)));
+    assert!(RouteSwapData::try_from(&with_deadline(&route[..16])[..]).is_err());
+
+    // The hop count, not the data length, says where the deadline starts.
+    let one_hop = [&route[..16], &[1, 1]].concat();
+    let parsed = RouteSwapData::try_from(&with_deadline(&one_hop)[..]).expect("Should parse route data");
+    assert_eq!((parsed.hop_count, parsed.deadline), (1, Some(deadline)));
+    let three_hops = [&route[..16], &[3, 1, 0, 1]].concat();
+    assert!(RouteSwapData::try_from(&with_deadline(&three_hops)[..]).is_ok());
+    assert!(RouteSwapData::try_from(&[&three_hops[..], &[0]].concat()[..]).is_err());
+}
+
+#[test]
+fn test_optimal_swap_amount_balances_the_deposit() {
+    use pinocchio_amm::math::{get_amount_out, optimal_swap_amount};
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..2_000 {
+        let reserve_in = next() % 1_000_000_000_000 + 1_000;
+        let reserve_out = next() % 1_000_000_000_000 + 1_000;
+        let amount_in = next() % (reserve_in * 4) + 1;
+        let fee_rate = (next() % 1_000) as u16;
+
+        let swap_amount = optimal_swap_amount(amount_in, reserve_in, fee_rate).expect("Should solve");
+        assert!(swap_amount <= amount_in);
+
+        // After swapping, the leftover input and the swap output sit in the
+        // pool's new ratio: remaining / reserve_in' == output / reserve_out'.
+        let (output, _) = get_amount_out(swap_amount, reserve_in, reserve_out, fee_rate).expect("Should quote");
+        let remaining = (amount_in - swap_amount) as f64;
+        let ratio_in = remaining / (reserve_in + swap_amount) as f64;
+        let ratio_out = output as f64 / (reserve_out - output) as f64;
+        if output > 1_000 {
+            assert!((ratio_in - ratio_out).abs() <= ratio_out * 1e-3 + 1e-9, "{amount_in} into {reserve_in}: {ratio_in} vs {ratio_out}");
+        }
+    }
+
+    // Values past the u128 headroom are bisected rather than overflowing.
+    let swap_amount = optimal_swap_amount(u64::MAX, u64::MAX, 30).expect("Should solve");
+    assert!(swap_amount > u64::MAX / 3 && swap_amount < u64::MAX / 2);
+    assert_eq!(optimal_swap_amount(1_000, 0, 30), Err(PinocchioError::InsufficientLiquidity));
+}
+
+#[test]
+fn test_optimal_swap_amount_of_huge_deposit_into_small_reserve() {
+    use pinocchio_amm::math::optimal_swap_amount;
+
+    // A small reserve must not round away: the result is the closed-form root,
+    // checked here in floating point, however far the inputs are apart.
+    for (amount_in, reserve_in) in [
+        (1u64 << 62, 1_000u64),
+        (u64::MAX, 1),
+        (1 << 60, 1 << 30),
+        (u64::MAX / 3, 123_456_789),
+        ((1 << 53) - 1, (1 << 44) - 1),
+        (1 << 53, 1 << 44),
+        (1_000, u64::MAX),
+    ] {
+        let swap_amount = optimal_swap_amount(amount_in, reserve_in, 30).expect("Should solve");
+
+        let (amount, reserve, g) = (amount_in as f64, reserve_in as f64, 9_970f64);
+        let linear = (10_000.0 + g) * reserve;
+        // Rationalised so that small roots don't cancel away.
+        let root = 2.0 * 10_000.0 * amount * reserve / ((linear * linear + 4.0 * g * 10_000.0 * amount * reserve).sqrt() + linear);
+        assert!((swap_amount as f64 - root).abs() <= root * 1e-9 + 2.0, "{amount_in} into {reserve_in}: {swap_amount} vs {root}");
+        assert!(swap_amount < amount_in);
+    }
+}
+
+#[test]
+fn test_deposit_single() {
+    use pinocchio_amm::instructions::DepositSingleData;
+    use pinocchio_amm::math::{amount_for_lp, get_amount_out, lp_for_amount, optimal_swap_amount};
+
+    let mut data = [0u8; 17];
+    data[0..8].copy_from_slice(&10_000u64.to_le_bytes());
+    data[8..16].copy_from_slice(&1u64.to_le_bytes());
+    let parsed = DepositSingleData::try_from(&data[..]).expect("Should parse deposit data");
+    assert_eq!((parsed.amount_in, parsed.min_lp_amount, parsed.deposit_a, parsed.deadline), (10_000, 1, false, None));
+    assert!(DepositSingleData::try_from(&data[..16]).is_err());
+    data[0..8].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(
+        DepositSingleData::try_from(&data[..]).err(),
+        Some(ProgramError::from(PinocchioError::InvalidAmount))
+    );
+
+    let (reserve_a, reserve_b, lp_supply) = (1_000_000u64, 4_000_000u64, 2_000_000u64);
+    let mut fixture = PoolFixture::new(reserve_a, reserve_b, lp_supply);
+    let amount_in = 100_000;
+
+    let swap_amount = optimal_swap_amount(amount_in, reserve_a, 30).expect("Should solve");
+    let (output, _) = get_amount_out(swap_amount, reserve_a, reserve_b, 30).expect("Should quote");
+    let expected_lp = lp_for_amount(amount_in - swap_amount, reserve_a + swap_amount, lp_supply)
+        .expect("Should price")
+        .min(lp_for_amount(output, reserve_b - output, lp_supply).expect("Should price"));
+    // A tenth of reserve A alone is worth about half of a tenth of the pool,
+    // less the fee and price impact of the internal swap.
+    assert!(expected_lp > 90_000 && expected_lp < 100_000, "{expected_lp}");
+    assert!(amount_for_lp(expected_lp, reserve_a + swap_amount, lp_supply).unwrap() <= amount_in - swap_amount);
+
+    assert_eq!(
+        fixture.deposit_single(DepositSingleData { amount_in, min_lp_amount: expected_lp + 1, deposit_a: true, deadline: None }),
+        Err(ProgramError::from(PinocchioError::SlippageExceeded))
+    );
+    fixture
+        .deposit_single(DepositSingleData { amount_in, min_lp_amount: expected_lp, deposit_a: true, deadline: None })
+        .expect("Deposit should succeed at the quoted LP amount");
+
+    fixture.pool_mut().status = Pool::SWAP_DISABLED;
+    assert_eq!(
+        fixture.deposit_single(DepositSingleData { amount_in, min_lp_amount: 0, deposit_a: false, deadline: None }),
+        Err(ProgramError::from(PinocchioError::SwapDisabled))
+    );
+    fixture.pool_mut().status = Pool::ADD_LIQUIDITY_DISABLED;
+    assert_eq!(
+        fixture.deposit_single(DepositSingleData { amount_in, min_lp_amount: 0, deposit_a: false, deadline: None }),
+        Err(ProgramError::from(PinocchioError::AddLiquidityDisabled))
+    );
+
+    let mut empty = PoolFixture::new(0, 0, 0);
+    assert_eq!(
+        empty.deposit_single(DepositSingleData { amount_in, min_lp_amount: 0, deposit_a: true, deadline: None }),
+        Err(ProgramError::from(PinocchioError::InsufficientLiquidity))
+    );
+}
+
+#[test]
+fn test_withdraw_single() {
+    use pinocchio_amm::instructions::WithdrawSingleData;
+    use pinocchio_amm::math::{get_amount_out, reserve_share};
+
+    let mut data = [0u8; 17];
+    data[0..8].copy_from_slice(&10_000u64.to_le_bytes());
+    data[8..16].copy_from_slice(&1u64.to_le_bytes());
+    data[16] = 1;
+    let parsed = WithdrawSingleData::try_from(&data[..]).expect("Should parse withdraw data");
+    assert_eq!((parsed.lp_tokens, parsed.min_amount_out, parsed.withdraw_a, parsed.deadline), (10_000, 1, true, None));
+    assert!(WithdrawSingleData::try_from(&data[..16]).is_err());
+    data[0..8].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(
+        WithdrawSingleData::try_from(&data[..]).err(),
+        Some(ProgramError::from(PinocchioError::InvalidAmount))
+    );
+
+    let (reserve_a, reserve_b, lp_supply) = (1_000_000u64, 4_000_000u64, 2_000_000u64);
+    let mut fixture = PoolFixture::new(reserve_a, reserve_b, lp_supply);
+    fixture.pool_mut().protocol_fee_share = 5_000;
+    let lp_tokens = 200_000;
+
+    // A tenth of the pool: 100_000 A kept, 400_000 B sold into the remaining reserves.
+    let amount_a = reserve_share(lp_tokens, reserve_a, lp_supply).unwrap();
+    let amount_b = reserve_share(lp_tokens, reserve_b, lp_supply).unwrap();
+    let (swap_output, fee) = get_amount_out(amount_b, reserve_b - amount_b, reserve_a - amount_a, 30).unwrap();
+    let expected = amount_a + swap_output;
+    assert_eq!((amount_a, amount_b), (100_000, 400_000));
+    // Less than the 200_000 A the share is worth at spot, because of fee and price impact.
+    assert!(expected > 180_000 && expected < 200_000, "{expected}");
+
+    assert_eq!(
+        fixture.withdraw_single(WithdrawSingleData { lp_tokens, min_amount_out: expected + 1, withdraw_a: true, deadline: None }),
+        Err(ProgramError::from(PinocchioError::SlippageExceeded))
+    );
+    assert_eq!(fixture.pool_mut().protocol_fees_b, 0);
+
+    fixture
+        .withdraw_single(WithdrawSingleData { lp_tokens, min_amount_out: expected, withdraw_a: true, deadline: None })
+        .expect("Withdrawal should succeed at the quoted amount");
+    // The internal swap pays B in, so its protocol fee accrues on the B side.
+    assert_eq!(fixture.pool_mut().protocol_fees_b, fee / 2);
+    assert_eq!(fixture.pool_mut().protocol_fees_a, 0);
+
+    assert_eq!(
+        fixture.withdraw_single(WithdrawSingleData { lp_tokens: lp_supply, min_amount_out: 0, withdraw_a: false, deadline: None }),
+        Err(ProgramError::InvalidArgument)
+    );
+
+    fixture.pool_mut().status = Pool::SWAP_DISABLED;
+    assert_eq!(
+        fixture.withdraw_single(WithdrawSingleData { lp_tokens, min_amount_out: 0, withdraw_a: false, deadline: None }),
+        Err(ProgramError::from(PinocchioError::SwapDisabled))
+    );
+    fixture.pool_mut().status = Pool::REMOVE_LIQUIDITY_DISABLED;
+    assert_eq!(
+        fixture.withdraw_single(WithdrawSingleData { lp_tokens, min_amount_out: 0, withdraw_a: false, deadline: None }),
+        Err(ProgramError::from(PinocchioError::RemoveLiquidityDisabled))
+    );
+}
+
+#[test]
+fn test_proportional_deposit_only_charges_matched_amounts() {
+    use pinocchio_amm::math::proportional_deposit;
+
+    // 1:2 pool; the depositor offers ten times more B than the ratio needs.
+    let (lp, amount_a, amount_b) = proportional_deposit(100_000, 2_000_000, 1_000_000, 2_000_000, 1_000_000).unwrap();
+    assert_eq!((lp, amount_a, amount_b), (100_000, 100_000, 200_000));
+
+    // Skewed the other way, A is the surplus side.
+    let (lp, amount_a, amount_b) = proportional_deposit(5_000_000, 20_000, 1_000_000, 2_000_000, 1_000_000).unwrap();
+    assert_eq!((lp, amount_a, amount_b), (10_000, 10_000, 20_000));
+
+    let mut state = 0xda94_2042_e4dd_58b5u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..5_000 {
+        let reserve_a = next() % 1_000_000_000_000 + 1;
+        let reserve_b = next() % 1_000_000_000_000 + 1;
+        let lp_supply = next() % 1_000_000_000_000 + 1;
+        let max_amount_a = next() % 1_000_000_000_000 + 1;
+        let max_amount_b = next() % 1_000_000_000_000 + 1;
+
+        let (lp, amount_a, amount_b) = proportional_deposit(max_amount_a, max_amount_b, reserve_a, reserve_b, lp_supply).unwrap();
+        assert!(amount_a <= max_amount_a && amount_b <= max_amount_b);
+
+        // Each side pays at least its share of the minted LP and less than one more LP's worth.
+        let (lp, supply) = (lp as u128, lp_supply as u128);
+        assert!(amount_a as u128 * supply >= lp * reserve_a as u128);
+        assert!(amount_b as u128 * supply >= lp * reserve_b as u128);
+        assert!((amount_a as u128).saturating_sub(1) * supply < (lp + 1) * reserve_a as u128);
+        assert!((amount_b as u128).saturating_sub(1) * supply < (lp + 1) * reserve_b as u128);
+    }
+
+    assert_eq!(proportional_deposit(1, 1, 0, 1, 1), Err(PinocchioError::InvalidPoolState));
+}
+
+#[test]
+fn test_add_liquidity_with_skewed_maximums() {
+    use pinocchio_amm::instructions::AddLiquidityData;
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+
+    // LP follows the scarcer side, so a lopsided maximum doesn't raise it...
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: 100_000, max_amount_b: 10_000_000, min_lp_amount: 100_001, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture
+        .add_liquidity(AddLiquidityData { max_amount_a: 100_000, max_amount_b: 10_000_000, min_lp_amount: 100_000, deadline: None })
+        .expect("Skewed deposit should mint for the matched amounts");
+
+    // ...and the B side can't be short of what the ratio needs.
+    let result = fixture.add_liquidity(AddLiquidityData { max_amount_a: 100_000, max_amount_b: 199_999, min_lp_amount: 100_000, deadline: None });
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+}
+
+#[test]
+fn test_token_2022_accounts_pass_layout_checks() {
+    use pinocchio_amm::{AccountCheck, instructions::TokenAccount};
+
+    let owner = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let mut legacy_mint = TestAccount::new(mint, spl_token::ID, false, &mint_data(&PAYER, 0));
+    let mut plain_2022_mint = TestAccount::new(mint, TOKEN_2022, false, &mint_data(&PAYER, 0));
+    let mut extended_mint = TestAccount::new(mint, TOKEN_2022, false, &with_extensions(mint_data(&PAYER, 0), 1));
+    let mut extended_account = TestAccount::new(Pubkey::new_unique(), TOKEN_2022, false, &with_extensions(token_account_data(&mint, &owner, 5), 2));
+    let mut plain_2022_account = TestAccount::new(Pubkey::new_unique(), TOKEN_2022, false, &token_account_data(&mint, &owner, 5));
+
+    for account in [&mut legacy_mint, &mut plain_2022_mint, &mut extended_mint] {
+        assert_eq!(<&AccountInfo as pinocchio_amm::MintInterface>::check(&account.info()), Ok(()));
+    }
+    for account in [&mut extended_account, &mut plain_2022_account] {
+        assert_eq!(TokenAccount::check(&account.info()), Ok(()));
+    }
+
+    // The account type byte has to match: a token account isn't a mint and vice versa.
+    assert_eq!(
+        <&AccountInfo as pinocchio_amm::MintInterface>::check(&extended_account.info()),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(TokenAccount::check(&extended_mint.info()), Err(ProgramError::InvalidAccountData));
+
+    // Extension bytes are only valid under Token-2022.
+    let mut legacy_with_extensions = TestAccount::new(mint, spl_token::ID, false, &with_extensions(mint_data(&PAYER, 0), 1));
+    assert_eq!(
+        <&AccountInfo as pinocchio_amm::MintInterface>::check(&legacy_with_extensions.info()),
+        Err(ProgramError::InvalidAccountData)
+    );
+
+    let mut foreign_mint = TestAccount::new(mint, PROGRAM, false, &mint_data(&PAYER, 0));
+    assert_eq!(
+        <&AccountInfo as pinocchio_amm::MintInterface>::check(&foreign_mint.info()),
+        Err(ProgramError::InvalidAccountOwner)
+    );
+    let mut foreign_account = TestAccount::new(Pubkey::new_unique(), PROGRAM, false, &token_account_data(&mint, &owner, 5));
+    assert_eq!(TokenAccount::check(&foreign_account.info()), Err(ProgramError::IllegalOwner));
+}
+
+#[test]
+fn test_user_token_accounts_must_belong_to_the_user() {
+    use pinocchio_amm::{AssociatedTokenAccount, AssociatedTokenAccountCheck};
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+    let (user, user_token_a) = (fixture.user.info(), fixture.user_token_a.info());
+    let (token_a_mint, token_b_mint) = (fixture.token_a_mint.info(), fixture.token_b_mint.info());
+    let mut stranger = TestAccount::new(Pubkey::new_unique(), solana_sdk::system_program::ID, true, &[]);
+
+    // The user's accounts aren't at their associated addresses; only the fields matter.
+    assert_eq!(AssociatedTokenAccount::check(&user_token_a, &user, &token_a_mint), Ok(()));
+    assert_eq!(
+        AssociatedTokenAccount::check(&user_token_a, &user, &token_b_mint),
+        Err(ProgramError::InvalidAccountData)
+    );
+    assert_eq!(
+        AssociatedTokenAccount::check(&user_token_a, &stranger.info(), &token_a_mint),
+        Err(ProgramError::InvalidAccountData)
+    );
+}
+
+#[test]
+fn test_token_program_must_own_its_side_mint() {
+    use pinocchio_amm::{TokenProgram, TokenProgramCheck};
+    use pinocchio_amm::token_interface::mint_decimals;
+
+    let mut legacy_mint = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &mint_data(&PAYER, 0));
+    let mut extended_mint = TestAccount::new(Pubkey::new_unique(), TOKEN_2022, false, &with_extensions(mint_data(&PAYER, 0), 1));
+    let mut legacy_program = TestAccount::new(spl_token::ID, solana_sdk::bpf_loader::ID, false, &[]);
+    let mut token_2022_program = TestAccount::new(TOKEN_2022, solana_sdk::bpf_loader::ID, false, &[]);
+    let mut other_program = TestAccount::new(PROGRAM, solana_sdk::bpf_loader::ID, false, &[]);
+
+    assert_eq!(TokenProgram::check(&legacy_program.info(), &legacy_mint.info()), Ok(()));
+    assert_eq!(TokenProgram::check(&token_2022_program.info(), &extended_mint.info()), Ok(()));
+    assert_eq!(
+        TokenProgram::check(&legacy_program.info(), &extended_mint.info()),
+        Err(ProgramError::IncorrectProgramId)
+    );
+    assert_eq!(
+        TokenProgram::check(&token_2022_program.info(), &legacy_mint.info()),
+        Err(ProgramError::IncorrectProgramId)
+    );
+
+    // A program that merely owns the mint account isn't a token program.
+    let mut spoofed_mint = TestAccount::new(Pubkey::new_unique(), PROGRAM, false, &mint_data(&PAYER, 0));
+    assert_eq!(
+        TokenProgram::check(&other_program.info(), &spoofed_mint.info()),
+        Err(ProgramError::IncorrectProgramId)
+    );
+
+    assert_eq!(mint_decimals(&extended_mint.info()), Ok(9));
+}
+
+/// Token-2022 mint data whose extensions are a close authority followed by a
+/// `TransferFeeConfig` with the given older and newer fees.
+fn transfer_fee_mint_data(older: (u64, u64, u16), newer: (u64, u64, u16)) -> Vec<u8> {
+    let mut data = mint_data(&PAYER, 0);
+    data.resize(spl_token::state::Account::LEN, 0);
+    data.push(1);
+
+    // MintCloseAuthority: type 3, 32 bytes.
+    data.extend_from_slice(&3u16.to_le_bytes());
+    data.extend_from_slice(&32u16.to_le_bytes());
+    data.extend_from_slice(&[7u8; 32]);
+
+    data.extend_from_slice(&1u16.to_le_bytes());
+    data.extend_from_slice(&108u16.to_le_bytes());
+    data.extend_from_slice(&[0u8; 32 + 32 + 8]);
+    for (epoch, maximum_fee, basis_points) in [older, newer] {
+        data.extend_from_slice(&epoch.to_le_bytes());
+        data.extend_from_slice(&maximum_fee.to_le_bytes());
+        data.extend_from_slice(&basis_points.to_le_bytes());
+    }
+    data
+}
+
+#[test]
+fn test_transfer_fee_config_parsing() {
+    use pinocchio_amm::token_interface::{active_transfer_fee, TransferFee, TransferFeeConfig};
+
+    let mut fee_mint = TestAccount::new(Pubkey::new_unique(), TOKEN_2022, false, &transfer_fee_mint_data((0, 1_000, 50), (200, 5_000, 100)));
+    let config = TransferFeeConfig::from_mint(&fee_mint.info())
+        .expect("Should read extensions")
+        .expect("Should find the transfer fee config");
+    assert_eq!(config.older, TransferFee { epoch: 0, maximum_fee: 1_000, transfer_fee_basis_points: 50 });
+    assert_eq!(config.newer, TransferFee { epoch: 200, maximum_fee: 5_000, transfer_fee_basis_points: 100 });
+    assert_eq!(config.epoch_fee(199), config.older);
+    assert_eq!(config.epoch_fee(200), config.newer);
+    for (epoch, fee) in [(199, config.older), (200, config.newer)] {
+        let clock = Clock { epoch, ..test_clock() };
+        assert_eq!(active_transfer_fee(&fee_mint.info(), &clock), Ok(fee));
+    }
+
+    // Mints without the extension charge nothing in any epoch.
+    let mut legacy_mint = TestAccount::new(Pubkey::new_unique(), spl_token::ID, false, &mint_data(&PAYER, 0));
+    let mut extended_mint = TestAccount::new(Pubkey::new_unique(), TOKEN_2022, false, &with_extensions(mint_data(&PAYER, 0), 1));
+    for mint in [&mut legacy_mint, &mut extended_mint] {
+        assert_eq!(TransferFeeConfig::from_mint(&mint.info()), Ok(None));
+        assert_eq!(active_transfer_fee(&mint.info(), &test_clock()), Ok(TransferFee::NONE));
+    }
+
+    let mut truncated = transfer_fee_mint_data((0, 1_000, 50), (0, 1_000, 50));
+    truncated.truncate(truncated.len() - 1);
+    let mut truncated_mint = TestAccount::new(Pubkey::new_unique(), TOKEN_2022, false, &truncated);
+    assert_eq!(TransferFeeConfig::from_mint(&truncated_mint.info()), Err(ProgramError::InvalidAccountData));
+}
+
+#[test]
+fn test_transfer_fee_net_and_gross_amounts() {
+    use pinocchio_amm::token_interface::TransferFee;
+
+    let fee = TransferFee { epoch: 0, maximum_fee: 5_000, transfer_fee_basis_points: 100 };
+    assert_eq!(fee.calculate_fee(10_000), Ok(100));
+    assert_eq!(fee.calculate_fee(10_001), Ok(101)); // rounds up
+    assert_eq!(fee.calculate_fee(10_000_000), Ok(5_000)); // capped
+    assert_eq!(fee.net_amount(10_000), Ok(9_900));
+    assert_eq!(fee.gross_amount(9_900), Ok(10_000));
+    assert_eq!(fee.gross_amount(10_000_000), Ok(10_005_000));
+    assert_eq!(TransferFee::NONE.gross_amount(1_234), Ok(1_234));
+
+    let mut state = 0x853c_49e6_748f_ea9bu64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..5_000 {
+        let fee = TransferFee {
+            epoch: 0,
+            maximum_fee: if next() % 2 == 0 { u64::MAX } else { next() % 1_000_000 },
+            transfer_fee_basis_points: (next() % 10_000) as u16,
+        };
+        let amount = next() % 1_000_000_000_000 + 1;
+
+        // Sending the gross-up always delivers the net amount...
+        let gross = fee.gross_amount(amount).expect("Should gross up");
+        assert!(fee.net_amount(gross).unwrap() >= amount, "{fee:?}: {gross} delivers less than {amount}");
+        // ...and is the smallest such amount when the fee isn't capped.
+        if fee.maximum_fee == u64::MAX {
+            assert!(fee.net_amount(gross - 1).unwrap() < amount, "{fee:?}: {} already delivers {amount}", gross - 1);
+        }
+
+        // Grossing up what a maximum nets never exceeds that maximum, which is
+        // what keeps ratio-matched deposits within `max_amount_a`/`max_amount_b`.
+        let net = fee.net_amount(amount).unwrap();
+        assert!(fee.gross_amount(net).unwrap() <= amount, "{fee:?}: gross of net({amount}) exceeds it");
+    }
+}
+
+#[test]
+fn test_price_accumulators_track_time_weighted_prices() {
+    use pinocchio_amm::math::{q64_price, twap};
+
+    let mut pool_account = pool_account(&Pubkey::new_unique(), 30);
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    let one = 1u128 << 64;
+
+    // An empty pool has no price: only the timestamp moves.
+    pool.update_price_accumulators(0, 0, 1_000).unwrap();
+    assert_eq!((pool.price_a_cumulative(), pool.price_b_cumulative()), (0, 0));
+    assert_eq!(pool.last_update_timestamp, 1_000);
+
+    // 1 A = 2 B for 10 seconds, then 1 A = 4 B for 30 seconds.
+    pool.update_price_accumulators(1_000, 2_000, 1_010).unwrap();
+    assert_eq!(pool.price_a_cumulative(), 20 * one);
+    assert_eq!(pool.price_b_cumulative(), 5 * one);
+    pool.update_price_accumulators(1_000, 4_000, 1_040).unwrap();
+    assert_eq!(pool.price_a_cumulative(), 140 * one);
+
+    // A second update in the same second, or a clock going backwards, changes nothing.
+    pool.update_price_accumulators(1, 1_000_000, 1_040).unwrap();
+    pool.update_price_accumulators(1, 1_000_000, 1_030).unwrap();
+    assert_eq!(pool.price_a_cumulative(), 140 * one);
+    assert_eq!(pool.last_update_timestamp, 1_040);
+
+    assert_eq!(twap(0, pool.price_a_cumulative(), 1_000, 1_040), Ok(35 * one / 10));
+    assert_eq!(twap(20 * one, 140 * one, 1_010, 1_040), Ok(q64_price(1_000, 4_000)));
+    assert_eq!(twap(0, one, 1_040, 1_040), Err(PinocchioError::InvalidAmount));
+
+    // Reading ahead uses the current reserves without writing to the pool.
+    assert_eq!(pool.cumulative_prices_at(1_000, 4_000, 1_050).unwrap().0, 180 * one);
+    assert_eq!(pool.price_a_cumulative(), 140 * one);
+}
+
+#[test]
+fn test_price_accumulators_wrap_and_twap_survives_it() {
+    use pinocchio_amm::math::twap;
+
+    let mut pool_account = pool_account(&Pubkey::new_unique(), 30);
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+
+    let start = u128::MAX - (1u128 << 64);
+    pool.price_a_cumulative = start.to_le_bytes();
+    pool.last_update_timestamp = 100;
+
+    // 1 A = 3 B for 2 seconds wraps the accumulator past zero.
+    pool.update_price_accumulators(1, 3, 102).unwrap();
+    assert!(pool.price_a_cumulative() < start);
+    assert_eq!(twap(start, pool.price_a_cumulative(), 100, 102), Ok(3u128 << 64));
+}
+
+/// An oracle account for `pool`, already initialized as `IncreaseObservationCardinality` would.
+pub fn observations_account(pool: &Pubkey) -> TestAccount {
+    let (observations_pda, bump) = Pubkey::find_program_address(&[b"observations", pool.as_ref()], &PROGRAM);
+    let mut account = TestAccount::new(observations_pda, PROGRAM, false, &vec![0u8; pinocchio_amm::Observations::LEN]);
+    pinocchio_amm::Observations::load_mut(account.data_mut())
+        .expect("Should load observations")
+        .set_inner(pool.to_bytes(), bump);
+    account
+}
+
+fn observation(timestamp: i64, price_a_cumulative: u128) -> pinocchio_amm::Observation {
+    pinocchio_amm::Observation {
+        timestamp,
+        price_a_cumulative: price_a_cumulative.to_le_bytes(),
+        price_b_cumulative: [0; 16],
+        liquidity: 0,
+    }
+}
+
+#[test]
+fn test_observations_state_size() {
+    use pinocchio_amm::{Observation, Observations};
+
+    assert_eq!(Observation::LEN, core::mem::size_of::<Observation>());
+    assert_eq!(Observations::LEN, core::mem::size_of::<Observations>());
+}
+
+#[test]
+fn test_observation_ring_buffer_grows_in_order() {
+    use pinocchio_amm::Observations;
+
+    let mut account = observations_account(&Pubkey::new_unique());
+    let observations = Observations::load_mut(account.data_mut()).expect("Should load observations");
+    assert_eq!((observations.cardinality, observations.cardinality_next), (0, 1));
+
+    // A single slot keeps only the latest reading, and one write per slot at most.
+    observations.write(observation(10, 1), 100);
+    observations.write(observation(11, 2), 100);
+    assert_eq!(observations.observations[0].timestamp, 10);
+    observations.write(observation(12, 3), 101);
+    assert_eq!((observations.index, observations.observations[0].timestamp), (0, 12));
+
+    // Growth applies once the index reaches the end of the current ring.
+    observations.grow(3).expect("Should grow");
+    observations.grow(2).expect("Lower targets are no-ops");
+    assert_eq!((observations.cardinality, observations.cardinality_next), (1, 3));
+    for (slot, timestamp) in [(102, 13), (103, 14), (104, 15)] {
+        observations.write(observation(timestamp, 0), slot);
+    }
+    assert_eq!(observations.cardinality, 3);
+    assert_eq!(observations.index, 0);
+    let timestamps: Vec<i64> = observations.observations[..3].iter().map(|o| o.timestamp).collect();
+    assert_eq!(timestamps, vec![15, 13, 14]);
+
+    assert_eq!(observations.grow(0), Err(ProgramError::InvalidArgument));
+    assert_eq!(observations.grow(Observations::MAX_CARDINALITY as u16 + 1), Err(ProgramError::InvalidArgument));
+}
+
+#[test]
+fn test_observe_interpolates_between_readings() {
+    use pinocchio_amm::{math::twap, Observations};
+
+    let mut pool_account = pool_account(&Pubkey::new_unique(), 30);
+    let pool_key = Pubkey::new_from_array(*pool_account.info().key());
+    let pool = Pool::load_mut(pool_account.data_mut()).expect("Should load pool");
+    let mut observations_account = observations_account(&pool_key);
+    let observations = Observations::load_mut(observations_account.data_mut()).expect("Should load observations");
+    observations.grow(4).expect("Should grow");
+
+    // Without readings, only times since the pool's last update can be answered.
+    pool.last_update_timestamp = 1_000;
+    assert_eq!(observations.observe(pool, 1, 1, 1_000, 0), Ok((0, 0)));
+    assert_eq!(observations.observe(pool, 1, 1, 1_000, 1), Err(PinocchioError::ObservationTooOld));
+
+    // 1 A = 2 B from t=1000, then 1 A = 4 B from t=1010; a swap is recorded at each step.
+    let one = 1u128 << 64;
+    let mut record = |pool: &mut Pool, reserve_a: u64, reserve_b: u64, now: i64, slot: u64| {
+        pool.update_price_accumulators(reserve_a, reserve_b, now).unwrap();
+        observations.write(pinocchio_amm::Observation::from_pool(pool, reserve_a, reserve_b), slot);
+    };
+    record(pool, 0, 0, 1_000, 1);
+    record(pool, 1_000, 2_000, 1_010, 2);
+    record(pool, 1_000, 4_000, 1_030, 3);
+    let observations = Observations::load(observations_account.data_mut()).expect("Should load observations");
+    assert_eq!(observations.observations[2].liquidity, 2_000);
+
+    // Exact hits, interpolation inside a window, and extension past the last update.
+    assert_eq!(observations.observe(pool, 1_000, 4_000, 1_040, 30).map(|c| c.0), Ok(20 * one));
+    assert_eq!(observations.observe(pool, 1_000, 4_000, 1_040, 35).map(|c| c.0), Ok(10 * one));
+    assert_eq!(observations.observe(pool, 1_000, 4_000, 1_040, 20).map(|c| c.0), Ok(60 * one));
+    assert_eq!(observations.observe(pool, 1_000, 4_000, 1_040, 0).map(|c| c.0), Ok(140 * one));
+    assert_eq!(observations.observe(pool, 1_000, 4_000, 1_040, 41), Err(PinocchioError::ObservationTooOld));
+
+    // The last 30 seconds averaged 1 A = 4 B.
+    let start = observations.observe(pool, 1_000, 4_000, 1_040, 30).unwrap().0;
+    let end = observations.observe(pool, 1_000, 4_000, 1_040, 0).unwrap().0;
+    assert_eq!(twap(start, end, 1_010, 1_040), Ok(4 * one));
+}
+
+#[test]
+fn test_increase_observation_cardinality() {
+    use pinocchio_amm::instructions::{
+        IncreaseObservationCardinality, IncreaseObservationCardinalityAccounts, IncreaseObservationCardinalityData,
+    };
+    use pinocchio_amm::Observations;
+
+    assert!(IncreaseObservationCardinalityData::try_from(&0u16.to_le_bytes()[..]).is_err());
+    assert!(IncreaseObservationCardinalityData::try_from(&129u16.to_le_bytes()[..]).is_err());
+    assert!(IncreaseObservationCardinalityData::try_from(&[1u8][..]).is_err());
+
+    let mut pool_account = pool_account(&Pubkey::new_unique(), 30);
+    let pool_key = Pubkey::new_from_array(*pool_account.info().key());
+    let (observations_pda, bump) = Pubkey::find_program_address(&[b"observations", pool_key.as_ref()], &PROGRAM);
+    let mut observations_account = TestAccount::new(observations_pda, PROGRAM, false, &vec![0u8; Observations::LEN]);
+    let mut payer_account = TestAccount::new(PAYER, solana_sdk::system_program::ID, true, &[]);
+
+    let payer = payer_account.info();
+    let pool = pool_account.info();
+    let observations = observations_account.info();
+
+    // A freshly created account gets its header before growing.
+    for (cardinality_next, observations_bump) in [(8u16, Some(bump)), (16, None)] {
+        IncreaseObservationCardinality {
+            accounts: IncreaseObservationCardinalityAccounts {
+                payer: &payer,
+                pool: &pool,
+                observations: &observations,
+                system_program: &payer,
+            },
+            data: IncreaseObservationCardinalityData::try_from(&cardinality_next.to_le_bytes()[..]).unwrap(),
+            observations_bump,
+        }.process().expect("Should increase cardinality");
+    }
+
+    let state = Observations::load(observations_account.data_mut()).expect("Should load observations");
+    assert_eq!(state.pool, pool_key.to_bytes());
+    assert_eq!(state.bump, bump);
+    assert_eq!((state.cardinality, state.cardinality_next), (0, 16));
+}
+
+#[test]
+fn test_observe_data_parsing() {
+    use pinocchio_amm::instructions::ObserveData;
+
+    let data: Vec<u8> = [600u32, 0].iter().flat_map(|s| s.to_le_bytes()).collect();
+    let parsed = ObserveData::try_from(&data[..]).expect("Should parse observe data");
+    assert_eq!(parsed.seconds_agos(), &[600, 0]);
+
+    assert!(ObserveData::try_from(&[][..]).is_err());
+    assert!(ObserveData::try_from(&data[..5]).is_err());
+    assert!(ObserveData::try_from(&[0u8; 4 * (ObserveData::MAX_POINTS + 1)][..]).is_err());
+}
+
+#[test]
+fn test_quote_swap_matches_execution() {
+    use pinocchio_amm::instructions::{QuoteSwapData, SwapData, SwapQuote};
+    use pinocchio_amm::math::get_amount_out;
+
+    let parsed = QuoteSwapData::try_from(&[[7u8; 8].as_slice(), &[1]].concat()[..]).expect("Should parse quote data");
+    assert_eq!((parsed.amount_in, parsed.swap_direction), (u64::from_le_bytes([7; 8]), true));
+    assert!(QuoteSwapData::try_from(&[0u8; 8][..]).is_err());
+
+    let mut fixture = PoolFixture::new(1_000_000, 4_000_000, 2_000_000);
+    fixture.pool_mut().protocol_fee_share = 2_000;
+
+    let quote = fixture.quote_swap(10_000, true).expect("Should quote");
+    let (amount_out, fee) = get_amount_out(10_000, 1_000_000, 4_000_000, 30).unwrap();
+    assert_eq!(quote, SwapQuote { amount_out, amount_received: amount_out, fee, protocol_fee: fee / 5, price_impact_bps: 98 });
+    assert_eq!(quote.to_bytes()[..8], amount_out.to_le_bytes());
+
+    // The quote is exactly what Swap delivers: one unit more of minimum output fails.
+    let swap = |min_amount_out| SwapData { amount_in: 10_000, min_amount_out, swap_direction: true, deadline: None };
+    assert_eq!(fixture.swap(swap(quote.amount_received + 1)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture.swap(swap(quote.amount_received)).expect("Swap should deliver the quote");
+
+    // Price impact grows with size: swapping the whole reserve halves the execution price.
+    assert!(fixture.quote_swap(100, false).unwrap().price_impact_bps < 10);
+    let whale = fixture.quote_swap(1_000_000, true).unwrap();
+    assert!((4_990..=5_000).contains(&whale.price_impact_bps), "{}", whale.price_impact_bps);
+
+    fixture.pool_mut().status = Pool::SWAP_DISABLED;
+    assert_eq!(fixture.quote_swap(10_000, true), Err(ProgramError::from(PinocchioError::SwapDisabled)));
+}
+
+#[test]
+fn test_quote_liquidity_matches_execution() {
+    use pinocchio_amm::instructions::{AddLiquidityData, AddLiquidityQuote, RemoveLiquidityData, RemoveLiquidityQuote};
+
+    let mut fixture = PoolFixture::new(1_000_000, 4_000_000, 2_000_000);
+
+    let deposit = fixture.quote_add_liquidity(10_000, 80_000).expect("Should quote");
+    assert_eq!(deposit, AddLiquidityQuote { lp_tokens: 20_000, amount_a: 10_000, amount_b: 40_000 });
+    let add = |min_lp_amount| AddLiquidityData { max_amount_a: 10_000, max_amount_b: 80_000, min_lp_amount, deadline: None };
+    assert_eq!(fixture.add_liquidity(add(deposit.lp_tokens + 1)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture.add_liquidity(add(deposit.lp_tokens)).expect("Deposit should mint the quote");
+
+    let withdrawal = fixture.quote_remove_liquidity(20_000).expect("Should quote");
+    assert_eq!(
+        withdrawal,
+        RemoveLiquidityQuote { amount_a: 10_000, amount_b: 40_000, amount_a_received: 10_000, amount_b_received: 40_000 }
+    );
+    let remove = |min_amount_a| RemoveLiquidityData { lp_tokens: 20_000, min_amount_a, min_amount_b: 40_000, deadline: None };
+    assert_eq!(fixture.remove_liquidity(remove(10_001)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture.remove_liquidity(remove(10_000)).expect("Withdrawal should pay the quote");
+
+    // Quotes fail the same way the instructions do.
+    assert_eq!(fixture.quote_remove_liquidity(2_000_000), Err(ProgramError::InvalidArgument));
+    fixture.pool_mut().status = Pool::ADD_LIQUIDITY_DISABLED | Pool::REMOVE_LIQUIDITY_DISABLED;
+    assert_eq!(fixture.quote_add_liquidity(1, 1), Err(ProgramError::from(PinocchioError::AddLiquidityDisabled)));
+    assert_eq!(fixture.quote_remove_liquidity(1), Err(ProgramError::from(PinocchioError::RemoveLiquidityDisabled)));
+}
+
+#[test]
+fn test_events_round_trip_through_decoder() {
+    use pinocchio_amm::events::*;
+
+    let events = [
+        Event::Swap(SwapEvent {
+            pool: [1; 32],
+            user: [2; 32],
+            a_to_b: true,
+            amount_in: 1_000,
+            amount_out: 990,
+            fee: 3,
+            protocol_fee: 1,
+        }),
+        Event::Deposit(DepositEvent { pool: [1; 32], user: [2; 32], amount_a: 5, amount_b: 0, lp_tokens: 7 }),
+        Event::Withdraw(WithdrawEvent { pool: [1; 32], user: [2; 32], amount_a: 0, amount_b: 9, lp_tokens: 4 }),
+        Event::PoolCreated(PoolCreatedEvent {
+            pool: [1; 32],
+            authority: [2; 32],
+            token_a_mint: [3; 32],
+            token_b_mint: [4; 32],
+            lp_mint: [5; 32],
+            fee_rate: 30,
+            curve: pinocchio_amm::Curve::Weighted { weight_a: 8_000, weight_b: 2_000 }.to_bytes(),
+        }),
+        Event::FeeChanged(FeeChangedEvent {
+            pool: [1; 32],
+            old_fee_rate: 30,
+            new_fee_rate: 25,
+            old_protocol_fee_share: 0,
+            new_protocol_fee_share: 0,
+        }),
+        Event::AmplificationRamp(AmplificationRampEvent {
+            pool: [1; 32],
+            start_amplification: 100,
+            target_amplification: 1_000,
+            start_timestamp: 1_700_000_000,
+            end_timestamp: 1_700_086_400,
+        }),
+        Event::AuthorityChanged(AuthorityChangedEvent {
+            pool: [1; 32],
+            old_authority: [2; 32],
+            new_authority: [2; 32],
+            old_pending_authority: [0; 32],
+            new_pending_authority: [3; 32],
+        }),
+        Event::PoolStatusChanged(PoolStatusChangedEvent { pool: [1; 32], old_status: 0, new_status: Pool::SWAP_DISABLED }),
+        Event::ProtocolFeesWithdrawn(ProtocolFeesWithdrawnEvent {
+            pool: [1; 32],
+            treasury_token_a: [2; 32],
+            treasury_token_b: [3; 32],
+            amount_a: 12,
+            amount_b: 0,
+        }),
+        Event::ObservationCardinalityChanged(ObservationCardinalityChangedEvent {
+            pool: [1; 32],
+            old_cardinality_next: 0,
+            new_cardinality_next: 8,
+        }),
+    ];
+
+    for event in events {
+        let bytes = event.to_bytes();
+        assert!(bytes.as_slice().len() <= MAX_EVENT_LEN);
+        assert_eq!(bytes.as_slice()[1], EVENT_VERSION);
+        assert_eq!(Event::decode(bytes.as_slice()), Ok(event));
+    }
+
+    let swap = events[0].to_bytes();
+    assert_eq!(&swap.as_slice()[..2], &EventKind::Swap.discriminator());
+    assert_eq!(swap.as_slice().len(), 2 + 32 + 32 + 1 + 4 * 8);
+    assert_eq!(events[7].to_bytes().as_slice().len(), 2 + 32 + 1 + 1);
+
+    // Pool creation carries the curve in `InitializePool`'s encoding.
+    let Event::PoolCreated(created) = events[3] else { unreachable!() };
+    assert_eq!(events[3].to_bytes().as_slice().len(), MAX_EVENT_LEN);
+    assert_eq!(
+        pinocchio_amm::Curve::parse(&created.curve),
+        Ok(pinocchio_amm::Curve::Weighted { weight_a: 8_000, weight_b: 2_000 })
+    );
+}
+
+#[test]
+fn test_event_decoder_rejects_malformed_data() {
+    use pinocchio_amm::events::*;
+
+    let deposit = DepositEvent { pool: [1; 32], user: [2; 32], amount_a: 5, amount_b: 6, lp_tokens: 7 }.to_bytes();
+    let bytes = deposit.as_slice();
+
+    assert_eq!(Event::decode(&[]), Err(EventDecodeError::InvalidLength));
+    assert_eq!(Event::decode(&bytes[..bytes.len() - 1]), Err(EventDecodeError::InvalidLength));
+
+    let mut trailing = bytes.to_vec();
+    trailing.push(0);
+    assert_eq!(Event::decode(&trailing), Err(EventDecodeError::InvalidLength));
+
+    let mut future_version = bytes.to_vec();
+    future_version[1] = EVENT_VERSION + 1;
+    assert_eq!(Event::decode(&future_version), Err(EventDecodeError::UnsupportedVersion(EVENT_VERSION + 1)));
+
+    let mut unknown_kind = bytes.to_vec();
+    unknown_kind[0] = 0xff;
+    assert_eq!(Event::decode(&unknown_kind), Err(EventDecodeError::UnknownKind(0xff)));
+}
+
+#[test]
+fn test_mul_div_uses_full_width_product() {
+    use pinocchio_amm::math::mul_div;
+
+    assert_eq!(mul_div(6, 7, 4), Some(10));
+    assert_eq!(mul_div(1, 1, 0), None);
+    assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX), Some(u128::MAX));
+    assert_eq!(mul_div(1 << 127, 4, 8), Some(1 << 126));
+    assert_eq!(mul_div(u128::MAX, 3, 2), None);
+
+    // Against the 128-bit path, with both factors pushed past 2^64.
+    let mut seed = 0x9e37_79b9_7f4a_7c15u128;
+    for _ in 0..500 {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let a = seed >> 40;
+        let b = (seed >> 70).max(1);
+        let denominator = (seed >> 64).max(1);
+        let shift = 24;
+        let expected = mul_div(a, b, denominator);
+        assert_eq!(mul_div(a << shift, b, denominator << shift), expected);
+        assert_eq!(mul_div(a << shift, b << shift, denominator << shift).map(|x| x >> shift), expected);
+    }
+}
+
+#[test]
+fn test_stable_swap_math() {
+    use pinocchio_amm::math::{get_amount_out, stable_get_amount_in, stable_get_amount_out, stable_invariant, stable_reserve};
+    use pinocchio_amm::Curve;
+
+    // A balanced pool's invariant is the sum of its reserves.
+    assert_eq!(stable_invariant(1_000_000, 1_000_000, 100), Ok(2_000_000));
+    assert_eq!(stable_invariant(0, 1_000_000, 100), Err(PinocchioError::InsufficientLiquidity));
+    let invariant = stable_invariant(1_000_000, 3_000_000, 100).unwrap();
+    assert!(invariant < 4_000_000 && invariant > 3_990_000, "{}", invariant);
+    let other = stable_reserve(1_000_000, invariant, 100).unwrap();
+    assert!(other.abs_diff(3_000_000) <= 1, "{}", other);
+
+    // Near the peg a stable pool trades almost 1:1, far better than constant product.
+    let (stable_out, fee) = stable_get_amount_out(100_000, 1_000_000, 1_000_000, 100, 4).unwrap();
+    let (product_out, _) = get_amount_out(100_000, 1_000_000, 1_000_000, 4).unwrap();
+    assert_eq!(fee, 40);
+    assert!(stable_out > 99_800 && stable_out < 99_960, "{}", stable_out);
+    assert!(stable_out > product_out + 8_000);
+
+    // Low amplification approaches constant product; higher is always flatter.
+    let (low_a, _) = stable_get_amount_out(100_000, 1_000_000, 1_000_000, 1, 0).unwrap();
+    let (high_a, _) = stable_get_amount_out(100_000, 1_000_000, 1_000_000, 1_000, 0).unwrap();
+    assert!(product_out < low_a && low_a < high_a);
+
+    // Swaps never shrink the invariant.
+    for (amount_in, reserve_in, reserve_out, amplification) in [
+        (1u64, 10u64, 10u64, 1u64),
+        (500_000, 1_000_000, 2_000_000, 50),
+        (5_000_000_000, 1_000_000_000, 1_000_000_000, 2_000),
+        (1 << 60, 1 << 62, 1 << 61, 1_000_000),
+    ] {
+        let before = stable_invariant(reserve_in, reserve_out, amplification).unwrap();
+        let (amount_out, _) = stable_get_amount_out(amount_in, reserve_in, reserve_out, amplification, 0).unwrap();
+        assert!(amount_out < reserve_out);
+        let after = stable_invariant(reserve_in + amount_in, reserve_out - amount_out, amplification).unwrap();
+        assert!(after >= before, "{} < {}", after, before);
+
+        // Exact-out charges enough to keep the invariant and at least covers the exact-in price.
+        let amount_in_needed = stable_get_amount_in(amount_out, reserve_in, reserve_out, amplification, 30).unwrap();
+        let (_, fee) = stable_get_amount_out(amount_in_needed, reserve_in, reserve_out, amplification, 30).unwrap();
+        let after = stable_invariant(reserve_in + amount_in_needed - fee, reserve_out - amount_out, amplification).unwrap();
+        assert!(after >= before, "{} < {}", after, before);
+    }
+    assert_eq!(stable_get_amount_in(1_000, 1_000, 1_000, 100, 30), Err(PinocchioError::InsufficientLiquidity));
+
+    // Reserves near the top of the u64 range still solve.
+    let large = u64::MAX / 2;
+    assert!(stable_get_amount_out(large / 2, large, large, 1_000_000, 30).is_ok());
+
+    // The marginal price is 1 at balance and favours the scarcer token off balance.
+    let curve = Curve::StableSwap { amplification: 100 };
+    assert_eq!(curve.q64_price(1_000_000, 1_000_000, true), Ok(1u128 << 64));
+    let price = curve.q64_price(1_000_000, 3_000_000, true).unwrap();
+    assert!(price > 1u128 << 64 && price < 3u128 << 64);
+    assert_eq!(Curve::ConstantProduct.q64_price(1_000_000, 3_000_000, true), Ok(pinocchio_amm::math::q64_price(1_000_000, 3_000_000)));
+}
+
+/// Swaps `amount_in` through a StableSwap pool and straight back, checking that
+/// neither leg lowers the invariant and that the round trip doesn't profit.
+/// Returns whether both legs priced.
+fn check_stable_round_trip(amount_in: u64, reserve_in: u64, reserve_out: u64, amplification: u64, fee_rate: u16) -> bool {
+    use pinocchio_amm::math::{stable_get_amount_in, stable_get_amount_out, stable_invariant};
+
+    let Ok(before) = stable_invariant(reserve_in, reserve_out, amplification) else { return false };
+    let Ok((amount_out, fee)) = stable_get_amount_out(amount_in, reserve_in, reserve_out, amplification, fee_rate) else { return false };
+    let (mid_in, mid_out) = (reserve_in + amount_in - fee, reserve_out - amount_out);
+    if mid_out == 0 {
+        return false;
+    }
+    let mid = stable_invariant(mid_in, mid_out, amplification).unwrap();
+    assert!(mid >= before, "invariant fell from {} to {} swapping {} into {}/{} at A = {}", before, mid, amount_in, reserve_in, reserve_out, amplification);
+
+    let Ok((returned, fee_back)) = stable_get_amount_out(amount_out, mid_out, mid_in, amplification, fee_rate) else { return false };
+    assert!(returned <= amount_in, "{} in, {} back from {}/{} at A = {}", amount_in, returned, reserve_in, reserve_out, amplification);
+    let after = stable_invariant(mid_in - returned, mid_out + amount_out - fee_back, amplification).unwrap();
+    assert!(after >= mid, "invariant fell from {} to {} on the way back", mid, after);
+
+    // Buying the first leg's output back out again costs at least what it paid.
+    if let Ok(amount_in_needed) = stable_get_amount_in(amount_out, reserve_in, reserve_out, amplification, fee_rate) {
+        let Ok((received, _)) = stable_get_amount_out(amount_in_needed, reserve_in, reserve_out, amplification, fee_rate) else { return true };
+        assert!(received >= amount_out, "exact-out for {} only buys {}", amount_out, received);
+    }
+    true
+}
+
+#[test]
+fn test_stable_swap_round_trips_never_profit() {
+    use pinocchio_amm::Curve;
+
+    // Cases that used to gain on the round trip or lower the invariant.
+    for amplification in [1, 10, 100, 2_000, 1_000_000] {
+        assert!(check_stable_round_trip(204_656_431, 8_267_903_587, 4_417_333_066, amplification, 0));
+    }
+    for amount_in in [1, 1_000, 1_479_819, 1_000_000_000, 11_555_885_159] {
+        assert!(check_stable_round_trip(amount_in, 11_555_885_159, 1_479_819, 1, 0));
+        assert!(check_stable_round_trip(amount_in, 1_479_819, 11_555_885_159, 1, 0));
+    }
+    for amount_in in [1, 6_400_000_000, 1 << 40, 1 << 60] {
+        assert!(check_stable_round_trip(amount_in, 3_050_000_000_000_000_000, 6_400_000_000, 1_000_000, 11));
+        assert!(check_stable_round_trip(amount_in, 6_400_000_000, 3_050_000_000_000_000_000, 1_000_000, 11));
+    }
+
+    // xorshift64* so the sample is deterministic without an extra dependency.
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut next = || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    };
+
+    let mut priced = 0;
+    for _ in 0..5_000 {
+        let reserve_in = (next() >> (next() % 62 + 2)).max(1);
+        let reserve_out = (next() >> (next() % 62 + 2)).max(1);
+        let amount_in = (next() >> (next() % 64)).min(u64::MAX - reserve_in).max(1);
+        let amplification = (next() >> (next() % 64)) % Curve::MAX_AMPLIFICATION + 1;
+        let fee_rate = [0, 1, 4, 30][(next() % 4) as usize];
+        priced += check_stable_round_trip(amount_in, reserve_in, reserve_out, amplification, fee_rate) as u32;
+    }
+    assert!(priced > 4_000, "only {} of the sampled round trips priced", priced);
+}
+
+#[test]
+fn test_curve_selection_at_pool_creation() {
+    use pinocchio_amm::instructions::InitializePoolData;
+    use pinocchio_amm::Curve;
+
+    let stable = Curve::StableSwap { amplification: 200 };
+    let data = [&30u16.to_le_bytes()[..], &[6], &stable.to_bytes()].concat();
+    assert_eq!(InitializePoolData::try_from(&data[..]).unwrap().curve, stable);
+
+    // No trailing curve keeps the constant-product layout.
+    assert_eq!(InitializePoolData::try_from(&[30, 0, 6][..]).unwrap().curve, Curve::ConstantProduct);
+
+    for (tag, parameter, error) in [
+        (Curve::STABLE_SWAP_TAG, 0, ProgramError::from(PinocchioError::InvalidAmplification)),
+        (Curve::STABLE_SWAP_TAG, Curve::MAX_AMPLIFICATION + 1, ProgramError::from(PinocchioError::InvalidAmplification)),
+        (Curve::CONSTANT_PRODUCT_TAG, 5, ProgramError::from(PinocchioError::InvalidPoolState)),
+        (7, 0, ProgramError::from(PinocchioError::InvalidPoolState)),
+    ] {
+        let curve = [&[tag][..], &parameter.to_le_bytes()].concat();
+        assert_eq!(Curve::parse(&curve), Err(error));
+    }
+    assert_eq!(Curve::parse(&[1, 0]), Err(ProgramError::InvalidInstructionData));
+
+    let mut pool = pool_account(&PAYER, 30);
+    let pool = Pool::load_mut(pool.data_mut()).unwrap();
+    assert_eq!(pool.curve_at(0), Ok(Curve::ConstantProduct));
+    pool.set_curve(stable);
+    assert_eq!((pool.curve_at(0), pool.curve_type, pool.amplification), (Ok(stable), Curve::STABLE_SWAP_TAG, 200));
+}
+
+#[test]
+fn test_stable_pool_swaps_and_deposits() {
+    use pinocchio_amm::instructions::{AddLiquidityData, DepositSingleData, SwapData};
+    use pinocchio_amm::math::{stable_deposit, stable_get_amount_out};
+    use pinocchio_amm::Curve;
+
+    let mut fixture = PoolFixture::new(10_000_000, 10_000_000, 10_000_000);
+    fixture.pool_mut().set_curve(Curve::StableSwap { amplification: 100 });
+
+    // Swaps and their quotes run on the stable curve.
+    let quote = fixture.quote_swap(1_000_000, true).unwrap();
+    let (amount_out, _) = stable_get_amount_out(1_000_000, 10_000_000, 10_000_000, 100, 30).unwrap();
+    assert_eq!(quote.amount_out, amount_out);
+    assert!(quote.price_impact_bps < 10, "{}", quote.price_impact_bps);
+    let swap = |min_amount_out| SwapData { amount_in: 1_000_000, min_amount_out, swap_direction: true, deadline: None };
+    assert_eq!(fixture.swap(swap(amount_out + 1)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture.swap(swap(amount_out)).expect("Stable swap should succeed");
+
+    // A balanced deposit mints its proportional share, minus at most rounding.
+    let balanced = fixture.quote_add_liquidity(1_000_000, 1_000_000).unwrap();
+    assert_eq!((balanced.amount_a, balanced.amount_b), (1_000_000, 1_000_000));
+    assert!(balanced.lp_tokens.abs_diff(1_000_000) <= 1, "{}", balanced.lp_tokens);
+
+    // Later deposits are matched to the reserve ratio; the surplus stays with the depositor.
+    let skewed = fixture.quote_add_liquidity(3_000_000, 1_000_000).unwrap();
+    assert_eq!((skewed.lp_tokens, skewed.amount_a, skewed.amount_b), (1_000_000, 1_000_000, 1_000_000));
+    assert_eq!(fixture.quote_add_liquidity(2_000_000, 0), Err(ProgramError::from(PinocchioError::InvalidAmount)));
+    fixture.add_liquidity(AddLiquidityData {
+        max_amount_a: 3_000_000,
+        max_amount_b: 1_000_000,
+        min_lp_amount: skewed.lp_tokens,
+        deadline: None,
+    }).expect("Skewed stable deposit should succeed");
+
+    // One-sided deposits go through DepositSingle and pay the imbalance fee.
+    let one_sided = stable_deposit(2_000_000, 0, 10_000_000, 10_000_000, 10_000_000, 100, 30).unwrap();
+    let fee_free = stable_deposit(2_000_000, 0, 10_000_000, 10_000_000, 10_000_000, 100, 0).unwrap();
+    assert!(one_sided < fee_free && fee_free < 2 * balanced.lp_tokens);
+
+    let deposit = |min_lp_amount| DepositSingleData { amount_in: 2_000_000, min_lp_amount, deposit_a: true, deadline: None };
+    assert_eq!(fixture.deposit_single(deposit(one_sided + 1)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture.deposit_single(deposit(one_sided)).expect("Stable zap should succeed");
+}
+
+#[test]
+fn test_amplification_ramp() {
+    use pinocchio_amm::Curve;
+
+    let day = Pool::MIN_RAMP_DURATION;
+    let mut account = pool_account(&PAYER, 30);
+    let pool = Pool::load_mut(account.data_mut()).unwrap();
+
+    // Only StableSwap pools have an amplification to ramp.
+    assert_eq!(pool.start_ramp(200, 2 * day, 0), Err(PinocchioError::InvalidPoolState));
+    assert_eq!(pool.stop_ramp(0), Err(PinocchioError::InvalidPoolState));
+
+    pool.set_curve(Curve::StableSwap { amplification: 100 });
+    let start = 10 * day;
+
+    // Too short, too large a change either way, or out of range.
+    assert_eq!(pool.start_ramp(200, start + day - 1, start), Err(PinocchioError::InvalidRamp));
+    assert_eq!(pool.start_ramp(1_001, start + day, start), Err(PinocchioError::InvalidAmplification));
+    assert_eq!(pool.start_ramp(9, start + day, start), Err(PinocchioError::InvalidAmplification));
+    assert_eq!(pool.start_ramp(0, start + day, start), Err(PinocchioError::InvalidAmplification));
+
+    // A moves linearly between the endpoints and holds at the target afterwards.
+    pool.start_ramp(1_000, start + 2 * day, start).expect("Should start ramp");
+    assert_eq!(pool.curve_at(start - 1), Ok(Curve::StableSwap { amplification: 100 }));
+    assert_eq!(pool.amplification_at(start), 100);
+    assert_eq!(pool.amplification_at(start + day / 2), 325);
+    assert_eq!(pool.amplification_at(start + day), 550);
+    assert_eq!(pool.amplification_at(start + 2 * day), 1_000);
+    assert_eq!(pool.amplification_at(i64::MAX), 1_000);
+
+    // A new ramp can't start within a day of the last one, and starts from the current A.
+    assert_eq!(pool.start_ramp(100, start + 3 * day, start + day - 1), Err(PinocchioError::InvalidRamp));
+    pool.start_ramp(100, start + 3 * day, start + day).expect("Should ramp back down");
+    assert_eq!(pool.amplification_at(start + 2 * day), 325);
+
+    // Stopping freezes A where it is.
+    pool.stop_ramp(start + 2 * day).expect("Should stop ramp");
+    assert_eq!(pool.amplification_at(start + 2 * day), 325);
+    assert_eq!(pool.curve_at(i64::MAX), Ok(Curve::StableSwap { amplification: 325 }));
+
+    // The oracle prices with the amplification in effect when it updates.
+    let flat = Curve::StableSwap { amplification: 325 }.q64_price(1_000_000, 3_000_000, true).unwrap();
+    pool.last_update_timestamp = start + 2 * day;
+    let (price_a_cumulative, _) = pool.cumulative_prices_at(1_000_000, 3_000_000, start + 2 * day + 10).unwrap();
+    assert_eq!(price_a_cumulative, flat * 10);
+}
+
+#[test]
+fn test_ramp_instructions_require_authority() {
+    use pinocchio_amm::instructions::{RampAmplification, RampAmplificationAccounts, RampAmplificationData, StopRamp};
+
+    let data = [1_000u64.to_le_bytes(), 1_000_000i64.to_le_bytes()].concat();
+    let parsed = RampAmplificationData::try_from(&data[..]).expect("Should parse ramp data");
+    assert_eq!((parsed.target_amplification, parsed.ramp_end_timestamp), (1_000, 1_000_000));
+    assert!(RampAmplificationData::try_from(&data[..15]).is_err());
+
+    let mut pool = pool_account(&PAYER, 30);
+    let mut intruder = TestAccount::new(Pubkey::new_unique(), solana_sdk::system_program::ID, true, &[]);
+    let (pool_info, intruder_info) = (pool.info(), intruder.info());
+    let accounts = || RampAmplificationAccounts { authority: &intruder_info, pool: &pool_info };
+
+    let result = RampAmplification { accounts: accounts(), data: RampAmplificationData { target_amplification: 1_000, ramp_end_timestamp: 1_000_000 } }.process_at(&test_clock());
+    assert_eq!(result, Err(ProgramError::from(PinocchioError::Unauthorized)));
+    assert_eq!(StopRamp { accounts: accounts() }.process_at(&test_clock()), Err(ProgramError::from(PinocchioError::Unauthorized)));
+}
+
+#[test]
+fn test_ramp_instructions_run_at_the_clock() {
+    use pinocchio_amm::instructions::{RampAmplification, RampAmplificationAccounts, RampAmplificationData, StopRamp};
+    use pinocchio_amm::Curve;
+
+    let day = Pool::MIN_RAMP_DURATION;
+    let mut pool = pool_account(&PAYER, 30);
+    Pool::load_mut(pool.data_mut()).unwrap().set_curve(Curve::StableSwap { amplification: 100 });
+    let mut authority = TestAccount::new(PAYER, solana_sdk::system_program::ID, true, &[]);
+    let (pool_info, authority_info) = (pool.info(), authority.info());
+    let accounts = || RampAmplificationAccounts { authority: &authority_info, pool: &pool_info };
+
+    let mut clock = test_clock();
+    let start = clock.unix_timestamp;
+    RampAmplification { accounts: accounts(), data: RampAmplificationData { target_amplification: 1_000, ramp_end_timestamp: start + 2 * day } }
+        .process_at(&clock)
+        .expect("Should start ramp");
+
+    clock.unix_timestamp += day;
+    StopRamp { accounts: accounts() }.process_at(&clock).expect("Should stop ramp");
+
+    let pool = Pool::load_mut(pool.data_mut()).unwrap();
+    assert_eq!(pool.ramp_start_timestamp, start + day);
+    assert_eq!(pool.curve_at(i64::MAX), Ok(Curve::StableSwap { amplification: 550 }));
+}
+
+#[test]
+fn test_swaps_accrue_price_accumulators_at_the_clock() {
+    use pinocchio_amm::instructions::SwapData;
+
+    let mut fixture = PoolFixture::new(1_000_000, 2_000_000, 1_000_000);
+    let swap = || SwapData { amount_in: 10_000, min_amount_out: 1, swap_direction: true, deadline: None };
+
+    fixture.swap(swap()).expect("Should swap");
+    let start = fixture.clock.unix_timestamp;
+    assert_eq!(fixture.pool_mut().last_update_timestamp, start);
+
+    // The next swap accrues the price over the time since, at the reserves it finds.
+    fixture.clock.unix_timestamp += 10;
+    let now = fixture.clock.unix_timestamp;
+    let pool = fixture.pool_mut();
+    let (reserve_a, reserve_b) = pool.pricing_reserves(1_000_000, 2_000_000).unwrap();
+    let expected = pool.cumulative_prices_at(reserve_a, reserve_b, now).unwrap();
+    assert!(expected.0 > pool.price_a_cumulative());
+
+    fixture.swap(swap()).expect("Should swap");
+    let pool = fixture.pool_mut();
+    assert_eq!((pool.price_a_cumulative(), pool.price_b_cumulative()), expected);
+    assert_eq!(pool.last_update_timestamp, now);
+}
+
+#[test]
+fn test_fixed_point_log_and_exp() {
+    use pinocchio_amm::math::{exp_q64, ln_q64, pow_q64, POW_PRECISION_BITS};
+
+    let one = 1u128 << 64;
+    let to_f64 = |value: u128| value as f64 / one as f64;
+    let close = |value: u128, expected: f64| {
+        let error = (to_f64(value) - expected).abs() / expected;
+        assert!(error < (-(POW_PRECISION_BITS as f64)).exp2(), "{} vs {}", to_f64(value), expected);
+    };
+
+    assert_eq!(ln_q64(one), Ok(0));
+    assert_eq!(ln_q64(0), Err(PinocchioError::MathOverflow));
+    assert!((ln_q64(2 * one).unwrap() as f64 / one as f64 - core::f64::consts::LN_2).abs() < 1e-15);
+    assert!((ln_q64(one / 10).unwrap() as f64 / one as f64 - 0.1f64.ln()).abs() < 1e-15);
+    assert_eq!(exp_q64(0), Ok(one));
+    assert_eq!(exp_q64(-200 * one as i128), Ok(0));
+    assert_eq!(exp_q64(45 * one as i128), Err(PinocchioError::MathOverflow));
+
+    for x in [one / 1_000, one / 3, one + 1, 7 * one / 5, 1_000_000 * one, u128::MAX] {
+        close(exp_q64(ln_q64(x).unwrap()).unwrap(), to_f64(x));
+    }
+
+    // Equal weights pass the base through untouched; other exponents stay within the bound.
+    assert_eq!(pow_q64(one / 3, 5_000, 5_000), Ok(one / 3));
+    close(pow_q64(4 * one, 1, 2).unwrap(), 2.0);
+    for (base, numerator, denominator) in [(one / 2, 8_000u128, 2_000u128), (3 * one / 4, 2_000, 8_000), (one + one / 7, 9_900, 100)] {
+        close(pow_q64(base, numerator, denominator).unwrap(), to_f64(base).powf(numerator as f64 / denominator as f64));
+    }
+}
+
+#[test]
+fn test_weighted_math() {
+    use pinocchio_amm::math::{
+        get_amount_out, weighted_deposit, weighted_get_amount_in, weighted_get_amount_out, weighted_invariant,
+        weighted_spot_amount_out,
+    };
+    use pinocchio_amm::Curve;
+
+    // Equal weights reduce to constant product.
+    assert!(weighted_invariant(1_000_000, 4_000_000, 5_000, 5_000).unwrap().abs_diff(2_000_000) <= 1);
+    let (product_out, _) = get_amount_out(100_000, 1_000_000, 2_000_000, 30).unwrap();
+    let (weighted_out, fee) = weighted_get_amount_out(100_000, 1_000_000, 2_000_000, 5_000, 5_000, 30).unwrap();
+    assert_eq!(fee, 300);
+    assert!(weighted_out <= product_out && product_out - weighted_out <= 1);
+
+    // An 80/20 pool holding four times as much A by value prices A at 1:1 with B...
+    let (reserve_a, reserve_b) = (4_000_000u64, 1_000_000u64);
+    assert_eq!(weighted_spot_amount_out(1_000, reserve_a, reserve_b, 8_000, 2_000), Ok(1_000));
+    assert_eq!(weighted_spot_amount_out(1_000, reserve_b, reserve_a, 2_000, 8_000), Ok(1_000));
+    assert_eq!(weighted_invariant(0, reserve_b, 8_000, 2_000), Err(PinocchioError::InsufficientLiquidity));
+    let invariant = weighted_invariant(reserve_a, reserve_b, 8_000, 2_000).unwrap();
+    assert!(invariant.abs_diff(3_031_433) <= 1, "{}", invariant);
+
+    // ...and follows `out = reserve_out * (1 - (reserve_in / (reserve_in + in))^(w_in / w_out))`.
+    let (a_for_b, _) = weighted_get_amount_out(400_000, reserve_a, reserve_b, 8_000, 2_000, 0).unwrap();
+    let expected = reserve_b as f64 * (1.0 - (4.0f64 / 4.4).powi(4));
+    assert!(a_for_b as f64 <= expected && a_for_b as f64 > expected - 2.0, "{}", a_for_b);
+    let (b_for_a, _) = weighted_get_amount_out(100_000, reserve_b, reserve_a, 2_000, 8_000, 0).unwrap();
+    let expected = reserve_a as f64 * (1.0 - (1.0f64 / 1.1).powf(0.25));
+    assert!(b_for_a as f64 <= expected && b_for_a as f64 > expected - 2.0, "{}", b_for_a);
+
+    // Exact-out charges at least what the exact-in direction needs.
+    for (amount_out, weight_in, weight_out) in [(1u64, 8_000u16, 2_000u16), (300_000, 8_000, 2_000), (900_000, 2_000, 8_000), (50_000, 100, 9_900)] {
+        let (reserve_in, reserve_out) = if weight_in == 2_000 { (reserve_b, reserve_a) } else { (reserve_a, reserve_b) };
+        let amount_in = weighted_get_amount_in(amount_out, reserve_in, reserve_out, weight_in, weight_out, 30).unwrap();
+        let (received, _) = weighted_get_amount_out(amount_in, reserve_in, reserve_out, weight_in, weight_out, 30).unwrap();
+        assert!(received >= amount_out, "{} < {}", received, amount_out);
+    }
+    assert_eq!(weighted_get_amount_in(reserve_b, reserve_a, reserve_b, 8_000, 2_000, 30), Err(PinocchioError::InsufficientLiquidity));
+
+    // Deposits in the pool's ratio mint their share of LP, less rounding.
+    let lp_supply = 3_000_000;
+    let proportional = weighted_deposit(400_000, 100_000, reserve_a, reserve_b, lp_supply, 8_000, 2_000, 30).unwrap();
+    assert!(proportional <= 300_000 && proportional + 2 >= 300_000, "{}", proportional);
+
+    // One-sided deposits follow Balancer's `supply * ((1 + in / reserve)^w - 1)`, less
+    // the swap fee on the part that doesn't match the pool's ratio.
+    let fee_free = weighted_deposit(400_000, 0, reserve_a, reserve_b, lp_supply, 8_000, 2_000, 0).unwrap();
+    let expected = lp_supply as f64 * (1.1f64.powf(0.8) - 1.0);
+    assert!(fee_free as f64 <= expected && fee_free as f64 > expected - 2.0, "{}", fee_free);
+    let charged = weighted_deposit(400_000, 0, reserve_a, reserve_b, lp_supply, 8_000, 2_000, 30).unwrap();
+    assert!(charged < fee_free);
+    assert!(fee_free - charged < fee_free * 30 / 10_000);
+
+    // The oracle reads the weighted spot price.
+    let curve = Curve::Weighted { weight_a: 8_000, weight_b: 2_000 };
+    assert_eq!(curve.q64_price(reserve_a, reserve_b, true), Ok(1u128 << 64));
+    assert_eq!(curve.q64_price(reserve_b, reserve_a, false), Ok(1u128 << 64));
+    assert_eq!(curve.q64_price(reserve_a, reserve_a, true), Ok(4u128 << 64));
+}
+
+#[test]
+fn test_weighted_dust_deposit_into_deep_pool() {
+    use pinocchio_amm::math::weighted_deposit;
+
+    // A one-unit deposit barely registers against these reserves, so rounding can
+    // put the weighted-average growth below one. It must mint nothing, not underflow.
+    for (reserve_a, reserve_b) in [(u64::MAX, u64::MAX), (u64::MAX - 1, u64::MAX / 4), (1_000_000_000_000_000_000, u64::MAX)] {
+        for (weight_a, weight_b) in [(8_000, 2_000), (2_000, 8_000), (100, 9_900)] {
+            for (amount_a, amount_b) in [(1, 0), (0, 1), (1, 1)] {
+                let lp = weighted_deposit(amount_a, amount_b, reserve_a, reserve_b, 1_000_000_000_000_000_000, weight_a, weight_b, 30);
+                assert_eq!(lp, Ok(0), "{} {} into {} {} at {}/{}", amount_a, amount_b, reserve_a, reserve_b, weight_a, weight_b);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_weighted_pool_creation() {
+    use pinocchio_amm::instructions::InitializePoolData;
+    use pinocchio_amm::Curve;
+
+    let weighted = Curve::weighted(8_000, 2_000).unwrap();
+    assert_eq!(weighted, Curve::Weighted { weight_a: 8_000, weight_b: 2_000 });
+    let data = [&30u16.to_le_bytes()[..], &[6], &weighted.to_bytes()].concat();
+    assert_eq!(InitializePoolData::try_from(&data[..]).unwrap().curve, weighted);
+
+    for (weight_a, weight_b) in [(0, 10_000), (99, 9_901), (8_000, 1_000), (u16::MAX, 2_000)] {
+        assert_eq!(Curve::weighted(weight_a, weight_b), Err(PinocchioError::InvalidWeights));
+    }
+    for parameter in [0u64, 10_000, 9_901, 1 << 16] {
+        let curve = [&[Curve::WEIGHTED_TAG][..], &parameter.to_le_bytes()].concat();
+        assert_eq!(Curve::parse(&curve), Err(ProgramError::from(PinocchioError::InvalidWeights)));
+    }
+
+    // Weights live in the pool; amplification stays unused and can't be ramped.
+    let mut account = pool_account(&PAYER, 30);
+    let pool = Pool::load_mut(account.data_mut()).unwrap();
+    pool.set_curve(weighted);
+    assert_eq!((pool.curve_type, pool.weight_a, pool.weight_b, pool.amplification), (Curve::WEIGHTED_TAG, 8_000, 2_000, 0));
+    assert_eq!(pool.curve_at(0), Ok(weighted));
+    assert_eq!(pool.start_ramp(200, 2 * Pool::MIN_RAMP_DURATION, 0), Err(PinocchioError::InvalidPoolState));
+
+    pool.set_curve(Curve::StableSwap { amplification: 100 });
+    assert_eq!((pool.weight_a, pool.weight_b), (0, 0));
+}
+
+#[test]
+fn test_weighted_pool_swaps_and_deposits() {
+    use pinocchio_amm::instructions::{AddLiquidityData, DepositSingleData, SwapData, SwapExactOutData, WithdrawSingleData};
+    use pinocchio_amm::math::{weighted_deposit, weighted_get_amount_out, weighted_invariant};
+    use pinocchio_amm::Curve;
+
+    let weighted = Curve::Weighted { weight_a: 8_000, weight_b: 2_000 };
+
+    // The first deposit mints the weighted geometric mean of the reserves.
+    let mut fixture = PoolFixture::new(0, 0, 0);
+    fixture.pool_mut().set_curve(weighted);
+    let first = fixture.quote_add_liquidity(4_000_000, 1_000_000).unwrap();
+    let invariant = weighted_invariant(4_000_000, 1_000_000, 8_000, 2_000).unwrap() as u64;
+    assert_eq!(first.lp_tokens, invariant - Pool::MINIMUM_LIQUIDITY);
+
+    let mut fixture = PoolFixture::new(4_000_000, 1_000_000, 3_000_000);
+    fixture.pool_mut().set_curve(weighted);
+
+    // Swaps in each direction use that direction's weights.
+    for (amount_in, a_to_b) in [(100_000, true), (100_000, false)] {
+        let quote = fixture.quote_swap(amount_in, a_to_b).unwrap();
+        let (reserve_in, reserve_out, weight_in, weight_out) = if a_to_b {
+            (4_000_000, 1_000_000, 8_000, 2_000)
+        } else {
+            (1_000_000, 4_000_000, 2_000, 8_000)
+        };
+        let (amount_out, _) = weighted_get_amount_out(amount_in, reserve_in, reserve_out, weight_in, weight_out, 30).unwrap();
+        assert_eq!(quote.amount_out, amount_out);
+        assert!(quote.price_impact_bps > 0 && quote.price_impact_bps < 2_000, "{}", quote.price_impact_bps);
+
+        let swap = |min_amount_out| SwapData { amount_in, min_amount_out, swap_direction: a_to_b, deadline: None };
+        assert_eq!(fixture.swap(swap(amount_out + 1)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+        fixture.swap(swap(amount_out)).expect("Weighted swap should succeed");
+    }
+
+    fixture.swap_exact_out(SwapExactOutData { amount_out: 50_000, max_amount_in: 60_000, swap_direction: true, deadline: None })
+        .expect("Weighted exact-out swap should succeed");
+
+    // Later deposits are matched to the reserve ratio. At the 80/20 pool's spot
+    // price that splits the deposit's value by weight: 400k A is worth 4x 100k B.
+    let skewed = fixture.quote_add_liquidity(400_000, 400_000).unwrap();
+    assert_eq!((skewed.lp_tokens, skewed.amount_a, skewed.amount_b), (300_000, 400_000, 100_000));
+    assert_eq!(fixture.quote_add_liquidity(400_000, 0), Err(ProgramError::from(PinocchioError::InvalidAmount)));
+    fixture.add_liquidity(AddLiquidityData { max_amount_a: 400_000, max_amount_b: 400_000, min_lp_amount: 300_000, deadline: None })
+        .expect("Skewed weighted deposit should succeed");
+
+    // One-sided deposits go through DepositSingle and pay the swap fee on the share that would be swapped.
+    let expected = weighted_deposit(400_000, 0, 4_000_000, 1_000_000, 3_000_000, 8_000, 2_000, 30).unwrap();
+    assert!(expected < 300_000);
+
+    let deposit = |min_lp_amount| DepositSingleData { amount_in: 400_000, min_lp_amount, deposit_a: true, deadline: None };
+    assert_eq!(fixture.deposit_single(deposit(expected + 1)), Err(ProgramError::from(PinocchioError::SlippageExceeded)));
+    fixture.deposit_single(deposit(expected)).expect("Weighted zap should succeed");
+
+    fixture.withdraw_single(WithdrawSingleData { lp_tokens: 30_000, min_amount_out: 1, withdraw_a: false, deadline: None })
+        .expect("Weighted single-sided withdrawal should succeed");
+}